@@ -12,7 +12,6 @@ const WARMUP_INCREMENT_COUNT: usize = 20;
 
 fn execute(
     state: &mut DiffState,
-    unit: LatencyUnit,
     mut f: impl FnMut(),
     exec_count: usize,
     pre_exec: impl Fn(),
@@ -21,21 +20,20 @@ fn execute(
     pre_exec();
     for _ in 1..=exec_count {
         let latency = latency(&mut f);
-        let elapsed = unit.latency_as_u64(latency);
-        state.capture_data(elapsed, 1);
+        let elapsed = LatencyUnit::Nano.latency_as_u64(latency);
+        state.capture_data(elapsed, 1).expect("latency must be > 0");
         exec_status();
     }
 }
 
 fn warm_up(
     state: &mut DiffState,
-    unit: LatencyUnit,
     mut f: impl FnMut(),
     mut warm_up_status: impl FnMut(usize, u64, u64),
 ) {
     let start = Instant::now();
     for i in 1.. {
-        execute(state, unit, &mut f, WARMUP_INCREMENT_COUNT, || {}, || {});
+        execute(state, &mut f, WARMUP_INCREMENT_COUNT, || {}, || {});
         let elapsed = Instant::now().duration_since(start);
         warm_up_status(i, elapsed.as_millis() as u64, WARMUP_MILLIS);
         if elapsed.ge(&Duration::from_millis(WARMUP_MILLIS)) {
@@ -86,10 +84,11 @@ pub fn bench_naive(unit: LatencyUnit, mut f: impl FnMut(), exec_count: usize) ->
 
     let mut out = DiffOut::new();
     let mut state = DiffState::new(&mut out);
-    warm_up(&mut state, unit, &mut f, &mut warm_up_status);
+    warm_up(&mut state, &mut f, &mut warm_up_status);
     state.reset();
 
-    execute(&mut state, unit, &mut f, exec_count, pre_exec, exec_status);
+    execute(&mut state, &mut f, exec_count, pre_exec, exec_status);
+    out.set_unit(unit);
     out
 }
 
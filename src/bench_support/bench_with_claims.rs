@@ -30,6 +30,11 @@ fn print_diff_out(out: &DiffOut) {
     );
     println!();
     println!("welch_ratio_ci={:?}", out.welch_ratio_ci(ALPHA),);
+    println!(
+        "robust_welch_ratio_ci={:?}",
+        out.robust_welch_ratio_ci(ALPHA)
+    );
+    println!("outliers={:?}", out.outliers());
     println!(
         "welch_ln_test_lt:{:?}",
         out.welch_ln_test(AltHyp::Lt, ALPHA)
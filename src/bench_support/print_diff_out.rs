@@ -18,6 +18,10 @@ pub fn comprehensive_print_diff_out(out: &DiffOut) {
     );
     println!();
     println!("welch_ratio_ci={:?}", out.welch_ratio_ci(ALPHA),);
+    println!(
+        "robust_welch_ratio_ci={:?}",
+        out.robust_welch_ratio_ci(ALPHA)
+    );
     println!(
         "welch_ln_test_lt:{:?}",
         out.welch_ln_test(AltHyp::Lt, ALPHA)
@@ -49,4 +53,19 @@ pub fn comprehensive_print_diff_out(out: &DiffOut) {
         out.count_f1_gt_f2()
     );
     println!();
+    let outliers = out.outliers();
+    println!("outliers_f1={:?}", outliers.f1);
+    println!("outliers_f2={:?}", outliers.f2);
+    println!("outliers_diff_ln_f1_f2={:?}", outliers.diff_ln_f1_f2);
+    println!();
+    if let Some(allocs) = out.allocations() {
+        println!("allocations_f1={:?}", allocs.f1);
+        println!("allocations_f2={:?}", allocs.f2);
+        println!(
+            "mean_diff_allocations_f1_f2={}, mean_diff_bytes_f1_f2={}",
+            allocs.mean_diff_allocations_f1_f2(),
+            allocs.mean_diff_bytes_f1_f2()
+        );
+        println!();
+    }
 }
@@ -1,3 +1,4 @@
+use crate::ClockKind;
 use std::time::{Duration, Instant};
 
 /// Invokes `f` once and returns its latency.
@@ -7,3 +8,20 @@ pub fn latency(f: impl FnOnce()) -> Duration {
     f();
     Instant::now().duration_since(start)
 }
+
+/// Invokes `f` once with `input` and returns its latency as measured by `clock`. The construction of `input` is
+/// not included in the latency, but its consumption by `f` (including any drop at the end of `f`) is.
+#[inline(always)]
+pub fn latency_with_input<I>(clock: ClockKind, input: I, f: impl FnOnce(I)) -> Duration {
+    clock.latency(|| f(input))
+}
+
+/// Invokes `f` `batch_size` times in a row and returns the total latency of the batch as measured by `clock`.
+#[inline(always)]
+pub fn latency_batch(clock: ClockKind, batch_size: u32, mut f: impl FnMut()) -> Duration {
+    clock.latency(|| {
+        for _ in 0..batch_size {
+            f();
+        }
+    })
+}
@@ -0,0 +1,353 @@
+//! Module implementing the attribution of heap allocations to the target functions of a benchmark (see
+//! [`CountingAllocator`] and [`crate::BenchDiff::track_allocations`]).
+
+use super::{DuoFlow, Phase, TargetFn};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    ops::ControlFlow,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+/// Global allocator wrapper that counts the allocations of each thread, which allows benchmarks to attribute
+/// heap allocations to the functions being compared (see [`crate::BenchDiff::track_allocations`]).
+///
+/// It must be installed as the global allocator of the benchmark executable:
+/// ```
+/// use bench_diff::CountingAllocator;
+/// use std::alloc::System;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator::new(System);
+/// ```
+///
+/// Besides delegating to the wrapped allocator, each operation only updates a few thread-local counters.
+/// A reallocation counts as an allocation of the new size.
+pub struct CountingAllocator<A = System> {
+    inner: A,
+}
+
+impl<A> CountingAllocator<A> {
+    /// Wraps `inner`, which performs the actual allocations.
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+/// Whether a [`CountingAllocator`] has performed an allocation, i.e., whether it is installed.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Whether a [`CountingAllocator`] is installed as the global allocator. Allocations can only be tracked if it
+/// is.
+pub fn counting_allocator_installed() -> bool {
+    INSTALLED.load(Ordering::Relaxed)
+}
+
+/// Allocation counters of a thread. Live bytes can be negative because memory allocated by another thread can be
+/// deallocated by this one.
+struct ThreadCounters {
+    allocations: Cell<u64>,
+    bytes: Cell<u64>,
+    live_bytes: Cell<i64>,
+    peak_live_bytes: Cell<i64>,
+}
+
+thread_local! {
+    // Const initialization and the absence of a destructor ensure that accessing the counters never allocates.
+    static COUNTERS: ThreadCounters = const {
+        ThreadCounters {
+            allocations: Cell::new(0),
+            bytes: Cell::new(0),
+            live_bytes: Cell::new(0),
+            peak_live_bytes: Cell::new(0),
+        }
+    };
+}
+
+/// Updates the counters of the current thread with an allocation of `size` bytes, if any, and a change of
+/// `live_delta` bytes in the live bytes.
+#[inline(always)]
+fn count(size: Option<usize>, live_delta: i64) {
+    // The counters are not available while the thread is being torn down, in which case nothing is measured.
+    let _ = COUNTERS.try_with(|c| {
+        if let Some(size) = size {
+            c.allocations.set(c.allocations.get() + 1);
+            c.bytes.set(c.bytes.get() + size as u64);
+        }
+        let live = c.live_bytes.get() + live_delta;
+        c.live_bytes.set(live);
+        if live > c.peak_live_bytes.get() {
+            c.peak_live_bytes.set(live);
+        }
+    });
+}
+
+/// Marks the allocator as installed the first time it allocates.
+#[inline(always)]
+fn mark_installed() {
+    if !INSTALLED.load(Ordering::Relaxed) {
+        INSTALLED.store(true, Ordering::Relaxed);
+    }
+}
+
+// SAFETY: all operations are delegated to `inner`; the counters don't affect the memory returned.
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        mark_installed();
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::alloc`.
+        let ptr = unsafe { self.inner.alloc(layout) };
+        if !ptr.is_null() {
+            count(Some(layout.size()), layout.size() as i64);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        mark_installed();
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::alloc_zeroed`.
+        let ptr = unsafe { self.inner.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            count(Some(layout.size()), layout.size() as i64);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::dealloc`.
+        unsafe { self.inner.dealloc(ptr, layout) };
+        count(None, -(layout.size() as i64));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::realloc`.
+        let new_ptr = unsafe { self.inner.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            count(Some(new_size), new_size as i64 - layout.size() as i64);
+        }
+        new_ptr
+    }
+}
+
+/// Heap allocations of a single invocation of a function.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocCounts {
+    /// Number of allocations and reallocations.
+    pub allocations: u64,
+    /// Number of bytes allocated.
+    pub bytes: u64,
+    /// Maximum increase of the live heap bytes of the thread during the invocation.
+    pub peak_bytes: u64,
+}
+
+impl AllocCounts {
+    /// Invokes `f` once and returns its allocations on the current thread, together with its result.
+    #[inline(always)]
+    fn measure<T>(f: impl FnOnce() -> T) -> (Self, T) {
+        let start = COUNTERS.try_with(|c| {
+            c.peak_live_bytes.set(c.live_bytes.get());
+            (c.allocations.get(), c.bytes.get(), c.live_bytes.get())
+        });
+        let value = f();
+        let end =
+            COUNTERS.try_with(|c| (c.allocations.get(), c.bytes.get(), c.peak_live_bytes.get()));
+        let counts = match (start, end) {
+            (Ok((allocations0, bytes0, live0)), Ok((allocations1, bytes1, peak1))) => Self {
+                allocations: allocations1 - allocations0,
+                bytes: bytes1 - bytes0,
+                peak_bytes: (peak1 - live0).max(0) as u64,
+            },
+            _ => Self::default(),
+        };
+        (counts, value)
+    }
+}
+
+/// Heap allocations of a function over the invocations of a benchmark (see [`AllocComparison`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Number of invocations.
+    pub n: u64,
+    /// Total number of allocations and reallocations.
+    pub allocations: u64,
+    /// Total number of bytes allocated.
+    pub bytes: u64,
+    /// Sum over the invocations of the peak live bytes of each invocation (see [`AllocCounts::peak_bytes`]).
+    pub sum_peak_bytes: u64,
+    /// Maximum over the invocations of the peak live bytes of each invocation.
+    pub max_peak_bytes: u64,
+}
+
+impl AllocStats {
+    /// Mean number of allocations per invocation.
+    pub fn mean_allocations(&self) -> f64 {
+        self.allocations as f64 / self.n as f64
+    }
+
+    /// Mean number of bytes allocated per invocation.
+    pub fn mean_bytes(&self) -> f64 {
+        self.bytes as f64 / self.n as f64
+    }
+
+    /// Mean peak live bytes per invocation.
+    pub fn mean_peak_bytes(&self) -> f64 {
+        self.sum_peak_bytes as f64 / self.n as f64
+    }
+
+    fn add(&mut self, counts: AllocCounts) {
+        self.n += 1;
+        self.allocations += counts.allocations;
+        self.bytes += counts.bytes;
+        self.sum_peak_bytes += counts.peak_bytes;
+        self.max_peak_bytes = self.max_peak_bytes.max(counts.peak_bytes);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.n += other.n;
+        self.allocations += other.allocations;
+        self.bytes += other.bytes;
+        self.sum_peak_bytes += other.sum_peak_bytes;
+        self.max_peak_bytes = self.max_peak_bytes.max(other.max_peak_bytes);
+    }
+}
+
+/// Heap allocations of `f1` and `f2` over the invocations of a benchmark, including a comparison of the pairs of
+/// invocations whose latencies are compared (see [`crate::DiffOut::allocations`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocComparison {
+    /// Allocations of `f1`.
+    pub f1: AllocStats,
+    /// Allocations of `f2`.
+    pub f2: AllocStats,
+    /// Number of pairs in which `f1` made fewer allocations than `f2`.
+    pub count_f1_lt_f2: u64,
+    /// Number of pairs in which `f1` and `f2` made the same number of allocations.
+    pub count_f1_eq_f2: u64,
+    /// Number of pairs in which `f1` made more allocations than `f2`.
+    pub count_f1_gt_f2: u64,
+}
+
+impl AllocComparison {
+    /// Number of pairs.
+    pub fn n(&self) -> u64 {
+        self.f1.n
+    }
+
+    /// Mean of the paired differences between the number of allocations of `f1` and `f2`.
+    pub fn mean_diff_allocations_f1_f2(&self) -> f64 {
+        self.f1.mean_allocations() - self.f2.mean_allocations()
+    }
+
+    /// Mean of the paired differences between the bytes allocated by `f1` and `f2`.
+    pub fn mean_diff_bytes_f1_f2(&self) -> f64 {
+        self.f1.mean_bytes() - self.f2.mean_bytes()
+    }
+
+    /// Mean of the paired differences between the peak live bytes of `f1` and `f2`.
+    pub fn mean_diff_peak_bytes_f1_f2(&self) -> f64 {
+        self.f1.mean_peak_bytes() - self.f2.mean_peak_bytes()
+    }
+
+    fn add(&mut self, counts1: AllocCounts, counts2: AllocCounts) {
+        self.f1.add(counts1);
+        self.f2.add(counts2);
+        match counts1.allocations.cmp(&counts2.allocations) {
+            std::cmp::Ordering::Less => self.count_f1_lt_f2 += 1,
+            std::cmp::Ordering::Equal => self.count_f1_eq_f2 += 1,
+            std::cmp::Ordering::Greater => self.count_f1_gt_f2 += 1,
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.f1.merge(&other.f1);
+        self.f2.merge(&other.f2);
+        self.count_f1_lt_f2 += other.count_f1_lt_f2;
+        self.count_f1_eq_f2 += other.count_f1_eq_f2;
+        self.count_f1_gt_f2 += other.count_f1_gt_f2;
+    }
+}
+
+/// Attributes the allocations of the target functions of a benchmark to `f1` and `f2` (see
+/// [`crate::BenchDiff::track_allocations`]).
+///
+/// Each pair of a duo invokes `f1` and `f2` once each, one right after the other, so the allocations of an
+/// invocation are paired with those of the most recent invocation of the other function, if not yet paired.
+pub(super) struct AllocTracker {
+    /// Whether invocations are measured, i.e., whether data is being collected.
+    active: Cell<bool>,
+    /// Allocations of the invocation of `f1` or `f2`, respectively, that awaits its pair.
+    pending: Cell<[Option<AllocCounts>; 2]>,
+    /// Allocations of the duo in progress.
+    duo: Cell<AllocComparison>,
+    /// Allocations of the completed duos.
+    total: Cell<AllocComparison>,
+}
+
+impl AllocTracker {
+    pub(super) fn new() -> Self {
+        Self {
+            active: Cell::new(false),
+            pending: Cell::new([None; 2]),
+            duo: Cell::new(AllocComparison::default()),
+            total: Cell::new(AllocComparison::default()),
+        }
+    }
+
+    /// Invokes `latency`, which measures the latency of an invocation of the function identified by `target`,
+    /// and returns its result, measuring the allocations of the invocation while data is being collected. The
+    /// allocation counters are read outside of `latency`, so they don't add to the latency measured.
+    #[inline(always)]
+    pub(super) fn measure(&self, target: TargetFn, latency: impl FnOnce() -> Duration) -> Duration {
+        if !self.active.get() {
+            return latency();
+        }
+        let (counts, latency) = AllocCounts::measure(latency);
+        self.record(target, counts);
+        latency
+    }
+
+    fn record(&self, target: TargetFn, counts: AllocCounts) {
+        let (this, other) = match target {
+            TargetFn::F1 => (0, 1),
+            TargetFn::F2 => (1, 0),
+        };
+        let mut pending = self.pending.get();
+        match pending[other].take() {
+            Some(other_counts) => {
+                let mut duo = self.duo.get();
+                match target {
+                    TargetFn::F1 => duo.add(counts, other_counts),
+                    TargetFn::F2 => duo.add(other_counts, counts),
+                }
+                self.duo.set(duo);
+            }
+            None => pending[this] = Some(counts),
+        }
+        self.pending.set(pending);
+    }
+
+    /// Wraps `duo`, whose latencies are measured by [`Self::measure`], so that allocations are measured outside of
+    /// warm-up and the allocations of a duo are kept only if it doesn't break, like its latencies.
+    pub(super) fn flow<'a>(
+        &'a self,
+        mut duo: impl FnMut(Phase) -> DuoFlow + 'a,
+    ) -> impl FnMut(Phase) -> DuoFlow + 'a {
+        move |phase| {
+            self.active.set(phase != Phase::Warmup);
+            let flow = duo(phase);
+            if let ControlFlow::Continue(_) = flow {
+                let mut total = self.total.get();
+                total.merge(&self.duo.get());
+                self.total.set(total);
+            }
+            self.duo.set(AllocComparison::default());
+            self.pending.set([None; 2]);
+            flow
+        }
+    }
+
+    /// Allocations of the duos kept.
+    pub(super) fn into_comparison(self) -> AllocComparison {
+        self.total.into_inner()
+    }
+}
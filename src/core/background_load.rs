@@ -0,0 +1,267 @@
+//! Module defining [`BackgroundLoad`], which configures background stressors that run while a benchmark executes.
+
+use super::SeededRng;
+use std::{
+    fmt::{self, Display, Formatter},
+    hint::black_box,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread::{self, JoinHandle},
+};
+
+/// Size of a cache line assumed by the cache thrashers, in bytes.
+const CACHE_LINE_BYTES: usize = 64;
+
+/// Number of units of work performed by a stressor between checks of its stop flag.
+const STRESSOR_STEP: usize = 4096;
+
+/// Background stressors that run on other threads while a benchmark executes, to compare functions under
+/// contention for the CPU, memory bandwidth, or caches, as on a busy host (see
+/// [`crate::BenchDiff::background_load`]).
+///
+/// There are three kinds of stressors, each running on its own threads:
+/// - *CPU spinners* execute a tight arithmetic loop, competing for cores and, with simultaneous multithreading,
+///   for the execution units of the core.
+/// - *Memory streamers* repeatedly read and write a buffer of `memory_bytes` sequentially, consuming memory
+///   bandwidth.
+/// - *Cache thrashers* read and write pseudo-randomly chosen cache lines of a buffer of `cache_bytes`, evicting
+///   the data of the benchmarked functions from shared caches.
+///
+/// The default has no stressors, i.e., it is *idle*. Stressors are configured by setting the fields, e.g.,
+/// `BackgroundLoad { cpu_threads: 3, ..Default::default() }`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BackgroundLoad {
+    /// Number of CPU spinner threads.
+    pub cpu_threads: usize,
+    /// Number of memory streamer threads.
+    pub memory_threads: usize,
+    /// Size of the buffer of each memory streamer, in bytes. To stress memory rather than caches, it should be
+    /// well above the size of the last-level cache.
+    pub memory_bytes: usize,
+    /// Number of cache thrasher threads.
+    pub cache_threads: usize,
+    /// Size of the buffer of each cache thrasher, in bytes. It should be about the size of the last-level cache.
+    pub cache_bytes: usize,
+}
+
+impl Default for BackgroundLoad {
+    /// No stressors, with buffers of 64 MiB for memory streamers and 8 MiB for cache thrashers.
+    fn default() -> Self {
+        Self {
+            cpu_threads: 0,
+            memory_threads: 0,
+            memory_bytes: 64 * 1024 * 1024,
+            cache_threads: 0,
+            cache_bytes: 8 * 1024 * 1024,
+        }
+    }
+}
+
+impl BackgroundLoad {
+    /// Whether `self` has no stressors.
+    pub fn is_idle(&self) -> bool {
+        self.thread_count() == 0
+    }
+
+    /// Total number of stressor threads.
+    pub fn thread_count(&self) -> usize {
+        self.cpu_threads + self.memory_threads + self.cache_threads
+    }
+}
+
+impl Display for BackgroundLoad {
+    /// Outputs `idle` or a comma-separated list of the stressors, e.g.,
+    /// `2 CPU spinners, 1 memory streamer (64 MiB)`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_idle() {
+            return write!(f, "idle");
+        }
+
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        let mut parts = Vec::new();
+        if self.cpu_threads > 0 {
+            parts.push(format!(
+                "{} CPU spinner{}",
+                self.cpu_threads,
+                plural(self.cpu_threads)
+            ));
+        }
+        if self.memory_threads > 0 {
+            parts.push(format!(
+                "{} memory streamer{} ({})",
+                self.memory_threads,
+                plural(self.memory_threads),
+                fmt_bytes(self.memory_bytes)
+            ));
+        }
+        if self.cache_threads > 0 {
+            parts.push(format!(
+                "{} cache thrasher{} ({})",
+                self.cache_threads,
+                plural(self.cache_threads),
+                fmt_bytes(self.cache_bytes)
+            ));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Formats a buffer size in the largest binary unit that represents it exactly.
+fn fmt_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1 << 20 && b % (1 << 20) == 0 => format!("{} MiB", b >> 20),
+        b if b >= 1 << 10 && b % (1 << 10) == 0 => format!("{} KiB", b >> 10),
+        b => format!("{b} B"),
+    }
+}
+
+/// Running stressors of a [`BackgroundLoad`], which are stopped when this value is dropped.
+pub(super) struct LoadGenerator {
+    flags: Arc<LoadFlags>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+/// Flags shared by the stressor threads and the [`LoadGenerator`] that started them.
+#[derive(Default)]
+struct LoadFlags {
+    /// Number of stressors that completed their setup.
+    ready: AtomicUsize,
+    /// Whether the stressors must stop.
+    stop: AtomicBool,
+}
+
+impl LoadGenerator {
+    /// Starts the stressors of `load` and returns once all of them are running, i.e., after their buffers are
+    /// allocated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a stressor thread can't be spawned, after stopping the stressors already started.
+    pub(super) fn start(load: &BackgroundLoad) -> Self {
+        let mut generator = Self {
+            flags: Arc::new(LoadFlags::default()),
+            threads: Vec::with_capacity(load.thread_count()),
+        };
+
+        let stressors = (0..load.cpu_threads)
+            .map(|_| Stressor::Cpu)
+            .chain((0..load.memory_threads).map(|_| Stressor::Memory(load.memory_bytes)))
+            .chain((0..load.cache_threads).map(|i| Stressor::Cache(load.cache_bytes, i as u64)));
+
+        for stressor in stressors {
+            let flags = generator.flags.clone();
+            let thread = thread::Builder::new()
+                .name("bench_diff-load".into())
+                .spawn(move || stressor.run(&flags))
+                .expect("failed to spawn background load thread");
+            generator.threads.push(thread);
+        }
+
+        while generator.flags.ready.load(Ordering::Acquire) < generator.threads.len() {
+            thread::yield_now();
+        }
+        generator
+    }
+}
+
+impl Drop for LoadGenerator {
+    fn drop(&mut self) {
+        self.flags.stop.store(true, Ordering::Relaxed);
+        for thread in self.threads.drain(..) {
+            // Stressors don't panic; a panic would only lose the load, not the benchmark data.
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A single stressor thread.
+enum Stressor {
+    Cpu,
+    /// Buffer size.
+    Memory(usize),
+    /// Buffer size and seed for the choice of cache lines.
+    Cache(usize, u64),
+}
+
+impl Stressor {
+    /// Runs the stressor until `flags.stop` is set, incrementing `flags.ready` after its setup.
+    fn run(self, flags: &LoadFlags) {
+        let stop = &flags.stop;
+        let started = || {
+            flags.ready.fetch_add(1, Ordering::Release);
+        };
+        match self {
+            Self::Cpu => {
+                started();
+                let mut x = 1_u64;
+                while !stop.load(Ordering::Relaxed) {
+                    for _ in 0..STRESSOR_STEP {
+                        x = black_box(x.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1));
+                    }
+                }
+            }
+            Self::Memory(bytes) => {
+                let mut buf = vec![0_u64; (bytes / size_of::<u64>()).max(1)];
+                started();
+                while !stop.load(Ordering::Relaxed) {
+                    for chunk in buf.chunks_mut(STRESSOR_STEP) {
+                        for v in chunk.iter_mut() {
+                            *v = v.wrapping_add(1);
+                        }
+                        black_box(&mut *chunk);
+                        if stop.load(Ordering::Relaxed) {
+                            break;
+                        }
+                    }
+                }
+            }
+            Self::Cache(bytes, seed) => {
+                let line_len = CACHE_LINE_BYTES / size_of::<u64>();
+                let line_count = (bytes / CACHE_LINE_BYTES).max(1);
+                let mut buf = vec![0_u64; line_count * line_len];
+                let mut rng = SeededRng::new(seed);
+                started();
+                while !stop.load(Ordering::Relaxed) {
+                    for _ in 0..STRESSOR_STEP {
+                        let i = (rng.next_u64() % line_count as u64) as usize * line_len;
+                        buf[i] = buf[i].wrapping_add(1);
+                    }
+                    black_box(&mut buf);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{BenchDiff, LatencyUnit};
+
+    #[test]
+    fn test_background_load() {
+        assert_eq!(BackgroundLoad::default().to_string(), "idle");
+
+        let load = BackgroundLoad {
+            cpu_threads: 1,
+            memory_threads: 1,
+            memory_bytes: 1 << 20,
+            cache_threads: 2,
+            cache_bytes: 64 << 10,
+        };
+        assert_eq!(
+            load.to_string(),
+            "1 CPU spinner, 1 memory streamer (1 MiB), 2 cache thrashers (64 KiB)"
+        );
+
+        // Returns only after the stressors are stopped.
+        let out = BenchDiff::new(LatencyUnit::Nano, 1_000)
+            .warmup_millis(1)
+            .background_load(load)
+            .run(|| (), || ());
+        assert_eq!(out.background_load(), load);
+        assert_eq!(out.n(), 1_000);
+    }
+}
@@ -1,11 +1,22 @@
 //! Main module implementing functions to compare the difference in latency between two closures.
 
-use crate::bench_utils::latency;
-
-use super::{DiffOut, Timing};
+use crate::bench_utils::{latency_batch, latency_with_input};
+
+use super::{
+    BenchDiff, BenchDiffError, ClockKind, ClosureReporter, DiffOut, Half, PairedSample, Reporter,
+    RunInfo, SeededRng, SilentReporter, StopReason, TargetFn, TargetPanic, Timing, WarmupOutcome,
+    WarmupPolicy, Warning,
+    allocations::AllocTracker,
+    outliers::RATIO_HIST_SCALE,
+    stderr_reporter,
+    warmup::{WarmupMonitor, WarmupPlan},
+};
 use std::{
+    any::Any,
+    cell::{Cell, RefCell},
     cmp,
-    io::{Write, stderr},
+    ops::ControlFlow,
+    panic::{self, AssertUnwindSafe},
     sync::atomic::{AtomicU64, Ordering},
     time::{Duration, Instant},
 };
@@ -14,6 +25,8 @@ static WARMUP_MILLIS: AtomicU64 = AtomicU64::new(3_000);
 
 /// The currently defined number of milliseconds used to "warm-up" the benchmark. The default is 3,000 ms.
 ///
+/// It applies to [`crate::WarmupPolicy::Fixed`], the default warm-up policy, and is the default warm-up
+/// duration of a new [`BenchDiff`] (see [`BenchDiff::warmup_millis`]).
 /// Use [`set_warmup_millis`] to change the value.
 pub fn get_warmup_millis() -> u64 {
     WARMUP_MILLIS.load(Ordering::Relaxed)
@@ -26,8 +39,18 @@ pub fn set_warmup_millis(millis: u64) {
 
 const WARMUP_INCREMENT_COUNT: usize = 20;
 
-/// Unit of time used to record latencies. Used as an argument in benchmarking functions.
-#[derive(Clone, Copy, Debug)]
+/// Number of empty-closure latencies measured at the end of warm-up to estimate the timer overhead.
+const TIMER_OVERHEAD_SAMPLE_COUNT: usize = 1_000;
+
+/// A [`Warning::NearTimerOverhead`] is issued when the median latency of a function is less than this factor
+/// times the median timer overhead.
+pub(super) const TIMER_OVERHEAD_WARNING_FACTOR: f64 = 10.;
+
+/// Unit of time used to present latencies. Used as an argument in benchmarking functions.
+///
+/// Latencies are always recorded in nanoseconds; the unit only determines how the latency statistics of a
+/// [`DiffOut`] are expressed (see [`DiffOut::unit`] and [`DiffOut::best_unit`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LatencyUnit {
     Milli,
     Micro,
@@ -66,17 +89,58 @@ impl LatencyUnit {
     pub fn latency_from_f64(&self, elapsed: f64) -> Duration {
         self.latency_from_u64(elapsed as u64)
     }
+
+    /// Number of nanoseconds in one unit `self`.
+    #[inline(always)]
+    pub fn nanos(&self) -> f64 {
+        match self {
+            Self::Nano => 1.,
+            Self::Micro => 1_000.,
+            Self::Milli => 1_000_000.,
+        }
+    }
+
+    /// Symbol of the unit `self`, e.g., `"µs"` for [`Self::Micro`].
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Nano => "ns",
+            Self::Micro => "µs",
+            Self::Milli => "ms",
+        }
+    }
 }
 
-/// Invokes `f1` then `f2` then `f2` then `f1` and returns two pairs of latencies. For each pair,
-/// the first component is an `f1` latency and the second component is an `f2` latency.
+/// Invokes `f`, the function identified by `target`, once and returns its latency measured with `clock`. If
+/// `tracker` is not `None`, the allocations of the invocation are measured around the latency measurement (see
+/// [`AllocTracker::measure`]).
 #[inline(always)]
-fn duo_exec(mut f1: impl FnMut(), mut f2: impl FnMut()) -> [(Duration, Duration); 2] {
-    let l01 = latency(&mut f1);
-    let l02 = latency(&mut f2);
+fn latency_tracked(
+    clock: ClockKind,
+    tracker: Option<&AllocTracker>,
+    target: TargetFn,
+    f: impl FnMut(),
+) -> Duration {
+    match tracker {
+        None => clock.latency(f),
+        Some(tracker) => tracker.measure(target, || clock.latency(f)),
+    }
+}
 
-    let l12 = latency(&mut f2);
-    let l11 = latency(&mut f1);
+/// Invokes `f1` then `f2` then `f2` then `f1` and returns two pairs of latencies measured with `clock`, tracking
+/// allocations with `tracker` if it is not `None` (see [`latency_tracked`]). For each pair, the first component
+/// is an `f1` latency and the second component is an `f2` latency.
+#[inline(always)]
+fn duo_exec(
+    clock: ClockKind,
+    tracker: Option<&AllocTracker>,
+    (t1, mut f1): (TargetFn, impl FnMut()),
+    (t2, mut f2): (TargetFn, impl FnMut()),
+) -> [(Duration, Duration); 2] {
+    let l01 = latency_tracked(clock, tracker, t1, &mut f1);
+    let l02 = latency_tracked(clock, tracker, t2, &mut f2);
+
+    let l12 = latency_tracked(clock, tracker, t2, &mut f2);
+    let l11 = latency_tracked(clock, tracker, t1, &mut f1);
 
     [(l01, l02), (l11, l12)]
 }
@@ -93,8 +157,14 @@ pub(crate) struct DiffState<'a> {
     sum2_ln_f1: &'a mut f64,
     sum_ln_f2: &'a mut f64,
     sum2_ln_f2: &'a mut f64,
-    sum2_diff_f1_f2: &'a mut i64,
+    sum2_diff_f1_f2: &'a mut f64,
     sum2_diff_ln_f1_f2: &'a mut f64,
+    hist_ratio_f1_f2: &'a mut Timing,
+    samples: Option<&'a mut Vec<PairedSample>>,
+    hist_timer_overhead: &'a mut Timing,
+    timer_overhead_subtracted: &'a mut u64,
+    clock: ClockKind,
+    first: TargetFn,
 }
 
 impl<'a> DiffState<'a> {
@@ -113,6 +183,12 @@ impl<'a> DiffState<'a> {
             sum2_ln_f2: &mut out.sum2_ln_f2,
             sum2_diff_f1_f2: &mut out.sum2_diff_f1_f2,
             sum2_diff_ln_f1_f2: &mut out.sum2_diff_ln_f1_f2,
+            hist_ratio_f1_f2: &mut out.hist_ratio_f1_f2,
+            samples: out.samples.as_mut(),
+            hist_timer_overhead: &mut out.hist_timer_overhead,
+            timer_overhead_subtracted: &mut out.timer_overhead_subtracted,
+            clock: out.clock,
+            first: TargetFn::F1,
         }
     }
 
@@ -131,234 +207,1602 @@ impl<'a> DiffState<'a> {
             sum2_ln_f2: self.sum2_ln_f1,
             sum2_diff_f1_f2: self.sum2_diff_f1_f2,
             sum2_diff_ln_f1_f2: self.sum2_diff_ln_f1_f2,
+            hist_ratio_f1_f2: self.hist_ratio_f1_f2,
+            samples: self.samples.as_deref_mut(),
+            hist_timer_overhead: self.hist_timer_overhead,
+            timer_overhead_subtracted: self.timer_overhead_subtracted,
+            clock: self.clock,
+            first: self.first.other(),
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.hist_f1.reset();
+        self.hist_f2.reset();
+        self.hist_f1_lt_f2.reset();
+        *self.count_f1_eq_f2 = 0;
+        self.hist_f1_gt_f2.reset();
+        *self.sum_f1 = 0;
+        *self.sum_f2 = 0;
+        *self.sum_ln_f1 = 0.;
+        *self.sum2_ln_f1 = 0.;
+        *self.sum_ln_f2 = 0.;
+        *self.sum2_ln_f2 = 0.;
+        *self.sum2_diff_f1_f2 = 0.;
+        *self.sum2_diff_ln_f1_f2 = 0.;
+        self.hist_ratio_f1_f2.reset();
+        if let Some(samples) = self.samples.as_deref_mut() {
+            samples.clear();
+        }
+    }
+
+    /// Updates the state with an elapsed time for each function. Returns an error, without updating the state,
+    /// if either elapsed time is zero.
+    #[inline(always)]
+    pub(crate) fn capture_data(
+        &mut self,
+        elapsed1: u64,
+        elapsed2: u64,
+    ) -> Result<(), BenchDiffError> {
+        if elapsed1 == 0 {
+            return Err(BenchDiffError::ZeroLatency { target: self.first });
+        }
+        if elapsed2 == 0 {
+            return Err(BenchDiffError::ZeroLatency {
+                target: self.first.other(),
+            });
+        }
+
+        self.hist_f1
+            .record(elapsed1)
+            .expect("can't happen: histogram is auto-resizable");
+        self.hist_f2
+            .record(elapsed2)
+            .expect("can't happen: histogram is auto-resizable");
+
+        let diff = elapsed1 as i64 - elapsed2 as i64;
+
+        match diff.cmp(&0) {
+            cmp::Ordering::Less => self
+                .hist_f1_lt_f2
+                .record(diff as u64)
+                .expect("can't happen: histogram is auto-resizable"),
+            cmp::Ordering::Greater => self
+                .hist_f1_gt_f2
+                .record(-diff as u64)
+                .expect("can't happen: histogram is auto-resizable"),
+            cmp::Ordering::Equal => *self.count_f1_eq_f2 += 1,
+        }
+
+        *self.sum_f1 += elapsed1 as i64;
+        let ln_f1 = (elapsed1 as f64).ln();
+        *self.sum_ln_f1 += ln_f1;
+        *self.sum2_ln_f1 += ln_f1.powi(2);
+
+        *self.sum_f2 += elapsed2 as i64;
+        let ln_f2 = (elapsed2 as f64).ln();
+        *self.sum_ln_f2 += ln_f2;
+        *self.sum2_ln_f2 += ln_f2.powi(2);
+
+        // Accumulated as `f64` because the sum of squares of differences in nanoseconds can overflow `i64`.
+        let diff_f1_f2 = elapsed1 as f64 - elapsed2 as f64;
+        *self.sum2_diff_f1_f2 += diff_f1_f2.powi(2);
+
+        let diff_ln_f1_f2 = ln_f1 - ln_f2;
+        *self.sum2_diff_ln_f1_f2 += diff_ln_f1_f2.powi(2);
+
+        // The ratio is always that of the latency of `f1` to the latency of `f2`, regardless of `self.first`.
+        let ratio = match self.first {
+            TargetFn::F1 => elapsed1 as f64 / elapsed2 as f64,
+            TargetFn::F2 => elapsed2 as f64 / elapsed1 as f64,
+        };
+        self.hist_ratio_f1_f2
+            .record(((ratio * RATIO_HIST_SCALE).round() as u64).max(1))
+            .expect("can't happen: histogram is auto-resizable");
+
+        // The buffer is never grown here, so that the execution of duos doesn't allocate; it is filled during
+        // warm-up and cleared by `reset` before data collection.
+        if let Some(samples) = self.samples.as_deref_mut()
+            && samples.len() < samples.capacity()
+        {
+            let (elapsed1, elapsed2, half) = match self.first {
+                TargetFn::F1 => (elapsed1, elapsed2, Half::Forward),
+                TargetFn::F2 => (elapsed2, elapsed1, Half::Reversed),
+            };
+            let duo_index = (samples.len() / 2) as u64;
+            samples.push(PairedSample {
+                elapsed1,
+                elapsed2,
+                duo_index,
+                half,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Measures the latency of an empty closure [`TIMER_OVERHEAD_SAMPLE_COUNT`] times and records it as the
+    /// timer overhead. If `subtract` is `true`, sets the value subtracted from each subsequently captured latency
+    /// to the median timer overhead. In batched mode, the timer is read once per batch, so the overhead is
+    /// subtracted from the total latency of each batch.
+    pub(crate) fn calibrate_timer_overhead(&mut self, subtract: bool) {
+        self.hist_timer_overhead.reset();
+        for _ in 0..TIMER_OVERHEAD_SAMPLE_COUNT {
+            let overhead = LatencyUnit::Nano.latency_as_u64(self.clock.latency(|| {}));
+            self.hist_timer_overhead
+                .record(overhead)
+                .expect("can't happen: histogram is auto-resizable");
+        }
+
+        *self.timer_overhead_subtracted = if subtract {
+            self.hist_timer_overhead.value_at_quantile(0.5)
+        } else {
+            0
+        };
+    }
+
+    /// Updates the state with a latency for each function, in nanoseconds, after subtraction of the timer
+    /// overhead if applicable (see [`Self::calibrate_timer_overhead`]).
+    #[inline(always)]
+    pub(crate) fn capture_latencies(
+        &mut self,
+        latency1: Duration,
+        latency2: Duration,
+    ) -> Result<(), BenchDiffError> {
+        let subtracted = *self.timer_overhead_subtracted;
+        let elapsed1 = LatencyUnit::Nano.latency_as_u64(latency1);
+        let elapsed2 = LatencyUnit::Nano.latency_as_u64(latency2);
+        if subtracted == 0 {
+            self.capture_data(elapsed1, elapsed2)
+        } else {
+            self.capture_data(
+                elapsed1.saturating_sub(subtracted).max(1),
+                elapsed2.saturating_sub(subtracted).max(1),
+            )
+        }
+    }
+
+    /// Executes duos repeatedly, invoking `duo` `exec_count / 2` times, and captures their latencies.
+    /// [`Reporter::progress`] is invoked at the end of each invocation of `duo`, with the number of executions
+    /// offset by `init_status_count`.
+    ///
+    /// If `duo` breaks, its latencies are discarded and execution stops, returning the break value. If
+    /// `reporter` breaks, execution stops after the latencies of the current duo are captured, returning
+    /// [`StopReason::Cancelled`].
+    fn execute(
+        &mut self,
+        mut duo: impl FnMut() -> DuoFlow,
+        exec_count: usize,
+        reporter: &mut impl Reporter,
+        init_status_count: usize,
+    ) -> Result<ControlFlow<StopReason>, BenchDiffError> {
+        for i in 1..=exec_count / 2 {
+            let pairs = match duo() {
+                ControlFlow::Continue(pairs) => pairs,
+                ControlFlow::Break(stop_reason) => return Ok(ControlFlow::Break(stop_reason)),
+            };
+
+            for (latency1, latency2) in pairs {
+                self.capture_latencies(latency1, latency2)?;
+            }
+
+            // `i * 2` to account for duos
+            if reporter.progress(init_status_count + i * 2).is_break() {
+                return Ok(ControlFlow::Break(StopReason::Cancelled));
+            }
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Warms-up the benchmark by invoking [`Self::execute`] repeatedly, each time with an `exec_count` value of
+    /// [`WARMUP_INCREMENT_COUNT`], until `plan.millis` is reached or exceeded or, for
+    /// [`WarmupPolicy::Convergence`], until the latencies are stable (see [`crate::WarmupConvergence`]).
+    /// [`Reporter::warmup_progress`] is invoked at the end of each invocation of [`Self::execute`]. The timer
+    /// overhead is calibrated at the end of warm-up (see [`Self::calibrate_timer_overhead`]).
+    ///
+    /// If `duo` breaks, warm-up stops without calibration of the timer overhead and the break value is returned
+    /// together with the warm-up outcome. The same happens, with [`StopReason::Cancelled`], if `reporter` breaks.
+    fn warmup(
+        &mut self,
+        mut duo: impl FnMut() -> DuoFlow,
+        plan: WarmupPlan,
+        reporter: &mut impl Reporter,
+    ) -> Result<(WarmupOutcome, ControlFlow<StopReason>), BenchDiffError> {
+        let WarmupPlan {
+            policy,
+            millis: warmup_millis,
+            subtract_timer_overhead,
+        } = plan;
+        let (mut monitor, min_millis) = match policy {
+            WarmupPolicy::Fixed => (None, warmup_millis),
+            WarmupPolicy::Convergence(convergence) => (
+                Some(WarmupMonitor::new(convergence)),
+                convergence.min_millis,
+            ),
+        };
+
+        let start = Instant::now();
+        let mut exec_count = 0;
+        let mut converged = false;
+        let mut flow = ControlFlow::Continue(());
+        for i in 1.. {
+            let duo = || {
+                let pairs = duo()?;
+                if let Some(monitor) = monitor.as_mut() {
+                    monitor.observe(&pairs);
+                }
+                // Each duo executes each function twice.
+                exec_count += 2;
+                ControlFlow::Continue(pairs)
+            };
+            flow = self.execute(duo, WARMUP_INCREMENT_COUNT, &mut SilentReporter, 0)?;
+            if flow.is_break() {
+                break;
+            }
+
+            let elapsed = Instant::now().duration_since(start);
+            let elapsed_millis = elapsed.as_millis() as u64;
+            if reporter
+                .warmup_progress(i, elapsed_millis, warmup_millis)
+                .is_break()
+            {
+                flow = ControlFlow::Break(StopReason::Cancelled);
+                break;
+            }
+            if let Some(monitor) = monitor.as_mut() {
+                converged =
+                    monitor.is_stable(exec_count, elapsed_millis) && elapsed_millis >= min_millis;
+            }
+            if converged || elapsed.ge(&Duration::from_millis(warmup_millis)) {
+                break;
+            }
+        }
+        let duration = Instant::now().duration_since(start);
+
+        if flow.is_continue() {
+            self.calibrate_timer_overhead(subtract_timer_overhead);
+        }
+
+        let outcome = WarmupOutcome {
+            policy,
+            exec_count,
+            duration,
+            converged,
+            trace: monitor.map(WarmupMonitor::into_trace).unwrap_or_default(),
+        };
+        Ok((outcome, flow))
+    }
+}
+
+/// The two pairs of latencies of a duo (see [`duo_exec`]).
+pub(super) type DuoPairs = [(Duration, Duration); 2];
+
+/// Result of a duo that can stop data collection: the two pairs of latencies of the duo, or the reason to stop.
+pub(super) type DuoFlow = ControlFlow<StopReason, DuoPairs>;
+
+/// Identifies a phase of the benchmark.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Phase {
+    /// Warm-up; duos start with `f1`, i.e., (`f1`, `f2`), (`f2`, `f1`).
+    Warmup,
+    /// First half of data collection; duos start with `f1`, i.e., (`f1`, `f2`), (`f2`, `f1`).
+    Forward,
+    /// Second half of data collection; duos start with `f2`, i.e., (`f2`, `f1`), (`f1`, `f2`).
+    Reversed,
+}
+
+/// Common implementation of the benchmarking functions with a fixed number of executions, which collects the data
+/// into `out` and returns [`BenchDiffError::ZeroLatency`] if a latency of zero is measured.
+///
+/// `out` must be empty; its unit is used, and attributes that affect data collection (e.g., [`DiffOut::clock`]
+/// and [`DiffOut::samples`]) must be set beforehand. Warm-up follows `warmup_plan`.
+///
+/// `duo` executes a single duo for the given [`Phase`]. For [`Phase::Warmup`] and [`Phase::Forward`], the first
+/// component of each returned pair is an `f1` latency and the second component is an `f2` latency; for
+/// [`Phase::Reversed`], the first component is an `f2` latency and the second component is an `f1` latency.
+/// `duo` can break, in which case data collection stops, the data of the duo in progress is discarded, and the
+/// break value is recorded as the [`DiffOut::stop_reason`]. If `duo` breaks during warm-up, no data is collected.
+///
+/// The remaining arguments are as in [`bench_diff_with_reporter`].
+pub(super) fn try_bench_duos_out_x(
+    mut out: DiffOut,
+    warmup_plan: WarmupPlan,
+    mut duo: impl FnMut(Phase) -> DuoFlow,
+    exec_count: usize,
+    reporter: &mut impl Reporter,
+) -> Result<DiffOut, BenchDiffError> {
+    let exec_count2 = exec_count / 2;
+
+    reporter.run_start(&RunInfo {
+        unit: out.unit,
+        exec_count: Some(exec_count),
+    });
+
+    let mut state = DiffState::new(&mut out);
+    let (warmup, mut flow) = state.warmup(|| duo(Phase::Warmup), warmup_plan, reporter)?;
+    state.reset();
+
+    if flow.is_continue() {
+        reporter.measurement_start();
+        reporter.half_start(Half::Forward);
+        flow = state.execute(|| duo(Phase::Forward), exec_count2, reporter, 0)?;
+    }
+
+    if flow.is_continue() {
+        let mut state_rev = state.reversed();
+        reporter.half_start(Half::Reversed);
+        flow = state_rev.execute(|| duo(Phase::Reversed), exec_count2, reporter, exec_count2)?;
+    }
+
+    if let ControlFlow::Break(stop_reason) = flow {
+        out.stop_reason = stop_reason;
+    }
+    out.warmup = warmup;
+    check_timer_overhead(&mut out);
+    reporter.complete(out.n() as usize, out.stop_reason);
+    Ok(out)
+}
+
+/// Returns the value of `result`, panicking with the error message if it is an error. Used by the infallible
+/// benchmarking functions.
+pub(super) fn expect_ok<T>(result: Result<T, BenchDiffError>) -> T {
+    result.unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Adds a [`Warning::NearTimerOverhead`] to `out` if the median latency of either function is less than
+/// [`TIMER_OVERHEAD_WARNING_FACTOR`] times the median timer overhead, amortized over the batch size. A previous
+/// [`Warning::NearTimerOverhead`] is replaced, so the check can be repeated if the batch size changes.
+pub(super) fn check_timer_overhead(out: &mut DiffOut) {
+    out.warnings
+        .retain(|warning| !matches!(warning, Warning::NearTimerOverhead { .. }));
+    if out.n() == 0 {
+        return;
+    }
+
+    let batch_size = out.batch_size as f64;
+    let timer_overhead = out.hist_timer_overhead.value_at_quantile(0.5) as f64 / batch_size;
+    let subtracted = out.timer_overhead_subtracted as f64;
+    let median = (out
+        .hist_f1
+        .value_at_quantile(0.5)
+        .min(out.hist_f2.value_at_quantile(0.5)) as f64
+        + subtracted)
+        / batch_size;
+    if median < TIMER_OVERHEAD_WARNING_FACTOR * timer_overhead {
+        out.warnings.push(Warning::NearTimerOverhead {
+            median,
+            timer_overhead,
+        });
+    }
+}
+
+/// Returns a closure that executes a duo of `f1` and `f2` (see [`duo_exec`]) for a given [`Phase`], as
+/// required by [`try_bench_duos_out_x`] and [`bench_duos_rounds_x`], with latencies measured by `clock` and
+/// allocations tracked by `tracker` if it is not `None`.
+fn duos<'a>(
+    clock: ClockKind,
+    tracker: Option<&'a AllocTracker>,
+    mut f1: impl FnMut() + 'a,
+    mut f2: impl FnMut() + 'a,
+) -> impl FnMut(Phase) -> [(Duration, Duration); 2] + 'a {
+    move |phase| match phase {
+        Phase::Warmup | Phase::Forward => duo_exec(
+            clock,
+            tracker,
+            (TargetFn::F1, &mut f1),
+            (TargetFn::F2, &mut f2),
+        ),
+        Phase::Reversed => duo_exec(
+            clock,
+            tracker,
+            (TargetFn::F2, &mut f2),
+            (TargetFn::F1, &mut f1),
+        ),
+    }
+}
+
+/// Target functions of a benchmark together with the way a duo of them is executed, which is specific to each
+/// benchmarking function family. The settings of a [`BenchDiff`] that don't depend on the family are applied to
+/// the duos by [`BenchDiff::collect_duos`].
+pub(super) trait DuoSource {
+    /// Whether the allocations of the target functions can be tracked (see [`BenchDiff::track_allocations`]).
+    const TRACKS_ALLOCATIONS: bool = false;
+
+    /// Clock used to measure latencies, given the clock configured in the [`BenchDiff`].
+    fn clock(&self, configured: ClockKind) -> ClockKind {
+        configured
+    }
+
+    /// Returns a closure that executes a duo for a given [`Phase`] (see [`try_bench_duos_out_x`]), with latencies
+    /// measured by `clock`. The target functions are wrapped by `panics` and, if `tracker` is not `None`, their
+    /// allocations are measured by `tracker` (see [`AllocTracker::measure`]).
+    fn duos<'a>(
+        &'a mut self,
+        clock: ClockKind,
+        panics: &'a impl Panics,
+        tracker: Option<&'a AllocTracker>,
+    ) -> impl FnMut(Phase) -> DuoPairs + 'a;
+}
+
+/// [`DuoSource`] of two closures that take no arguments, executed by [`duos`].
+pub(super) struct PlainDuos<F1, F2>(pub(super) F1, pub(super) F2);
+
+impl<F1: FnMut(), F2: FnMut()> DuoSource for PlainDuos<F1, F2> {
+    const TRACKS_ALLOCATIONS: bool = true;
+
+    fn duos<'a>(
+        &'a mut self,
+        clock: ClockKind,
+        panics: &'a impl Panics,
+        tracker: Option<&'a AllocTracker>,
+    ) -> impl FnMut(Phase) -> DuoPairs + 'a {
+        let f1 = panics.wrap(TargetFn::F1, &mut self.0);
+        let f2 = panics.wrap(TargetFn::F2, &mut self.1);
+        duos(clock, tracker, f1, f2)
+    }
+}
+
+/// Wraps `duo` (as required by [`try_bench_duos_out_x`] and [`bench_duos_rounds_x`]) so that, outside warm-up, the
+/// order of the two pairs in each duo is chosen pseudo-randomly from `seed`, i.e., a duo that would execute
+/// (`f1`, `f2`), (`f2`, `f1`) executes (`f2`, `f1`), (`f1`, `f2`) instead, and vice-versa.
+///
+/// The generator is re-seeded at the start of data collection and, at the start of each run of
+/// [`Phase::Reversed`] duos, it is restored to its state at the start of the preceding run of [`Phase::Forward`]
+/// duos. As these runs have the same number of duos, each run of [`Phase::Reversed`] duos mirrors the preceding
+/// run of [`Phase::Forward`] duos, so each function starts exactly half of the duos and the order-effect
+/// cancellation of the fixed order is preserved.
+pub(super) fn randomized(
+    seed: u64,
+    mut duo: impl FnMut(Phase) -> [(Duration, Duration); 2],
+) -> impl FnMut(Phase) -> [(Duration, Duration); 2] {
+    let mut rng = SeededRng::new(seed);
+    let mut forward_start_rng = rng.clone();
+    let mut prev_phase = Phase::Warmup;
+
+    move |phase| {
+        match (prev_phase, phase) {
+            (Phase::Warmup, Phase::Forward | Phase::Reversed) => {
+                rng = SeededRng::new(seed);
+                forward_start_rng = rng.clone();
+            }
+            (Phase::Reversed, Phase::Forward) => forward_start_rng = rng.clone(),
+            (Phase::Forward, Phase::Reversed) => rng = forward_start_rng.clone(),
+            _ => (),
+        }
+        prev_phase = phase;
+
+        let flip = phase != Phase::Warmup && rng.next_u64() >> 63 == 1;
+        match (phase, flip) {
+            (Phase::Forward, true) => duo(Phase::Reversed).map(|(l2, l1)| (l1, l2)),
+            (Phase::Reversed, true) => duo(Phase::Forward).map(|(l1, l2)| (l2, l1)),
+            _ => duo(phase),
+        }
+    }
+}
+
+/// Handling of the panics of the target functions of a benchmark: [`PanicCatcher`] catches them and
+/// [`PropagatePanics`] lets them propagate to the caller (see [`BenchDiff::catch_panics`]).
+pub(super) trait Panics {
+    /// Invokes `f`, which calls the function identified by `target`, and returns its result, or `None` if the
+    /// panic of a function was caught.
+    fn catch<T>(&self, target: TargetFn, f: impl FnOnce() -> T) -> Option<T>;
+
+    /// Returns a closure that invokes `f`, the function identified by `target`, handling its panics.
+    fn wrap<'a>(&'a self, target: TargetFn, f: impl FnMut() + 'a) -> impl FnMut() + 'a;
+
+    /// Same as [`Self::wrap`] for a function that takes an input.
+    fn wrap_input<'a, I>(&'a self, target: TargetFn, f: impl FnMut(I) + 'a) -> impl FnMut(I) + 'a;
+
+    /// Whether a panic has been caught.
+    fn panicked(&self) -> bool;
+
+    /// Wraps `duo`, which executes functions wrapped by `self`, so that it breaks with [`StopReason::Panic`] if a
+    /// panic was caught during its execution.
+    fn flow<'a>(
+        &'a self,
+        duo: impl FnMut(Phase) -> DuoPairs + 'a,
+    ) -> impl FnMut(Phase) -> DuoFlow + 'a;
+}
+
+/// [`Panics`] implementation that lets panics propagate and doesn't add any overhead to the functions.
+pub(super) struct PropagatePanics;
+
+impl Panics for PropagatePanics {
+    fn panicked(&self) -> bool {
+        false
+    }
+
+    #[inline(always)]
+    fn catch<T>(&self, _target: TargetFn, f: impl FnOnce() -> T) -> Option<T> {
+        Some(f())
+    }
+
+    fn wrap<'a>(&'a self, _target: TargetFn, f: impl FnMut() + 'a) -> impl FnMut() + 'a {
+        f
+    }
+
+    fn wrap_input<'a, I>(&'a self, _target: TargetFn, f: impl FnMut(I) + 'a) -> impl FnMut(I) + 'a {
+        f
+    }
+
+    fn flow<'a>(
+        &'a self,
+        mut duo: impl FnMut(Phase) -> DuoPairs + 'a,
+    ) -> impl FnMut(Phase) -> DuoFlow + 'a {
+        move |phase| ControlFlow::Continue(duo(phase))
+    }
+}
+
+/// Catches the panics of the target functions of a benchmark (see [`BenchDiff::catch_panics`]), identified by
+/// values of type `K`. Once a panic has been caught, the functions are no longer invoked.
+pub(super) struct PanicCatcher<K = TargetFn> {
+    panicked: Cell<bool>,
+    panic: RefCell<Option<(K, Box<dyn Any + Send>)>>,
+}
+
+impl<K> PanicCatcher<K> {
+    pub(super) fn new() -> Self {
+        Self {
+            panicked: Cell::new(false),
+            panic: RefCell::new(None),
+        }
+    }
+
+    /// Invokes `f`, which calls the function identified by `target`, catching its panic. Returns the result of
+    /// `f`, or `None` if it panicked or a panic had already been caught, in which case `f` is not invoked.
+    pub(super) fn catch_from<T>(&self, target: K, f: impl FnOnce() -> T) -> Option<T> {
+        if self.panicked.get() {
+            return None;
+        }
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(value) => Some(value),
+            Err(payload) => {
+                self.panicked.set(true);
+                *self.panic.borrow_mut() = Some((target, payload));
+                None
+            }
+        }
+    }
+
+    /// Whether a panic has been caught.
+    pub(super) fn has_panicked(&self) -> bool {
+        self.panicked.get()
+    }
+
+    /// The function that panicked and the panic payload, if a panic was caught.
+    pub(super) fn into_caught(self) -> Option<(K, Box<dyn Any + Send>)> {
+        self.panic.into_inner()
+    }
+}
+
+impl PanicCatcher {
+    /// Information about the panic caught, if any.
+    pub(super) fn into_panic(self) -> Option<TargetPanic> {
+        self.into_caught()
+            .map(|(target, payload)| TargetPanic::new(target, payload))
+    }
+}
+
+impl Panics for PanicCatcher {
+    fn panicked(&self) -> bool {
+        self.panicked.get()
+    }
+
+    fn catch<T>(&self, target: TargetFn, f: impl FnOnce() -> T) -> Option<T> {
+        self.catch_from(target, f)
+    }
+
+    fn wrap<'a>(&'a self, target: TargetFn, mut f: impl FnMut() + 'a) -> impl FnMut() + 'a {
+        move || {
+            self.catch_from(target, &mut f);
+        }
+    }
+
+    fn wrap_input<'a, I>(
+        &'a self,
+        target: TargetFn,
+        mut f: impl FnMut(I) + 'a,
+    ) -> impl FnMut(I) + 'a {
+        move |input| {
+            self.catch_from(target, || f(input));
+        }
+    }
+
+    fn flow<'a>(
+        &'a self,
+        mut duo: impl FnMut(Phase) -> DuoPairs + 'a,
+    ) -> impl FnMut(Phase) -> DuoFlow + 'a {
+        move |phase| {
+            let pairs = duo(phase);
+            if self.has_panicked() {
+                ControlFlow::Break(StopReason::Panic)
+            } else {
+                ControlFlow::Continue(pairs)
+            }
         }
     }
+}
+
+/// Common implementation of the benchmarking functions whose sample size is determined during data collection.
+///
+/// After warm-up, data is collected in *rounds*. Each round executes a number of duos for [`Phase::Forward`]
+/// followed by the same number of duos for [`Phase::Reversed`], so that, at the end of each round, each function
+/// has started the same number of duos and the sample size is a multiple of 4.
+///
+/// `next_round` determines the number of duos per phase in the next round, or the reason to stop data collection.
+/// It is first invoked right after warm-up, with a [`DiffOut`] containing the warm-up data; subsequently, it is
+/// invoked after each round, with the data collected so far. A number of duos equal to `0` is treated as `1`. If
+/// `next_round` returns an error, data collection stops and the error is returned.
+///
+/// `exec_count` is the maximum number of executions for each function, if known (see [`RunInfo::exec_count`]).
+/// The other arguments are as in [`try_bench_duos_out_x`], except that [`Reporter::progress`] is invoked with the
+/// cumulative number of executions across rounds. If samples are retained (see [`DiffOut::samples`]), the buffer
+/// of `out` is grown before each round to accommodate the samples of the round.
+///
+/// Returns [`BenchDiffError::ZeroLatency`] if a latency of zero is measured.
+pub(super) fn bench_duos_rounds_x(
+    mut out: DiffOut,
+    warmup_plan: WarmupPlan,
+    mut duo: impl FnMut(Phase) -> DuoFlow,
+    exec_count: Option<usize>,
+    reporter: &mut impl Reporter,
+    mut next_round: impl FnMut(&DiffOut) -> Result<ControlFlow<StopReason, usize>, BenchDiffError>,
+) -> Result<DiffOut, BenchDiffError> {
+    reporter.run_start(&RunInfo {
+        unit: out.unit,
+        exec_count,
+    });
+
+    let (warmup, flow) =
+        DiffState::new(&mut out).warmup(|| duo(Phase::Warmup), warmup_plan, reporter)?;
+    out.warmup = warmup;
+    let mut round = match flow {
+        ControlFlow::Continue(()) => next_round(&out)?,
+        ControlFlow::Break(stop_reason) => ControlFlow::Break(stop_reason),
+    };
+    DiffState::new(&mut out).reset();
+
+    if flow.is_continue() {
+        reporter.measurement_start();
+    }
+    let mut count = 0;
+
+    let stop_reason = loop {
+        let round_duos = match round {
+            ControlFlow::Continue(round_duos) => round_duos.max(1),
+            ControlFlow::Break(stop_reason) => break stop_reason,
+        };
+        if let Some(samples) = out.samples.as_mut() {
+            // Each phase of the round executes `round_duos` duos of two pairs.
+            samples.reserve(round_duos * 4);
+        }
+
+        let mut state = DiffState::new(&mut out);
+        reporter.half_start(Half::Forward);
+        let flow = state.execute(|| duo(Phase::Forward), round_duos * 2, reporter, count)?;
+        if let ControlFlow::Break(stop_reason) = flow {
+            break stop_reason;
+        }
+        count += round_duos * 2;
+
+        let mut state_rev = state.reversed();
+        reporter.half_start(Half::Reversed);
+        let flow = state_rev.execute(|| duo(Phase::Reversed), round_duos * 2, reporter, count)?;
+        if let ControlFlow::Break(stop_reason) = flow {
+            break stop_reason;
+        }
+        count += round_duos * 2;
+
+        round = next_round(&out)?;
+    };
+
+    out.stop_reason = stop_reason;
+    check_timer_overhead(&mut out);
+    reporter.complete(out.n() as usize, out.stop_reason);
+    Ok(out)
+}
+
+/// Return type of the status closures (e.g., `warmup_status` and `exec_status` of [`bench_diff_x`]), which
+/// allows them to cancel the benchmark.
+///
+/// Status closures that return `()` never cancel the benchmark. Status closures that return
+/// [`ControlFlow::Break`] cancel it, e.g., when a time limit is reached or the user interrupts an interactive
+/// tool. A cancelled benchmark stops cleanly at the end of the current duo and returns the data collected so
+/// far, with [`StopReason::Cancelled`] as the stop reason (see [`DiffOut::is_truncated`]). As each duo executes
+/// both the pair (`f1`, `f2`) and the pair (`f2`, `f1`), the partial data remains balanced with respect to the
+/// order of execution. A benchmark cancelled during warm-up collects no data.
+pub trait StatusFlow {
+    /// Converts `self` into a [`ControlFlow`], where [`ControlFlow::Break`] requests that the benchmark stop.
+    fn into_flow(self) -> ControlFlow<()>;
+}
+
+impl StatusFlow for () {
+    fn into_flow(self) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+
+impl StatusFlow for ControlFlow<()> {
+    fn into_flow(self) -> ControlFlow<()> {
+        self
+    }
+}
+
+/// Compares latencies for two closures `f1` and `f2` and *optionally* outputs information about the benchmark
+/// and its execution status.
+///
+/// This function repeatedly executes *duos* of pairs (`f1`, `f2`), (`f2`, `f1`) and collects the resulting
+/// latency data in a [`DiffOut`] object.
+/// Prior to data collection, the benchmark is "warmed-up" by executing the duos of pairs for
+/// [`get_warmup_millis`] milliseconds. At the end of warm-up, the latency of an empty closure is measured to
+/// estimate the timer overhead (see [`DiffOut::timer_overhead`]), which is optionally subtracted from the
+/// collected latencies (see [`BenchDiff::subtract_timer_overhead`]).
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `warmup_status` - is invoked every so often during warm-up and can be used to output the warm-up status,
+///   e.g., how much warm-up time has elapsed and the target warm-up time. The first argument is the warm-up
+///   execution iteration, the second is the elapsed warm-up time, and the third is the target warm-up time.
+///   It can return `()` or a [`ControlFlow`], in which case [`ControlFlow::Break`] cancels the benchmark
+///   (see [`StatusFlow`]).
+/// - `pre_exec` - is invoked once at the beginning of data collection, after warm-up. It can be used,
+///   for example, to output a preamble to the execution status (see `exec_status` below).
+/// - `exec_status` - is invoked after the execution of each *duo* and can be used to output on the execution
+///   status, e.g., how many observations have been collected for the pair of functions versus `exec_count`.
+///   Its argument is the current number of executions performed. It can return `()` or a [`ControlFlow`], in
+///   which case [`ControlFlow::Break`] cancels the benchmark (see [`StatusFlow`]).
+///
+/// The status closures receive a subset of the events received by a [`Reporter`] (see [`ClosureReporter`]);
+/// [`bench_diff_with_reporter`] accepts any reporter.
+///
+/// This function is a thin wrapper around [`BenchDiff`], which supports additional configuration. Like the other
+/// free benchmarking functions, it always measures latencies with [`crate::InstantClock`]; use
+/// [`BenchDiff::clock`] to select another clock.
+pub fn bench_diff_x<W: StatusFlow, E: StatusFlow>(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+    warmup_status: impl FnMut(usize, u64, u64) -> W,
+    pre_exec: impl FnOnce(),
+    exec_status: impl FnMut(usize) -> E,
+) -> DiffOut {
+    BenchDiff::new(unit, exec_count)
+        .reporter(ClosureReporter::new(warmup_status, pre_exec, exec_status))
+        .run(f1, f2)
+}
+
+/// Compares latencies for two closures `f1` and `f2` and reports the progress of the benchmark to `reporter`.
+///
+/// This function is like [`bench_diff_x`] except that the events of the benchmark are reported to `reporter`
+/// instead of status closures.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `reporter` - receives the events of the benchmark (see [`Reporter`]).
+pub fn bench_diff_with_reporter(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+    reporter: &mut impl Reporter,
+) -> DiffOut {
+    BenchDiff::new(unit, exec_count)
+        .reporter(reporter)
+        .run(f1, f2)
+}
+
+/// Compares latencies for two closures `f1` and `f2`.
+///
+/// This function repeatedly executes *duos* of pairs (`f1`, `f2`), (`f2`, `f1`) and collects the resulting
+/// latency data in a [`DiffOut`] object.
+/// Prior to data collection, the benchmark is "warmed-up" by executing the duos of pairs for
+/// [`get_warmup_millis`] milliseconds.
+/// This function calls [`bench_diff_x`] with no-op closures for the arguments that support the output of
+/// benchmark status.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+pub fn bench_diff(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+) -> DiffOut {
+    bench_diff_x(unit, f1, f2, exec_count, |_, _, _| {}, || (), |_| ())
+}
+
+/// Fallible version of [`bench_diff_x`], which returns an error instead of panicking.
+///
+/// Returns [`BenchDiffError::InsufficientSamples`], before any execution, if `exec_count` would produce fewer
+/// than two observations for each function, and [`BenchDiffError::ZeroLatency`] if a latency of zero is
+/// measured. See [`BenchDiff::try_run`].
+pub fn try_bench_diff_x<W: StatusFlow, E: StatusFlow>(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+    warmup_status: impl FnMut(usize, u64, u64) -> W,
+    pre_exec: impl FnOnce(),
+    exec_status: impl FnMut(usize) -> E,
+) -> Result<DiffOut, BenchDiffError> {
+    BenchDiff::new(unit, exec_count)
+        .reporter(ClosureReporter::new(warmup_status, pre_exec, exec_status))
+        .try_run(f1, f2)
+}
+
+/// Fallible version of [`bench_diff`], which returns an error instead of panicking (see [`try_bench_diff_x`]).
+pub fn try_bench_diff(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+) -> Result<DiffOut, BenchDiffError> {
+    try_bench_diff_x(unit, f1, f2, exec_count, |_, _, _| {}, || (), |_| ())
+}
+
+/// Compares latencies for two closures `f1` and `f2`, executing the pairs in each duo in a pseudo-random order,
+/// and *optionally* outputs information about the benchmark and its execution status.
+///
+/// This function is like [`bench_diff_x`] except that, in each duo, the order of the pairs (`f1`, `f2`) and
+/// (`f2`, `f1`) is chosen pseudo-randomly from `seed` instead of following a fixed pattern. This prevents the
+/// execution schedule from aligning with periodic activity, such as timer ticks or garbage collection in the
+/// code under test. The choices for the second half of data collection mirror the choices for the first half,
+/// so each function starts exactly half of the duos and the ordering effect is cancelled as with the fixed
+/// order. The `seed` is recorded in the result (see [`DiffOut::duo_order`]), so the schedule can be
+/// reproduced.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `seed` - seed that determines the order of the pairs in each duo.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `warmup_status` - see [`bench_diff_x`].
+/// - `pre_exec` - see [`bench_diff_x`].
+/// - `exec_status` - see [`bench_diff_x`].
+///
+/// This function is a thin wrapper around [`BenchDiff`] with [`BenchDiff::seed`].
+pub fn bench_diff_randomized_x<W: StatusFlow, E: StatusFlow>(
+    unit: LatencyUnit,
+    seed: u64,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+    warmup_status: impl FnMut(usize, u64, u64) -> W,
+    pre_exec: impl FnOnce(),
+    exec_status: impl FnMut(usize) -> E,
+) -> DiffOut {
+    BenchDiff::new(unit, exec_count)
+        .seed(seed)
+        .reporter(ClosureReporter::new(warmup_status, pre_exec, exec_status))
+        .run(f1, f2)
+}
+
+/// Compares latencies for two closures `f1` and `f2`, executing the pairs in each duo in a pseudo-random order,
+/// and reports the progress of the benchmark to `reporter`.
+///
+/// This function is like [`bench_diff_randomized_x`] except that the events of the benchmark are reported to
+/// `reporter` (see [`Reporter`]) instead of status closures.
+pub fn bench_diff_randomized_with_reporter(
+    unit: LatencyUnit,
+    seed: u64,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+    reporter: &mut impl Reporter,
+) -> DiffOut {
+    BenchDiff::new(unit, exec_count)
+        .seed(seed)
+        .reporter(reporter)
+        .run(f1, f2)
+}
+
+/// Compares latencies for two closures `f1` and `f2`, executing the pairs in each duo in a pseudo-random order.
+///
+/// This function calls [`bench_diff_randomized_x`] with no-op closures for the arguments that support the
+/// output of benchmark status.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `seed` - seed that determines the order of the pairs in each duo.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+pub fn bench_diff_randomized(
+    unit: LatencyUnit,
+    seed: u64,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+) -> DiffOut {
+    bench_diff_randomized_x(unit, seed, f1, f2, exec_count, |_, _, _| {}, || (), |_| ())
+}
+
+/// Compares latencies for two closures `f1` and `f2` and outputs information about the benchmark and its
+/// execution status. Execution status is output to `stderr`.
+///
+/// This function repeatedly executes *duos* of pairs (`f1`, `f2`), (`f2`, `f1`) and collects the resulting
+/// latency data in a [`DiffOut`] object.
+/// Prior to data collection, the benchmark is "warmed-up" by executing the duos of pairs for
+/// [`get_warmup_millis`] milliseconds.
+/// Status is output with [`stderr_reporter`], which outputs a progress bar if `stderr` is a terminal and one
+/// line per event otherwise. Before the benchmark, the environment is checked for common sources of noise (see
+/// [`crate::environment_check`]); the resulting warnings are output to `stderr` and the result of the check is
+/// available from [`DiffOut::environment`].
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `header` - is invoked once at the start of this function's execution; it can be used, for example,
+///   to output information about the functions being compared to `stdout` and/or `stderr`. The first
+///   argument is the the `LatencyUnit` and the second argument is the `exec_count`.
+pub fn bench_diff_with_status(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+    header: impl FnOnce(LatencyUnit, usize),
+) -> DiffOut {
+    header(unit, exec_count);
+
+    BenchDiff::new(unit, exec_count)
+        .environment_check(true)
+        .reporter(stderr_reporter())
+        .run(f1, f2)
+}
+
+/// Fallible version of [`bench_diff_with_status`], which returns an error instead of panicking
+/// (see [`try_bench_diff_x`]).
+///
+/// Returns [`BenchDiffError::Io`] if the output of the execution status to `stderr` fails, in which case no
+/// further status is output and the benchmark runs to completion before the error is returned.
+pub fn try_bench_diff_with_status(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+    header: impl FnOnce(LatencyUnit, usize),
+) -> Result<DiffOut, BenchDiffError> {
+    header(unit, exec_count);
+
+    let mut reporter = stderr_reporter();
+    let out = BenchDiff::new(unit, exec_count)
+        .environment_check(true)
+        .reporter(&mut reporter)
+        .try_run(f1, f2)?;
+
+    match reporter.take_io_error() {
+        None => Ok(out),
+        Some(e) => Err(BenchDiffError::Io(e)),
+    }
+}
+
+/// Compares latencies for two closures `f1` and `f2`, executing the pairs in each duo in a pseudo-random order,
+/// and outputs information about the benchmark and its execution status. Execution status is output to `stderr`.
+///
+/// Status is output and the environment is checked as in [`bench_diff_with_status`].
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `seed` - seed that determines the order of the pairs in each duo.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `header` - see [`bench_diff_with_status`].
+pub fn bench_diff_randomized_with_status(
+    unit: LatencyUnit,
+    seed: u64,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+    header: impl FnOnce(LatencyUnit, usize),
+) -> DiffOut {
+    header(unit, exec_count);
+
+    BenchDiff::new(unit, exec_count)
+        .seed(seed)
+        .environment_check(true)
+        .reporter(stderr_reporter())
+        .run(f1, f2)
+}
+
+/// Invokes `setup` before each invocation of `f1` or `f2` and passes its result to that invocation, in the
+/// order `f1` then `f2` then `f2` then `f1`. Returns two pairs of latencies which do not include the
+/// latencies of `setup`. For each pair, the first component is an `f1` latency and the second component is an
+/// `f2` latency.
+#[inline(always)]
+fn duo_exec_with_setup<I>(
+    clock: ClockKind,
+    mut setup: impl FnMut() -> I,
+    mut f1: impl FnMut(I),
+    mut f2: impl FnMut(I),
+) -> [(Duration, Duration); 2] {
+    let l01 = latency_with_input(clock, setup(), &mut f1);
+    let l02 = latency_with_input(clock, setup(), &mut f2);
+
+    let l12 = latency_with_input(clock, setup(), &mut f2);
+    let l11 = latency_with_input(clock, setup(), &mut f1);
+
+    [(l01, l02), (l11, l12)]
+}
+
+/// [`DuoSource`] of two closures that take an input produced by a setup closure, executed by
+/// [`duo_exec_with_setup`].
+struct SetupDuos<S, F1, F2> {
+    setup: S,
+    f1: F1,
+    f2: F2,
+}
+
+impl<I, S: FnMut() -> I, F1: FnMut(I), F2: FnMut(I)> DuoSource for SetupDuos<S, F1, F2> {
+    fn duos<'a>(
+        &'a mut self,
+        clock: ClockKind,
+        panics: &'a impl Panics,
+        _tracker: Option<&'a AllocTracker>,
+    ) -> impl FnMut(Phase) -> DuoPairs + 'a {
+        let Self { setup, f1, f2 } = self;
+        let mut f1 = panics.wrap_input(TargetFn::F1, f1);
+        let mut f2 = panics.wrap_input(TargetFn::F2, f2);
+        move |phase| match phase {
+            Phase::Warmup | Phase::Forward => {
+                duo_exec_with_setup(clock, &mut *setup, &mut f1, &mut f2)
+            }
+            Phase::Reversed => duo_exec_with_setup(clock, &mut *setup, &mut f2, &mut f1),
+        }
+    }
+}
+
+/// Compares latencies for two closures `f1` and `f2` that take an input produced by `setup`, and *optionally*
+/// outputs information about the benchmark and its execution status.
+///
+/// This function is like [`bench_diff_x`] except that `setup` is invoked before each invocation of `f1` or `f2`
+/// and its result is passed as the argument of that invocation. The latency of `setup` is not included in the
+/// latencies collected for `f1` and `f2`, so this function is suitable for targets that need fresh input on
+/// each invocation (e.g., an unsorted `Vec` to be sorted). Notice that the latency of dropping the input is
+/// included in the latency of the target function that consumes it, unless the target function returns it
+/// or otherwise moves it out.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `setup` - produces the input for each invocation of `f1` or `f2`.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `warmup_status` - see [`bench_diff_x`].
+/// - `pre_exec` - see [`bench_diff_x`].
+/// - `exec_status` - see [`bench_diff_x`].
+pub fn bench_diff_with_setup_x<I, W: StatusFlow, E: StatusFlow>(
+    unit: LatencyUnit,
+    setup: impl FnMut() -> I,
+    f1: impl FnMut(I),
+    f2: impl FnMut(I),
+    exec_count: usize,
+    warmup_status: impl FnMut(usize, u64, u64) -> W,
+    pre_exec: impl FnOnce(),
+    exec_status: impl FnMut(usize) -> E,
+) -> DiffOut {
+    bench_diff_with_setup_and_reporter(
+        unit,
+        setup,
+        f1,
+        f2,
+        exec_count,
+        &mut ClosureReporter::new(warmup_status, pre_exec, exec_status),
+    )
+}
+
+/// Compares latencies for two closures `f1` and `f2` that take an input produced by `setup`, and reports the
+/// progress of the benchmark to `reporter`.
+///
+/// This function is like [`bench_diff_with_setup_x`] except that the events of the benchmark are reported to
+/// `reporter` instead of status closures.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `setup` - produces the input for each invocation of `f1` or `f2`.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `reporter` - receives the events of the benchmark (see [`Reporter`]).
+pub fn bench_diff_with_setup_and_reporter<I>(
+    unit: LatencyUnit,
+    setup: impl FnMut() -> I,
+    f1: impl FnMut(I),
+    f2: impl FnMut(I),
+    exec_count: usize,
+    reporter: &mut impl Reporter,
+) -> DiffOut {
+    BenchDiff::new(unit, exec_count)
+        .reporter(reporter)
+        .run_with_setup(setup, f1, f2)
+}
+
+/// Compares latencies for two closures `f1` and `f2` that take an input produced by `setup`.
+///
+/// This function calls [`bench_diff_with_setup_x`] with no-op closures for the arguments that support the
+/// output of benchmark status.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `setup` - produces the input for each invocation of `f1` or `f2`; its latency is not measured.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+pub fn bench_diff_with_setup<I>(
+    unit: LatencyUnit,
+    setup: impl FnMut() -> I,
+    f1: impl FnMut(I),
+    f2: impl FnMut(I),
+    exec_count: usize,
+) -> DiffOut {
+    bench_diff_with_setup_x(unit, setup, f1, f2, exec_count, |_, _, _| {}, || (), |_| ())
+}
+
+/// Compares latencies for two closures `f1` and `f2` that take an input produced by `setup`, and outputs
+/// information about the benchmark and its execution status. Execution status is output to `stderr`.
+///
+/// This function calls [`bench_diff_with_setup_and_reporter`] with [`stderr_reporter`] (see
+/// [`bench_diff_with_status`]).
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `setup` - produces the input for each invocation of `f1` or `f2`; its latency is not measured.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `header` - see [`bench_diff_with_status`].
+pub fn bench_diff_with_setup_and_status<I>(
+    unit: LatencyUnit,
+    setup: impl FnMut() -> I,
+    f1: impl FnMut(I),
+    f2: impl FnMut(I),
+    exec_count: usize,
+    header: impl FnOnce(LatencyUnit, usize),
+) -> DiffOut {
+    header(unit, exec_count);
+
+    bench_diff_with_setup_and_reporter(unit, setup, f1, f2, exec_count, &mut stderr_reporter())
+}
+
+/// Determines how often a new input is generated by the paired-input benchmarking functions
+/// (e.g., [`bench_diff_paired_input`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NewInputPer {
+    /// A new input is generated for each pair; both functions in the pair receive the same input.
+    Pair,
+    /// A new input is generated for each duo; all four executions in the duo receive the same input.
+    Duo,
+}
+
+/// Invokes `f1` then `f2` then `f2` then `f1`, passing to each invocation a clone of an input obtained from
+/// `next_input`. Both invocations in a pair receive the same input; a new input is obtained for each pair or
+/// for each duo according to `new_input_per`. Returns two pairs of latencies which do not include the
+/// latencies of input generation and cloning. For each pair, the first component is an `f1` latency and the
+/// second component is an `f2` latency.
+#[inline(always)]
+fn duo_exec_paired_input<I: Clone>(
+    clock: ClockKind,
+    mut next_input: impl FnMut() -> I,
+    new_input_per: NewInputPer,
+    mut f1: impl FnMut(I),
+    mut f2: impl FnMut(I),
+) -> [(Duration, Duration); 2] {
+    let input0 = next_input();
+    let input1 = match new_input_per {
+        NewInputPer::Pair => next_input(),
+        NewInputPer::Duo => input0.clone(),
+    };
+
+    let l01 = latency_with_input(clock, input0.clone(), &mut f1);
+    let l02 = latency_with_input(clock, input0, &mut f2);
+
+    let l12 = latency_with_input(clock, input1.clone(), &mut f2);
+    let l11 = latency_with_input(clock, input1, &mut f1);
+
+    [(l01, l02), (l11, l12)]
+}
+
+/// [`DuoSource`] of two closures that take the same generated inputs, executed by [`duo_exec_paired_input`].
+struct PairedInputDuos<G, F1, F2> {
+    seed: u64,
+    new_input_per: NewInputPer,
+    gen_input: G,
+    f1: F1,
+    f2: F2,
+}
+
+impl<I: Clone, G: FnMut(&mut SeededRng) -> I, F1: FnMut(I), F2: FnMut(I)> DuoSource
+    for PairedInputDuos<G, F1, F2>
+{
+    fn duos<'a>(
+        &'a mut self,
+        clock: ClockKind,
+        panics: &'a impl Panics,
+        _tracker: Option<&'a AllocTracker>,
+    ) -> impl FnMut(Phase) -> DuoPairs + 'a {
+        let Self {
+            seed,
+            new_input_per,
+            gen_input,
+            f1,
+            f2,
+        } = self;
+        let mut f1 = panics.wrap_input(TargetFn::F1, f1);
+        let mut f2 = panics.wrap_input(TargetFn::F2, f2);
+        let (seed, new_input_per) = (*seed, *new_input_per);
+        let mut rng = SeededRng::new(seed);
+        let mut prev_phase = Phase::Warmup;
+
+        move |phase| {
+            if prev_phase == Phase::Warmup && phase != Phase::Warmup {
+                rng = SeededRng::new(seed);
+            }
+            prev_phase = phase;
+
+            let next_input = || gen_input(&mut rng);
+            match phase {
+                Phase::Warmup | Phase::Forward => {
+                    duo_exec_paired_input(clock, next_input, new_input_per, &mut f1, &mut f2)
+                }
+                Phase::Reversed => {
+                    duo_exec_paired_input(clock, next_input, new_input_per, &mut f2, &mut f1)
+                }
+            }
+        }
+    }
+}
+
+/// Compares latencies for two closures `f1` and `f2` that take the same generated inputs, and *optionally*
+/// outputs information about the benchmark and its execution status.
+///
+/// This function is like [`bench_diff_x`] except that both functions in each pair receive a clone of the same
+/// input, produced by `gen_input`. This ensures that the paired statistics in [`DiffOut`] (e.g.,
+/// [`DiffOut::stdev_diff_ln_f1_f2`] and [`DiffOut::count_f1_lt_f2`]) reflect per-input differences between
+/// the functions. The latencies of input generation and cloning are not included in the latencies collected
+/// for `f1` and `f2`.
+///
+/// `gen_input` receives a [`SeededRng`] created from `seed`, which it can use to generate random inputs.
+/// The generator is re-seeded at the start of data collection, so the sequence of inputs used for data
+/// collection depends only on `seed`, not on the duration of the warm-up.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `seed` - seed for the [`SeededRng`] passed to `gen_input`.
+/// - `new_input_per` - whether a new input is generated for each pair or for each duo.
+/// - `gen_input` - generates an input.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `warmup_status` - see [`bench_diff_x`].
+/// - `pre_exec` - see [`bench_diff_x`].
+/// - `exec_status` - see [`bench_diff_x`].
+pub fn bench_diff_paired_input_x<I: Clone, W: StatusFlow, E: StatusFlow>(
+    unit: LatencyUnit,
+    seed: u64,
+    new_input_per: NewInputPer,
+    gen_input: impl FnMut(&mut SeededRng) -> I,
+    f1: impl FnMut(I),
+    f2: impl FnMut(I),
+    exec_count: usize,
+    warmup_status: impl FnMut(usize, u64, u64) -> W,
+    pre_exec: impl FnOnce(),
+    exec_status: impl FnMut(usize) -> E,
+) -> DiffOut {
+    bench_diff_paired_input_with_reporter(
+        unit,
+        seed,
+        new_input_per,
+        gen_input,
+        f1,
+        f2,
+        exec_count,
+        &mut ClosureReporter::new(warmup_status, pre_exec, exec_status),
+    )
+}
+
+/// Compares latencies for two closures `f1` and `f2` that take the same generated inputs, and reports the progress
+/// of the benchmark to `reporter`.
+///
+/// This function is like [`bench_diff_paired_input_x`] except that the events of the benchmark are reported to
+/// `reporter` instead of status closures.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `seed` - seed for the [`SeededRng`] passed to `gen_input`.
+/// - `new_input_per` - whether a new input is generated for each pair or for each duo.
+/// - `gen_input` - generates an input.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `reporter` - receives the events of the benchmark (see [`Reporter`]).
+pub fn bench_diff_paired_input_with_reporter<I: Clone>(
+    unit: LatencyUnit,
+    seed: u64,
+    new_input_per: NewInputPer,
+    gen_input: impl FnMut(&mut SeededRng) -> I,
+    f1: impl FnMut(I),
+    f2: impl FnMut(I),
+    exec_count: usize,
+    reporter: &mut impl Reporter,
+) -> DiffOut {
+    BenchDiff::new(unit, exec_count)
+        .reporter(reporter)
+        .run_paired_input(seed, new_input_per, gen_input, f1, f2)
+}
+
+/// Compares latencies for two closures `f1` and `f2` that take the same generated inputs.
+///
+/// This function calls [`bench_diff_paired_input_x`] with no-op closures for the arguments that support the
+/// output of benchmark status.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `seed` - seed for the [`SeededRng`] passed to `gen_input`.
+/// - `new_input_per` - whether a new input is generated for each pair or for each duo.
+/// - `gen_input` - generates an input; its latency is not measured.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+pub fn bench_diff_paired_input<I: Clone>(
+    unit: LatencyUnit,
+    seed: u64,
+    new_input_per: NewInputPer,
+    gen_input: impl FnMut(&mut SeededRng) -> I,
+    f1: impl FnMut(I),
+    f2: impl FnMut(I),
+    exec_count: usize,
+) -> DiffOut {
+    bench_diff_paired_input_x(
+        unit,
+        seed,
+        new_input_per,
+        gen_input,
+        f1,
+        f2,
+        exec_count,
+        |_, _, _| {},
+        || (),
+        |_| (),
+    )
+}
 
-    pub(crate) fn reset(&mut self) {
-        self.hist_f1.reset();
-        self.hist_f2.reset();
-        self.hist_f1_lt_f2.reset();
-        *self.count_f1_eq_f2 = 0;
-        self.hist_f1_gt_f2.reset();
-        *self.sum_f1 = 0;
-        *self.sum_f2 = 0;
-        *self.sum_ln_f1 = 0.;
-        *self.sum2_ln_f1 = 0.;
-        *self.sum_ln_f2 = 0.;
-        *self.sum2_ln_f2 = 0.;
-        *self.sum2_diff_f1_f2 = 0;
-        *self.sum2_diff_ln_f1_f2 = 0.;
-    }
+/// Compares latencies for two closures `f1` and `f2` that take the same generated inputs, and outputs
+/// information about the benchmark and its execution status. Execution status is output to `stderr`.
+///
+/// This function calls [`bench_diff_paired_input_with_reporter`] with [`stderr_reporter`] (see
+/// [`bench_diff_with_status`]).
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `seed` - seed for the [`SeededRng`] passed to `gen_input`.
+/// - `new_input_per` - whether a new input is generated for each pair or for each duo.
+/// - `gen_input` - generates an input; its latency is not measured.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `header` - see [`bench_diff_with_status`].
+pub fn bench_diff_paired_input_with_status<I: Clone>(
+    unit: LatencyUnit,
+    seed: u64,
+    new_input_per: NewInputPer,
+    gen_input: impl FnMut(&mut SeededRng) -> I,
+    f1: impl FnMut(I),
+    f2: impl FnMut(I),
+    exec_count: usize,
+    header: impl FnOnce(LatencyUnit, usize),
+) -> DiffOut {
+    header(unit, exec_count);
 
-    /// Updates the state with an elapsed time for each function.
-    #[inline(always)]
-    pub(crate) fn capture_data(&mut self, elapsed1: u64, elapsed2: u64) {
-        self.hist_f1
-            .record(elapsed1)
-            .expect("can't happen: histogram is auto-resizable");
-        self.hist_f2
-            .record(elapsed2)
-            .expect("can't happen: histogram is auto-resizable");
+    bench_diff_paired_input_with_reporter(
+        unit,
+        seed,
+        new_input_per,
+        gen_input,
+        f1,
+        f2,
+        exec_count,
+        &mut stderr_reporter(),
+    )
+}
 
-        let diff = elapsed1 as i64 - elapsed2 as i64;
+/// Minimum latency of a batch of calls of the faster function targeted by [`calibrate_batch_size`].
+const BATCH_TARGET_LATENCY: Duration = Duration::from_micros(10);
 
-        match diff.cmp(&0) {
-            cmp::Ordering::Less => self
-                .hist_f1_lt_f2
-                .record(diff as u64)
-                .expect("can't happen: histogram is auto-resizable"),
-            cmp::Ordering::Greater => self
-                .hist_f1_gt_f2
-                .record(-diff as u64)
-                .expect("can't happen: histogram is auto-resizable"),
-            cmp::Ordering::Equal => *self.count_f1_eq_f2 += 1,
-        }
+/// Maximum batch size returned by [`calibrate_batch_size`].
+const MAX_BATCH_SIZE: u32 = 1 << 20;
 
-        assert!(elapsed1 > 0, "f1 latency must be > 0");
-        *self.sum_f1 += elapsed1 as i64;
-        let ln_f1 = (elapsed1 as f64).ln();
-        *self.sum_ln_f1 += ln_f1;
-        *self.sum2_ln_f1 += ln_f1.powi(2);
+/// Number of times each batch is timed in each step of [`calibrate_batch_size`].
+const BATCH_CALIBRATION_REPEATS: usize = 3;
 
-        assert!(elapsed2 > 0, "f2 latency must be > 0");
-        *self.sum_f2 += elapsed2 as i64;
-        let ln_f2 = (elapsed2 as f64).ln();
-        *self.sum_ln_f2 += ln_f2;
-        *self.sum2_ln_f2 += ln_f2.powi(2);
+/// Returns the batch size to be used for both `f1` and `f2` in batched mode.
+///
+/// Starting from `1`, the batch size is doubled until the minimum observed latency of a batch of the faster
+/// function reaches [`BATCH_TARGET_LATENCY`], which is well above the overhead of reading the timer, or until
+/// the batch size reaches [`MAX_BATCH_SIZE`].
+fn calibrate_batch_size(clock: ClockKind, mut f1: impl FnMut(), mut f2: impl FnMut()) -> u32 {
+    let mut batch_size = 1;
+    while batch_size < MAX_BATCH_SIZE {
+        let mut min_latency = Duration::MAX;
+        for _ in 0..BATCH_CALIBRATION_REPEATS {
+            min_latency = min_latency
+                .min(latency_batch(clock, batch_size, &mut f1))
+                .min(latency_batch(clock, batch_size, &mut f2));
+        }
+        if min_latency >= BATCH_TARGET_LATENCY {
+            break;
+        }
+        batch_size *= 2;
+    }
+    batch_size
+}
 
-        let diff_f1_f2 = elapsed1 as i64 - elapsed2 as i64;
-        *self.sum2_diff_f1_f2 += diff_f1_f2.pow(2);
+/// Invokes a batch of `f1` then a batch of `f2` then a batch of `f2` then a batch of `f1`, each batch consisting
+/// of `batch_size` consecutive calls, and returns two pairs of total latencies of the batches. For each pair,
+/// the first component is an `f1` latency and the second component is an `f2` latency.
+#[inline(always)]
+fn duo_exec_batched(
+    clock: ClockKind,
+    batch_size: u32,
+    mut f1: impl FnMut(),
+    mut f2: impl FnMut(),
+) -> [(Duration, Duration); 2] {
+    let l01 = latency_batch(clock, batch_size, &mut f1);
+    let l02 = latency_batch(clock, batch_size, &mut f2);
 
-        let diff_ln_f1_f2 = ln_f1 - ln_f2;
-        *self.sum2_diff_ln_f1_f2 += diff_ln_f1_f2.powi(2);
-    }
+    let l12 = latency_batch(clock, batch_size, &mut f2);
+    let l11 = latency_batch(clock, batch_size, &mut f1);
 
-    /// Executes `f1` and `f2` repeatedly, using [`duo_exec`] `exec_count / 2` times, and captures their latencies.
-    /// `pre_exec` is invoked once just before the invocations of `f1` and `f2`, and `exec_status` is invoked at the
-    /// end of each iteration with [`duo_exec`].
-    fn execute(
-        &mut self,
-        unit: LatencyUnit,
-        mut f1: impl FnMut(),
-        mut f2: impl FnMut(),
-        exec_count: usize,
-        pre_exec: impl FnOnce(),
-        mut exec_status: impl FnMut(usize),
-        init_status_count: usize,
-    ) {
-        pre_exec();
+    [(l01, l02), (l11, l12)]
+}
 
-        for i in 1..=exec_count / 2 {
-            let pairs = duo_exec(&mut f1, &mut f2);
+/// [`DuoSource`] of two very fast closures, executed in batches by [`duo_exec_batched`].
+struct BatchedDuos<F1, F2> {
+    f1: F1,
+    f2: F2,
+    /// Batch size used for the last duo executed.
+    batch_size: u32,
+}
 
-            for (latency1, latency2) in pairs {
-                let elapsed1 = unit.latency_as_u64(latency1);
-                let elapsed2 = unit.latency_as_u64(latency2);
-                self.capture_data(elapsed1, elapsed2);
+impl<F1: FnMut(), F2: FnMut()> DuoSource for BatchedDuos<F1, F2> {
+    fn duos<'a>(
+        &'a mut self,
+        clock: ClockKind,
+        panics: &'a impl Panics,
+        _tracker: Option<&'a AllocTracker>,
+    ) -> impl FnMut(Phase) -> DuoPairs + 'a {
+        let Self { f1, f2, batch_size } = self;
+        let mut f1 = panics.wrap(TargetFn::F1, f1);
+        let mut f2 = panics.wrap(TargetFn::F2, f2);
+        // Provisional batch size for warm-up, chosen while the functions are still cold.
+        *batch_size = calibrate_batch_size(clock, &mut f1, &mut f2);
+        let mut prev_phase = Phase::Warmup;
+
+        move |phase| {
+            if prev_phase == Phase::Warmup && phase != Phase::Warmup {
+                *batch_size = calibrate_batch_size(clock, &mut f1, &mut f2);
             }
+            prev_phase = phase;
 
-            // `i * 2` to account for duos
-            exec_status(init_status_count + i * 2);
-        }
-    }
-
-    /// Warms-up the benchmark by invoking [`Self::execute`] repeatedly, each time with an `exec_count` value of
-    /// [`WARMUP_INCREMENT_COUNT`], until the globally set number of warm-up millisecods [`WARMUP_MILLIS`] is
-    /// reached or exceeded. `warmup_status` is invoked at the end of each invocation of [`Self::execute`].
-    fn warmup(
-        &mut self,
-        unit: LatencyUnit,
-        mut f1: impl FnMut(),
-        mut f2: impl FnMut(),
-        mut warmup_status: impl FnMut(usize, u64, u64),
-    ) {
-        let warmup_millis = get_warmup_millis();
-        let start = Instant::now();
-        for i in 1.. {
-            self.execute(
-                unit,
-                &mut f1,
-                &mut f2,
-                WARMUP_INCREMENT_COUNT,
-                || {},
-                |_| {},
-                0,
-            );
-            let elapsed = Instant::now().duration_since(start);
-            warmup_status(i, elapsed.as_millis() as u64, warmup_millis);
-            if elapsed.ge(&Duration::from_millis(warmup_millis)) {
-                break;
+            match phase {
+                Phase::Warmup | Phase::Forward => {
+                    duo_exec_batched(clock, *batch_size, &mut f1, &mut f2)
+                }
+                Phase::Reversed => duo_exec_batched(clock, *batch_size, &mut f2, &mut f1),
             }
         }
     }
 }
 
-/// Compares latencies for two closures `f1` and `f2` and *optionally* outputs information about the benchmark
-/// and its execution status.
+/// Compares latencies for two very fast closures `f1` and `f2` by timing batches of calls, and *optionally*
+/// outputs information about the benchmark and its execution status.
 ///
-/// This function repeatedly executes *duos* of pairs (`f1`, `f2`), (`f2`, `f1`) and collects the resulting
-/// latency data in a [`DiffOut`] object.
-/// Prior to data collection, the benchmark is "warmed-up" by executing the duos of pairs for
-/// [`get_warmup_millis`] milliseconds.
+/// This function is like [`bench_diff_x`] except that each recorded latency is the total latency of a batch of
+/// consecutive calls of the same function. This amortizes the overhead and limited resolution of the timer, which
+/// otherwise dominate the measurements of functions with latencies of a few nanoseconds.
+///
+/// The batch size is chosen automatically, such that each batch of the faster function takes several
+/// microseconds. A provisional batch size is chosen before warm-up, and the batch size is chosen again once the
+/// functions are warm, at the start of data collection. The same batch size is used for both functions, so the
+/// results remain comparable, and it is recorded in the output (see [`DiffOut::batch_size`]). The statistics
+/// with a time dimension are divided by the batch size when they are presented, so they are per call, with
+/// sub-nanosecond resolution.
 ///
 /// Arguments:
-/// - `unit` - the unit used for data collection.
+/// - `unit` - the unit used to present latency statistics.
 /// - `f1` - first target for comparison.
 /// - `f2` - second target for comparison.
-/// - `exec_count` - number of executions (sample size) for each function.
-/// - `warmup_status` - is invoked every so often during warm-up and can be used to output the warm-up status,
-///   e.g., how much warm-up time has elapsed and the target warm-up time. The first argument is the warm-up
-///   execution iteration, the second is the elapsed warm-up time, and the third is the target warm-up time.
-///   (See the source code of [`bench_diff_with_status`] for an example.)
-/// - `pre_exec` - is invoked once at the beginning of data collection, after warm-up. It can be used,
-///   for example, to output a preamble to the execution status (see `exec_status` below).
-/// - `exec_status` - is invoked after the execution of each *duo* and can be used to output on the execution
-///   status, e.g., how many observations have been collected for the pair of functions versus `exec_count`.
-///   Its argument is the current number of executions performed.
-///   (See the source code of [`bench_diff_with_status`] for an example.)
-pub fn bench_diff_x(
+/// - `exec_count` - number of batches (sample size) for each function.
+/// - `warmup_status` - see [`bench_diff_x`].
+/// - `pre_exec` - see [`bench_diff_x`].
+/// - `exec_status` - see [`bench_diff_x`].
+pub fn bench_diff_batched_x<W: StatusFlow, E: StatusFlow>(
     unit: LatencyUnit,
-    mut f1: impl FnMut(),
-    mut f2: impl FnMut(),
+    f1: impl FnMut(),
+    f2: impl FnMut(),
     exec_count: usize,
-    mut warmup_status: impl FnMut(usize, u64, u64),
+    warmup_status: impl FnMut(usize, u64, u64) -> W,
     pre_exec: impl FnOnce(),
-    mut exec_status: impl FnMut(usize),
+    exec_status: impl FnMut(usize) -> E,
 ) -> DiffOut {
-    let exec_count2 = exec_count / 2;
-
-    let mut out = DiffOut::new();
-
-    let mut state = DiffState::new(&mut out);
-    state.warmup(unit, &mut f1, &mut f2, &mut warmup_status);
-    state.reset();
-
-    state.execute(
+    bench_diff_batched_with_reporter(
         unit,
-        &mut f1,
-        &mut f2,
-        exec_count2,
-        pre_exec,
-        &mut exec_status,
-        0,
-    );
-
-    let mut state_rev = state.reversed();
-    state_rev.execute(
-        unit,
-        &mut f2,
-        &mut f1,
-        exec_count2,
-        || (),
-        &mut exec_status,
-        exec_count2,
-    );
+        f1,
+        f2,
+        exec_count,
+        &mut ClosureReporter::new(warmup_status, pre_exec, exec_status),
+    )
+}
 
-    out
+/// Compares latencies for two very fast closures `f1` and `f2` by timing batches of calls, and reports the progress
+/// of the benchmark to `reporter`.
+///
+/// This function is like [`bench_diff_batched_x`] except that the events of the benchmark are reported to
+/// `reporter` instead of status closures.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of batches (sample size) for each function.
+/// - `reporter` - receives the events of the benchmark (see [`Reporter`]).
+pub fn bench_diff_batched_with_reporter(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+    reporter: &mut impl Reporter,
+) -> DiffOut {
+    BenchDiff::new(unit, exec_count)
+        .reporter(reporter)
+        .run_batched(f1, f2)
 }
 
-/// Compares latencies for two closures `f1` and `f2`.
+/// Compares latencies for two very fast closures `f1` and `f2` by timing batches of calls.
 ///
-/// This function repeatedly executes *duos* of pairs (`f1`, `f2`), (`f2`, `f1`) and collects the resulting
-/// latency data in a [`DiffOut`] object.
-/// Prior to data collection, the benchmark is "warmed-up" by executing the duos of pairs for
-/// [`get_warmup_millis`] milliseconds.
-/// This function calls [`bench_diff_x`] with no-op closures for the arguments that support the output of
+/// This function calls [`bench_diff_batched_x`] with no-op closures for the arguments that support the output of
 /// benchmark status.
 ///
 /// Arguments:
-/// - `unit` - the unit used for data collection.
+/// - `unit` - the unit used to present latency statistics.
 /// - `f1` - first target for comparison.
 /// - `f2` - second target for comparison.
-/// - `exec_count` - number of executions (sample size) for each function.
-pub fn bench_diff(
+/// - `exec_count` - number of batches (sample size) for each function.
+pub fn bench_diff_batched(
     unit: LatencyUnit,
     f1: impl FnMut(),
     f2: impl FnMut(),
     exec_count: usize,
 ) -> DiffOut {
-    bench_diff_x(unit, f1, f2, exec_count, |_, _, _| {}, || (), |_| ())
+    bench_diff_batched_x(unit, f1, f2, exec_count, |_, _, _| {}, || (), |_| ())
 }
 
-/// Compares latencies for two closures `f1` and `f2` and outputs information about the benchmark and its
-/// execution status. Execution status is output to `stderr`.
+/// Compares latencies for two very fast closures `f1` and `f2` by timing batches of calls, and outputs
+/// information about the benchmark and its execution status. Execution status is output to `stderr`.
 ///
-/// This function repeatedly executes *duos* of pairs (`f1`, `f2`), (`f2`, `f1`) and collects the resulting
-/// latency data in a [`DiffOut`] object.
-/// Prior to data collection, the benchmark is "warmed-up" by executing the duos of pairs for
-/// [`get_warmup_millis`] milliseconds.
-/// This function calls [`bench_diff_x`] with pre-defined closures for the arguments that support the output of
-/// benchmark status to `stderr`.
+/// This function calls [`bench_diff_batched_with_reporter`] with [`stderr_reporter`] (see
+/// [`bench_diff_with_status`]).
 ///
 /// Arguments:
-/// - `unit` - the unit used for data collection.
+/// - `unit` - the unit used to present latency statistics.
 /// - `f1` - first target for comparison.
 /// - `f2` - second target for comparison.
-/// - `exec_count` - number of executions (sample size) for each function.
-/// - `header` - is invoked once at the start of this function's execution; it can be used, for example,
-///   to output information about the functions being compared to `stdout` and/or `stderr`. The first
-///   argument is the the `LatencyUnit` and the second argument is the `exec_count`.
-pub fn bench_diff_with_status(
+/// - `exec_count` - number of batches (sample size) for each function.
+/// - `header` - see [`bench_diff_with_status`].
+pub fn bench_diff_batched_with_status(
     unit: LatencyUnit,
     f1: impl FnMut(),
     f2: impl FnMut(),
@@ -367,52 +1811,105 @@ pub fn bench_diff_with_status(
 ) -> DiffOut {
     header(unit, exec_count);
 
-    let warmup_status = {
-        let mut status_len: usize = 0;
+    bench_diff_batched_with_reporter(unit, f1, f2, exec_count, &mut stderr_reporter())
+}
 
-        move |_: usize, elapsed_millis: u64, warmup_millis: u64| {
-            if status_len == 0 {
-                eprint!("Warming up ... ");
-                stderr().flush().expect("unexpected I/O error");
-            }
-            eprint!("{}", "\u{8}".repeat(status_len));
-            let status = format!("{elapsed_millis} millis of {warmup_millis}.");
-            if elapsed_millis.lt(&warmup_millis) {
-                status_len = status.len();
-            } else {
-                status_len = 0; // reset status in case of multiple warm-up phases
-            };
-            eprint!("{status}");
-            stderr().flush().expect("unexpected I/O error");
-        }
-    };
+impl BenchDiff<'_> {
+    /// Compares latencies for two closures `f1` and `f2` that take an input produced by `setup`, according to the
+    /// configuration of `self` (see [`bench_diff_with_setup_x`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a latency of zero is measured (see [`BenchDiffError::ZeroLatency`]).
+    pub fn run_with_setup<I>(
+        &mut self,
+        setup: impl FnMut() -> I,
+        f1: impl FnMut(I),
+        f2: impl FnMut(I),
+    ) -> DiffOut {
+        expect_ok(self.try_run_with_setup(setup, f1, f2))
+    }
 
-    let pre_exec = || {
-        eprint!(" Executing bench_diff ... ");
-        stderr().flush().expect("unexpected I/O error");
-    };
+    /// Fallible version of [`Self::run_with_setup`], which returns an error instead of panicking (see
+    /// [`Self::try_run`]).
+    pub fn try_run_with_setup<I>(
+        &mut self,
+        setup: impl FnMut() -> I,
+        f1: impl FnMut(I),
+        f2: impl FnMut(I),
+    ) -> Result<DiffOut, BenchDiffError> {
+        self.check_exec_count()?;
+        self.collect_fixed(&mut SetupDuos { setup, f1, f2 })
+    }
 
-    let exec_status = {
-        let mut status_len: usize = 0;
+    /// Compares latencies for two closures `f1` and `f2` that take the same generated inputs, according to the
+    /// configuration of `self` (see [`bench_diff_paired_input_x`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a latency of zero is measured (see [`BenchDiffError::ZeroLatency`]).
+    pub fn run_paired_input<I: Clone>(
+        &mut self,
+        seed: u64,
+        new_input_per: NewInputPer,
+        gen_input: impl FnMut(&mut SeededRng) -> I,
+        f1: impl FnMut(I),
+        f2: impl FnMut(I),
+    ) -> DiffOut {
+        expect_ok(self.try_run_paired_input(seed, new_input_per, gen_input, f1, f2))
+    }
 
-        move |i| {
-            eprint!("{}", "\u{8}".repeat(status_len));
-            let status = format!("{i} of {exec_count}.");
-            status_len = status.len();
-            eprint!("{status}");
-            stderr().flush().expect("unexpected I/O error");
-        }
-    };
+    /// Fallible version of [`Self::run_paired_input`], which returns an error instead of panicking (see
+    /// [`Self::try_run`]).
+    pub fn try_run_paired_input<I: Clone>(
+        &mut self,
+        seed: u64,
+        new_input_per: NewInputPer,
+        gen_input: impl FnMut(&mut SeededRng) -> I,
+        f1: impl FnMut(I),
+        f2: impl FnMut(I),
+    ) -> Result<DiffOut, BenchDiffError> {
+        self.check_exec_count()?;
+        let mut source = PairedInputDuos {
+            seed,
+            new_input_per,
+            gen_input,
+            f1,
+            f2,
+        };
+        self.collect_fixed(&mut source)
+    }
 
-    bench_diff_x(
-        unit,
-        f1,
-        f2,
-        exec_count,
-        warmup_status,
-        pre_exec,
-        exec_status,
-    )
+    /// Compares latencies for two very fast closures `f1` and `f2` by timing batches of calls, according to the
+    /// configuration of `self` (see [`bench_diff_batched_x`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the latency of a batch is zero nanoseconds (see [`BenchDiffError::ZeroLatency`]).
+    pub fn run_batched(&mut self, f1: impl FnMut(), f2: impl FnMut()) -> DiffOut {
+        expect_ok(self.try_run_batched(f1, f2))
+    }
+
+    /// Fallible version of [`Self::run_batched`], which returns an error instead of panicking (see
+    /// [`Self::try_run`]).
+    pub fn try_run_batched(
+        &mut self,
+        f1: impl FnMut(),
+        f2: impl FnMut(),
+    ) -> Result<DiffOut, BenchDiffError> {
+        self.check_exec_count()?;
+        let mut source = BatchedDuos {
+            f1,
+            f2,
+            batch_size: 1,
+        };
+        let mut out = self.collect_fixed(&mut source)?;
+
+        // The latencies are recorded as totals, so the batch size is only needed for their presentation.
+        out.batch_size = source.batch_size as u64;
+        check_timer_overhead(&mut out);
+        Ok(out)
+    }
 }
 
 #[cfg(test)]
@@ -534,7 +2031,9 @@ mod test {
 
         for _ in 1..=exec_count {
             let (elapsed1, elapsed2) = (f1() as u64, f2() as u64);
-            state.capture_data(elapsed1, elapsed2);
+            state
+                .capture_data(elapsed1, elapsed2)
+                .expect("latencies must be > 0");
         }
 
         out
@@ -748,4 +2247,242 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_setup_excluded() {
+        let setup_count = std::cell::Cell::new(0_usize);
+        let call_count = std::cell::Cell::new(0_usize);
+
+        let setup = || {
+            std::thread::sleep(std::time::Duration::from_micros(100));
+            setup_count.set(setup_count.get() + 1);
+            setup_count.get()
+        };
+        let f = |input: usize| {
+            assert_eq!(
+                input,
+                setup_count.get(),
+                "input must come from the latest setup"
+            );
+            call_count.set(call_count.get() + 1);
+        };
+
+        let out = bench_diff_with_setup(LatencyUnit::Nano, setup, f, f, 100);
+
+        assert_eq!(setup_count.get(), call_count.get());
+        assert!(out.median_f1() < 50_000., "median_f1={}", out.median_f1());
+        assert!(out.median_f2() < 50_000., "median_f2={}", out.median_f2());
+    }
+
+    fn paired_inputs(seed: u64, new_input_per: NewInputPer) -> (Vec<u64>, Vec<u64>) {
+        let collecting = std::cell::Cell::new(false);
+        let inputs1 = std::cell::RefCell::new(Vec::new());
+        let inputs2 = std::cell::RefCell::new(Vec::new());
+
+        bench_diff_paired_input_x(
+            LatencyUnit::Nano,
+            seed,
+            new_input_per,
+            |rng| rng.next_u64(),
+            |input| {
+                if collecting.get() {
+                    inputs1.borrow_mut().push(input);
+                }
+            },
+            |input| {
+                if collecting.get() {
+                    inputs2.borrow_mut().push(input);
+                }
+            },
+            100,
+            |_, _, _| {},
+            || collecting.set(true),
+            |_| (),
+        );
+
+        (inputs1.into_inner(), inputs2.into_inner())
+    }
+
+    #[test]
+    fn test_paired_input() {
+        for (new_input_per, distinct_duos) in [(NewInputPer::Pair, 50), (NewInputPer::Duo, 0)] {
+            let (inputs1, inputs2) = paired_inputs(42, new_input_per);
+            assert_eq!(inputs1.len(), 100, "{new_input_per:?}");
+            assert_eq!(inputs1, inputs2, "{new_input_per:?}");
+
+            let count = inputs1.chunks(2).filter(|duo| duo[0] != duo[1]).count();
+            assert_eq!(count, distinct_duos, "{new_input_per:?}");
+
+            assert_eq!(paired_inputs(42, new_input_per), (inputs1.clone(), inputs2));
+            assert_ne!(
+                paired_inputs(43, new_input_per).0,
+                inputs1,
+                "{new_input_per:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_batched() {
+        let collecting = std::cell::Cell::new(false);
+        let calls1 = std::cell::Cell::new(0_u64);
+        let calls2 = std::cell::Cell::new(0_u64);
+        let count = |calls: &std::cell::Cell<u64>| {
+            std::hint::black_box((0..10_u64).sum::<u64>());
+            if collecting.get() {
+                calls.set(calls.get() + 1);
+            }
+        };
+
+        let out = bench_diff_batched_x(
+            LatencyUnit::Nano,
+            || count(&calls1),
+            || count(&calls2),
+            100,
+            |_, _, _| {},
+            || collecting.set(true),
+            |_| (),
+        );
+
+        let batch_size = out.batch_size();
+        assert!(batch_size > 1, "batch_size={batch_size}");
+        // The batch size is recalibrated at the start of data collection, timing batches of sizes
+        // 1, 2, 4, ..., `batch_size`, `BATCH_CALIBRATION_REPEATS` times each.
+        let calibration_calls = BATCH_CALIBRATION_REPEATS as u64 * (2 * batch_size - 1);
+        assert_eq!(calls1.get(), out.n() * batch_size + calibration_calls);
+        assert_eq!(calls2.get(), out.n() * batch_size + calibration_calls);
+
+        let slow = || std::thread::sleep(std::time::Duration::from_micros(20));
+        let out = bench_diff_batched(LatencyUnit::Nano, slow, slow, 100);
+        assert_eq!(out.batch_size(), 1);
+    }
+
+    #[test]
+    fn test_batched_calibrated_when_warm() {
+        // The functions are slow for their first calls, as if they had to fill caches.
+        let calls = std::cell::Cell::new(0_u64);
+        let f = || {
+            calls.set(calls.get() + 1);
+            if calls.get() <= 20 {
+                std::thread::sleep(std::time::Duration::from_micros(20));
+            } else {
+                std::hint::black_box((0..10_u64).sum::<u64>());
+            }
+        };
+
+        let out = BenchDiff::new(LatencyUnit::Nano, 100)
+            .warmup_millis(1)
+            .run_batched(f, f);
+
+        assert!(out.batch_size() > 1, "batch_size={}", out.batch_size());
+        assert!(out.median_f1() < 10_000., "median_f1={}", out.median_f1());
+    }
+
+    #[test]
+    fn test_batched_sub_nanosecond() {
+        // In release builds, empty closures take a fraction of a nanosecond per call.
+        let out = BenchDiff::new(LatencyUnit::Nano, 1000)
+            .warmup_millis(1)
+            .try_run_batched(|| (), || ())
+            .expect("batch latencies are not zero");
+
+        assert!(out.batch_size() > 1, "batch_size={}", out.batch_size());
+        assert!(out.median_f1() > 0., "median_f1={}", out.median_f1());
+        assert!(out.median_f2() > 0., "median_f2={}", out.median_f2());
+        assert!(out.mean_ln_f1().is_finite());
+        let ratio = out.ratio_medians_f1_f2();
+        assert!(ratio > 0.5 && ratio < 2., "ratio={ratio}");
+    }
+
+    #[test]
+    fn test_randomized() {
+        fn run(seed: u64, duo_count: usize) -> Vec<bool> {
+            let f1_latency = Duration::from_nanos(1);
+            let f2_latency = Duration::from_nanos(2);
+            let mut f1_starts = Vec::new();
+
+            let mut duo = randomized(seed, |phase| match phase {
+                Phase::Warmup | Phase::Forward => {
+                    f1_starts.push(true);
+                    [(f1_latency, f2_latency); 2]
+                }
+                Phase::Reversed => {
+                    f1_starts.push(false);
+                    [(f2_latency, f1_latency); 2]
+                }
+            });
+
+            for _ in 0..3 {
+                assert_eq!(duo(Phase::Warmup), [(f1_latency, f2_latency); 2]);
+            }
+            for _ in 0..duo_count {
+                assert_eq!(duo(Phase::Forward), [(f1_latency, f2_latency); 2]);
+            }
+            for _ in 0..duo_count {
+                assert_eq!(duo(Phase::Reversed), [(f2_latency, f1_latency); 2]);
+            }
+
+            drop(duo);
+            f1_starts.split_off(3)
+        }
+
+        let duo_count = 100;
+        let f1_starts = run(42, duo_count);
+        let (first_half, second_half) = f1_starts.split_at(duo_count);
+
+        assert!(first_half.iter().any(|&b| b), "f1 must start some duos");
+        assert!(first_half.iter().any(|&b| !b), "f2 must start some duos");
+        assert!(
+            first_half.iter().zip(second_half).all(|(b1, b2)| b1 != b2),
+            "second half must mirror first half"
+        );
+        assert_eq!(
+            f1_starts,
+            run(42, duo_count),
+            "same seed must produce same order"
+        );
+        assert_ne!(
+            f1_starts,
+            run(43, duo_count),
+            "different seeds must produce different orders"
+        );
+    }
+
+    #[test]
+    fn test_timer_overhead_subtraction() {
+        let mut out = DiffOut::new();
+        out.timer_overhead_subtracted = 30;
+        let mut state = DiffState::new(&mut out);
+        for (latency1, latency2) in [(100, 20), (30, 31), (10, 130)] {
+            state
+                .capture_latencies(
+                    Duration::from_nanos(latency1),
+                    Duration::from_nanos(latency2),
+                )
+                .expect("latencies are never recorded as 0");
+        }
+
+        // Latencies not greater than the overhead are recorded as 1 ns.
+        assert_eq!((out.sum_f1, out.sum_f2), (70 + 1 + 1, 1 + 1 + 100));
+        assert_eq!(out.summary_f1().min, 1);
+        assert_eq!(out.summary_f2().min, 1);
+    }
+
+    #[test]
+    fn test_timer_overhead() {
+        let near_timer_overhead = |out: &DiffOut| {
+            out.warnings()
+                .iter()
+                .any(|w| matches!(w, Warning::NearTimerOverhead { .. }))
+        };
+
+        let out = bench_diff(LatencyUnit::Nano, || (), || (), 100);
+        assert!(out.timer_overhead().median > 0);
+        assert!(near_timer_overhead(&out), "warnings={:?}", out.warnings());
+
+        let sleep = || std::thread::sleep(Duration::from_micros(100));
+        let out = bench_diff(LatencyUnit::Nano, sleep, sleep, 100);
+        assert_eq!(out.timer_overhead_subtracted(), Duration::ZERO);
+        assert!(!near_timer_overhead(&out), "warnings={:?}", out.warnings());
+    }
 }
@@ -0,0 +1,351 @@
+//! Module implementing functions to compare the difference in latency between two closures, with a sample size
+//! that adapts to reach a target precision.
+
+use super::{
+    BenchDiff, BenchDiffError, ClosureReporter, DiffOut, LatencyUnit, Reporter, StatusFlow,
+    StopReason,
+    bench_diff::{PlainDuos, bench_duos_rounds_x, expect_ok},
+    stderr_reporter,
+};
+use basic_stats::normal::z_alpha;
+use std::{
+    ops::ControlFlow,
+    time::{Duration, Instant},
+};
+
+/// Minimum number of duos per phase in each data collection round of [`bench_diff_adaptive_x`].
+const MIN_ROUND_DUOS: usize = 10;
+
+/// Target precision and limits for the adaptive benchmarking functions (e.g., [`bench_diff_adaptive`]).
+#[derive(Clone, Copy, Debug)]
+pub struct PrecisionTarget {
+    /// Target relative half-width of the Welch confidence interval for the ratio of medians
+    /// (see [`DiffOut::welch_ratio_ci_rel_half_width`]). For example, `0.005` targets a precision of ±0.5%.
+    pub rel_half_width: f64,
+    /// The confidence level of the confidence interval is `(1 - alpha)`.
+    pub alpha: f64,
+    /// Maximum number of executions (sample size) for each function.
+    pub max_exec_count: usize,
+    /// Maximum duration of data collection, excluding warm-up.
+    pub max_duration: Duration,
+}
+
+/// Estimated number of executions for each function required to reach `target`, based on the standard deviations
+/// of the natural logarithms of the latencies in `out`. Returns an error if `target.alpha` is not in the open
+/// interval `(0, 1)` or `out` has fewer than two observations.
+fn estimated_exec_count(out: &DiffOut, target: &PrecisionTarget) -> Result<usize, BenchDiffError> {
+    let z = z_alpha(target.alpha / 2.)?;
+    let var_ln = out.try_stdev_ln_f1()?.powi(2) + out.try_stdev_ln_f2()?.powi(2);
+    let half_width_ln = target.rel_half_width.ln_1p();
+    let count = (z.powi(2) * var_ln / half_width_ln.powi(2)).ceil();
+    if count.is_finite() {
+        Ok(count as usize)
+    } else {
+        Ok(target.max_exec_count)
+    }
+}
+
+/// Returns `true` if the relative half-width of the Welch confidence interval for the ratio of medians in `out`
+/// is at most `target.rel_half_width`. If the log-latencies of both functions are constant, e.g., because the
+/// clock is too coarse to tell the executions apart, the interval has zero width and the target is reached.
+fn target_precision_reached(
+    out: &DiffOut,
+    target: &PrecisionTarget,
+) -> Result<bool, BenchDiffError> {
+    match out.try_welch_ratio_ci_rel_half_width(target.alpha) {
+        Ok(rel_half_width) => Ok(rel_half_width <= target.rel_half_width),
+        Err(BenchDiffError::DegenerateVariance) => Ok(true),
+        Err(e) => Err(e),
+    }
+}
+
+/// Compares latencies for two closures `f1` and `f2`, collecting data until a target precision is reached, and
+/// *optionally* outputs information about the benchmark and its execution status.
+///
+/// After warm-up, this function repeatedly executes *rounds* of duos of pairs (`f1`, `f2`), (`f2`, `f1`),
+/// followed by the same number of duos of pairs (`f2`, `f1`), (`f1`, `f2`), until one of the following
+/// stopping rules fires:
+/// - The relative half-width of the Welch confidence interval for the ratio of medians
+///   (see [`DiffOut::welch_ratio_ci_rel_half_width`]) is at most `target.rel_half_width`.
+/// - Another round would exceed `target.max_exec_count` executions for each function.
+/// - Another round would exceed `target.max_duration`, based on the observed time per duo.
+///
+/// The stopping rule that fired is recorded in the output (see [`DiffOut::stop_reason`]). The sample size is
+/// always a multiple of 4.
+///
+/// The size of the first round is estimated from the variability of the latencies observed during warm-up,
+/// and the size of each subsequent round is re-estimated from the data collected so far.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `target` - target precision and limits for data collection.
+/// - `warmup_status` - see [`crate::bench_diff_x`].
+/// - `pre_exec` - see [`crate::bench_diff_x`].
+/// - `exec_status` - see [`crate::bench_diff_x`].
+pub fn bench_diff_adaptive_x<W: StatusFlow, E: StatusFlow>(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    target: PrecisionTarget,
+    warmup_status: impl FnMut(usize, u64, u64) -> W,
+    pre_exec: impl FnOnce(),
+    exec_status: impl FnMut(usize) -> E,
+) -> DiffOut {
+    bench_diff_adaptive_with_reporter(
+        unit,
+        f1,
+        f2,
+        target,
+        &mut ClosureReporter::new(warmup_status, pre_exec, exec_status),
+    )
+}
+
+/// Compares latencies for two closures `f1` and `f2`, collecting data until a target precision is reached, and
+/// reports the progress of the benchmark to `reporter`.
+///
+/// This function is like [`bench_diff_adaptive_x`] except that the events of the benchmark are reported to
+/// `reporter` instead of status closures.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `target` - target precision and limits for data collection.
+/// - `reporter` - receives the events of the benchmark (see [`Reporter`]).
+pub fn bench_diff_adaptive_with_reporter(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    target: PrecisionTarget,
+    reporter: &mut impl Reporter,
+) -> DiffOut {
+    BenchDiff::new(unit, target.max_exec_count)
+        .reporter(reporter)
+        .run_adaptive(f1, f2, target)
+}
+
+/// Compares latencies for two closures `f1` and `f2`, collecting data until a target precision is reached.
+///
+/// This function calls [`bench_diff_adaptive_x`] with no-op closures for the arguments that support the output
+/// of benchmark status.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `target` - target precision and limits for data collection.
+pub fn bench_diff_adaptive(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    target: PrecisionTarget,
+) -> DiffOut {
+    bench_diff_adaptive_x(unit, f1, f2, target, |_, _, _| {}, || (), |_| ())
+}
+
+/// Compares latencies for two closures `f1` and `f2`, collecting data until a target precision is reached, and
+/// outputs information about the benchmark and its execution status. Execution status is output to `stderr`.
+///
+/// This function calls [`bench_diff_adaptive_with_reporter`] with [`stderr_reporter`] (see
+/// [`crate::bench_diff_with_status`]).
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `target` - target precision and limits for data collection.
+/// - `header` - see [`crate::bench_diff_with_status`]; its second argument is `target.max_exec_count`.
+pub fn bench_diff_adaptive_with_status(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    target: PrecisionTarget,
+    header: impl FnOnce(LatencyUnit, usize),
+) -> DiffOut {
+    header(unit, target.max_exec_count);
+
+    bench_diff_adaptive_with_reporter(unit, f1, f2, target, &mut stderr_reporter())
+}
+
+impl BenchDiff<'_> {
+    /// Compares latencies for two closures `f1` and `f2`, collecting data until a target precision is reached,
+    /// according to the configuration of `self` (see [`bench_diff_adaptive_x`]). The sample size of `self` is not
+    /// used; the maximum sample size is `target.max_exec_count`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a latency of zero is measured (see [`BenchDiffError::ZeroLatency`]) or `target.alpha` is not in
+    /// the open interval `(0, 1)`.
+    pub fn run_adaptive(
+        &mut self,
+        f1: impl FnMut(),
+        f2: impl FnMut(),
+        target: PrecisionTarget,
+    ) -> DiffOut {
+        expect_ok(self.try_run_adaptive(f1, f2, target))
+    }
+
+    /// Fallible version of [`Self::run_adaptive`], which returns an error instead of panicking, i.e.,
+    /// [`BenchDiffError::ZeroLatency`] if a latency of zero is measured and [`BenchDiffError::Statistics`] if
+    /// `target.alpha` is not in the open interval `(0, 1)`.
+    pub fn try_run_adaptive(
+        &mut self,
+        f1: impl FnMut(),
+        f2: impl FnMut(),
+        target: PrecisionTarget,
+    ) -> Result<DiffOut, BenchDiffError> {
+        let mut start: Option<Instant> = None;
+
+        let next_round = |out: &DiffOut| {
+            let n = out.n() as usize;
+            let duo_count = (n / 2).max(1) as f64;
+
+            let (collected, elapsed, duo_latency) = match start {
+                None => {
+                    // `out` contains the warm-up data
+                    start = Some(Instant::now());
+                    (0, Duration::ZERO, out.warmup().duration.div_f64(duo_count))
+                }
+                Some(start) => {
+                    if target_precision_reached(out, &target)? {
+                        return Ok(ControlFlow::Break(StopReason::TargetPrecision));
+                    }
+                    let elapsed = start.elapsed();
+                    (n, elapsed, elapsed.div_f64(duo_count))
+                }
+            };
+
+            let max_duos_by_count = target.max_exec_count.saturating_sub(collected) / 4;
+            if max_duos_by_count == 0 {
+                return Ok(ControlFlow::Break(StopReason::MaxExecCount));
+            }
+
+            let remaining = target.max_duration.saturating_sub(elapsed);
+            let max_duos_by_time =
+                (remaining.as_secs_f64() / (2. * duo_latency.as_secs_f64())) as usize;
+            if max_duos_by_time == 0 {
+                return Ok(ControlFlow::Break(StopReason::MaxDuration));
+            }
+
+            let round_duos = estimated_exec_count(out, &target)?
+                .saturating_sub(collected)
+                .div_ceil(4)
+                .max(MIN_ROUND_DUOS);
+
+            Ok(ControlFlow::Continue(
+                round_duos.min(max_duos_by_count).min(max_duos_by_time),
+            ))
+        };
+
+        let warmup_plan = self.config.warmup_plan(None);
+        self.collect_duos(&mut PlainDuos(f1, f2), 0, |out, duo, reporter| {
+            bench_duos_rounds_x(
+                out,
+                warmup_plan,
+                duo,
+                Some(target.max_exec_count),
+                reporter,
+                next_round,
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DiffState;
+    use std::hint::black_box;
+
+    const TARGET: PrecisionTarget = PrecisionTarget {
+        rel_half_width: 0.01,
+        alpha: 0.05,
+        max_exec_count: 1_000,
+        max_duration: Duration::from_secs(1),
+    };
+
+    /// Returns the output for `n` pairs of latencies `elapsed1` and `elapsed2`.
+    fn constant_out(n: usize, elapsed1: u64, elapsed2: u64) -> DiffOut {
+        let mut out = DiffOut::new();
+        let mut state = DiffState::new(&mut out);
+        for _ in 0..n {
+            state
+                .capture_data(elapsed1, elapsed2)
+                .expect("latencies are not zero");
+        }
+        out
+    }
+
+    #[test]
+    fn test_target_precision_constant_latencies() {
+        // The variances of the log-latencies are zero up to rounding errors.
+        let out = constant_out(10, 100, 200);
+        assert!(out.try_stdev_ln_f1().expect("n >= 2") >= 0.);
+        assert!(out.try_stdev_ln_f2().expect("n >= 2") >= 0.);
+        assert!(matches!(target_precision_reached(&out, &TARGET), Ok(true)));
+        assert!(matches!(estimated_exec_count(&out, &TARGET), Ok(0 | 1)));
+
+        // The log-latencies are exactly zero.
+        let out = constant_out(10, 1, 1);
+        assert!(matches!(
+            out.try_welch_ratio_ci_rel_half_width(TARGET.alpha),
+            Err(BenchDiffError::DegenerateVariance)
+        ));
+        assert!(matches!(target_precision_reached(&out, &TARGET), Ok(true)));
+    }
+
+    #[test]
+    fn test_invalid_alpha() {
+        let target = PrecisionTarget {
+            alpha: 1.5,
+            ..TARGET
+        };
+        let result = BenchDiff::new(LatencyUnit::Nano, 0)
+            .warmup_millis(1)
+            .try_run_adaptive(|| (), || (), target);
+
+        assert!(matches!(result, Err(BenchDiffError::Statistics(_))));
+    }
+
+    /// Runs an adaptive benchmark of two small busy closures with `target`.
+    fn run(target: PrecisionTarget) -> DiffOut {
+        let f = || {
+            black_box((0..100_u64).sum::<u64>());
+        };
+        bench_diff_adaptive(LatencyUnit::Nano, f, f, target)
+    }
+
+    #[test]
+    fn test_stop_reasons() {
+        let out = run(PrecisionTarget {
+            rel_half_width: 0.5,
+            max_exec_count: 1_000_000,
+            max_duration: Duration::from_secs(10),
+            ..TARGET
+        });
+        assert_eq!(out.stop_reason(), StopReason::TargetPrecision);
+        assert!(out.welch_ratio_ci_rel_half_width(TARGET.alpha) <= 0.5);
+        assert_eq!(out.n() % 4, 0);
+
+        let out = run(PrecisionTarget {
+            rel_half_width: 1e-9,
+            max_exec_count: 400,
+            max_duration: Duration::from_secs(10),
+            ..TARGET
+        });
+        assert_eq!(out.stop_reason(), StopReason::MaxExecCount);
+        assert!(out.n() > 0 && out.n() <= 400, "n={}", out.n());
+        assert_eq!(out.n() % 4, 0);
+
+        let out = run(PrecisionTarget {
+            rel_half_width: 1e-9,
+            max_exec_count: usize::MAX,
+            max_duration: Duration::from_millis(20),
+            ..TARGET
+        });
+        assert_eq!(out.stop_reason(), StopReason::MaxDuration);
+        assert_eq!(out.n() % 4, 0);
+    }
+}
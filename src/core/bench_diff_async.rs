@@ -0,0 +1,382 @@
+//! Module implementing functions to compare the difference in latency between two closures that return futures.
+
+use super::{
+    BenchDiff, BenchDiffError, ClockKind, ClosureReporter, DiffOut, LatencyUnit, Reporter,
+    StatusFlow, TargetFn,
+    allocations::AllocTracker,
+    bench_diff::{DuoPairs, DuoSource, Panics, Phase, expect_ok},
+    stderr_reporter,
+};
+use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::{Pin, pin},
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+    time::Duration,
+};
+
+/// Executor that drives futures to completion on the current thread. Used as an argument of the async
+/// benchmarking functions (e.g., [`bench_diff_async`]).
+pub trait BlockOn {
+    /// Drives `future` to completion on the current thread and returns its output.
+    fn block_on<F: Future>(&mut self, future: F) -> F::Output;
+}
+
+/// [`Wake`] implementation that unparks the thread blocked on a future.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Minimal single-threaded executor.
+///
+/// It polls the future on the current thread and parks the thread while the future is pending, so its overhead
+/// per future is very small. It does not provide any I/O or timer drivers, so it is suitable for futures that do
+/// not depend on a particular runtime.
+///
+/// Its waker unparks the thread on which it was created, so it is neither `Send` nor `Sync`, which ensures that it
+/// is only used on that thread:
+/// ```compile_fail
+/// fn assert_send<T: Send>() {}
+/// assert_send::<bench_diff::SimpleExecutor>();
+/// ```
+pub struct SimpleExecutor {
+    waker: Waker,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl SimpleExecutor {
+    /// Creates a new instance for the current thread.
+    pub fn new() -> Self {
+        Self {
+            waker: Waker::from(Arc::new(ThreadWaker(thread::current()))),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Default for SimpleExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlockOn for SimpleExecutor {
+    fn block_on<F: Future>(&mut self, future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(&self.waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+}
+
+/// Adapter for a Tokio current-thread runtime. Requires feature **`tokio`**.
+#[cfg(feature = "tokio")]
+pub struct TokioCurrentThread(tokio::runtime::Runtime);
+
+#[cfg(feature = "tokio")]
+impl TokioCurrentThread {
+    /// Creates a new instance with a new current-thread runtime with all drivers enabled.
+    pub fn new() -> std::io::Result<Self> {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map(Self)
+    }
+
+    /// Creates a new instance from `runtime`, which should be a current-thread runtime.
+    pub fn from_runtime(runtime: tokio::runtime::Runtime) -> Self {
+        Self(runtime)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl BlockOn for TokioCurrentThread {
+    fn block_on<F: Future>(&mut self, future: F) -> F::Output {
+        self.0.block_on(future)
+    }
+}
+
+/// Drives `future` to completion with `executor` and returns its latency as measured by `clock`.
+#[inline(always)]
+fn latency_future(clock: ClockKind, executor: &mut impl BlockOn, future: impl Future) -> Duration {
+    clock.latency(|| {
+        executor.block_on(future);
+    })
+}
+
+/// Drives futures returned by `f1` then `f2` then `f2` then `f1` with `executor` and returns two pairs of
+/// latencies. The creation of each future is not included in its latency. For each pair, the first component is
+/// an `f1` latency and the second component is an `f2` latency.
+#[inline(always)]
+fn duo_exec_async<Fut1: Future, Fut2: Future>(
+    clock: ClockKind,
+    executor: &mut impl BlockOn,
+    mut f1: impl FnMut() -> Fut1,
+    mut f2: impl FnMut() -> Fut2,
+) -> [(Duration, Duration); 2] {
+    let l01 = latency_future(clock, executor, f1());
+    let l02 = latency_future(clock, executor, f2());
+
+    let l12 = latency_future(clock, executor, f2());
+    let l11 = latency_future(clock, executor, f1());
+
+    [(l01, l02), (l11, l12)]
+}
+
+/// Future that polls `future`, the future returned by the function identified by `target`, handling its panics
+/// with `panics`. Completes immediately if `future` is `None`, i.e., if the creation of the future panicked.
+struct Caught<'p, P, Fut> {
+    panics: &'p P,
+    target: TargetFn,
+    future: Option<Fut>,
+}
+
+impl<P: Panics, Fut: Future> Future for Caught<'_, P, Fut> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // SAFETY: `future` is never moved out of `self` nor replaced, so it stays pinned while `self` is pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let Some(future) = this.future.as_mut() else {
+            return Poll::Ready(());
+        };
+        // SAFETY: see above.
+        let future = unsafe { Pin::new_unchecked(future) };
+        match this.panics.catch(this.target, || future.poll(cx)) {
+            Some(Poll::Pending) => Poll::Pending,
+            Some(Poll::Ready(_)) | None => Poll::Ready(()),
+        }
+    }
+}
+
+/// [`DuoSource`] of two closures that return futures, driven by `executor` in [`duo_exec_async`].
+struct AsyncDuos<'e, E, F1, F2> {
+    executor: &'e mut E,
+    f1: F1,
+    f2: F2,
+}
+
+impl<E: BlockOn, Fut1: Future, Fut2: Future, F1: FnMut() -> Fut1, F2: FnMut() -> Fut2> DuoSource
+    for AsyncDuos<'_, E, F1, F2>
+{
+    fn duos<'a>(
+        &'a mut self,
+        clock: ClockKind,
+        panics: &'a impl Panics,
+        _tracker: Option<&'a AllocTracker>,
+    ) -> impl FnMut(Phase) -> DuoPairs + 'a {
+        let Self { executor, f1, f2 } = self;
+        let mut f1 = move || Caught {
+            panics,
+            target: TargetFn::F1,
+            future: panics.catch(TargetFn::F1, &mut *f1),
+        };
+        let mut f2 = move || Caught {
+            panics,
+            target: TargetFn::F2,
+            future: panics.catch(TargetFn::F2, &mut *f2),
+        };
+        move |phase| match phase {
+            Phase::Warmup | Phase::Forward => duo_exec_async(clock, *executor, &mut f1, &mut f2),
+            Phase::Reversed => duo_exec_async(clock, *executor, &mut f2, &mut f1),
+        }
+    }
+}
+
+/// Compares latencies for two closures `f1` and `f2` that return futures, and *optionally* outputs information
+/// about the benchmark and its execution status.
+///
+/// This function is like [`crate::bench_diff_x`] except that the latency of an invocation of `f1` or `f2` is the
+/// time taken by `executor` to drive the returned future to completion. Each future is created before its latency
+/// starts being measured. The duos of pairs (`f1`, `f2`), (`f2`, `f1`) are executed as in
+/// [`crate::bench_diff_x`].
+///
+/// [`SimpleExecutor`] is a minimal executor which adds very little overhead; [`TokioCurrentThread`] (feature
+/// **`tokio`**) supports futures that require a Tokio runtime.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `executor` - executor used to drive the futures.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `warmup_status` - see [`crate::bench_diff_x`].
+/// - `pre_exec` - see [`crate::bench_diff_x`].
+/// - `exec_status` - see [`crate::bench_diff_x`].
+pub fn bench_diff_async_x<Fut1: Future, Fut2: Future, W: StatusFlow, E: StatusFlow>(
+    unit: LatencyUnit,
+    executor: &mut impl BlockOn,
+    f1: impl FnMut() -> Fut1,
+    f2: impl FnMut() -> Fut2,
+    exec_count: usize,
+    warmup_status: impl FnMut(usize, u64, u64) -> W,
+    pre_exec: impl FnOnce(),
+    exec_status: impl FnMut(usize) -> E,
+) -> DiffOut {
+    bench_diff_async_with_reporter(
+        unit,
+        executor,
+        f1,
+        f2,
+        exec_count,
+        &mut ClosureReporter::new(warmup_status, pre_exec, exec_status),
+    )
+}
+
+/// Compares latencies for two closures `f1` and `f2` that return futures, and reports the progress of the benchmark
+/// to `reporter`.
+///
+/// This function is like [`bench_diff_async_x`] except that the events of the benchmark are reported to
+/// `reporter` instead of status closures.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `executor` - executor used to drive the futures.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `reporter` - receives the events of the benchmark (see [`Reporter`]).
+pub fn bench_diff_async_with_reporter<Fut1: Future, Fut2: Future>(
+    unit: LatencyUnit,
+    executor: &mut impl BlockOn,
+    f1: impl FnMut() -> Fut1,
+    f2: impl FnMut() -> Fut2,
+    exec_count: usize,
+    reporter: &mut impl Reporter,
+) -> DiffOut {
+    BenchDiff::new(unit, exec_count)
+        .reporter(reporter)
+        .run_async(executor, f1, f2)
+}
+
+/// Compares latencies for two closures `f1` and `f2` that return futures.
+///
+/// This function calls [`bench_diff_async_x`] with no-op closures for the arguments that support the output of
+/// benchmark status.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `executor` - executor used to drive the futures.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+pub fn bench_diff_async<Fut1: Future, Fut2: Future>(
+    unit: LatencyUnit,
+    executor: &mut impl BlockOn,
+    f1: impl FnMut() -> Fut1,
+    f2: impl FnMut() -> Fut2,
+    exec_count: usize,
+) -> DiffOut {
+    bench_diff_async_x(
+        unit,
+        executor,
+        f1,
+        f2,
+        exec_count,
+        |_, _, _| {},
+        || (),
+        |_| (),
+    )
+}
+
+/// Compares latencies for two closures `f1` and `f2` that return futures, and outputs information about the
+/// benchmark and its execution status. Execution status is output to `stderr`.
+///
+/// This function calls [`bench_diff_async_with_reporter`] with [`stderr_reporter`] (see
+/// [`crate::bench_diff_with_status`]).
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `executor` - executor used to drive the futures.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `header` - see [`crate::bench_diff_with_status`].
+pub fn bench_diff_async_with_status<Fut1: Future, Fut2: Future>(
+    unit: LatencyUnit,
+    executor: &mut impl BlockOn,
+    f1: impl FnMut() -> Fut1,
+    f2: impl FnMut() -> Fut2,
+    exec_count: usize,
+    header: impl FnOnce(LatencyUnit, usize),
+) -> DiffOut {
+    header(unit, exec_count);
+
+    bench_diff_async_with_reporter(unit, executor, f1, f2, exec_count, &mut stderr_reporter())
+}
+
+impl BenchDiff<'_> {
+    /// Compares latencies for two closures `f1` and `f2` that return futures, driven by `executor`, according to
+    /// the configuration of `self` (see [`bench_diff_async_x`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a latency of zero is measured (see [`BenchDiffError::ZeroLatency`]).
+    pub fn run_async<Fut1: Future, Fut2: Future>(
+        &mut self,
+        executor: &mut impl BlockOn,
+        f1: impl FnMut() -> Fut1,
+        f2: impl FnMut() -> Fut2,
+    ) -> DiffOut {
+        expect_ok(self.try_run_async(executor, f1, f2))
+    }
+
+    /// Fallible version of [`Self::run_async`], which returns an error instead of panicking (see
+    /// [`Self::try_run`]).
+    pub fn try_run_async<Fut1: Future, Fut2: Future>(
+        &mut self,
+        executor: &mut impl BlockOn,
+        f1: impl FnMut() -> Fut1,
+        f2: impl FnMut() -> Fut2,
+    ) -> Result<DiffOut, BenchDiffError> {
+        self.check_exec_count()?;
+        self.collect_fixed(&mut AsyncDuos { executor, f1, f2 })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BlockOn, SimpleExecutor};
+    use std::{
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    /// Future that is pending `count` times, waking itself each time, before becoming ready.
+    struct YieldN(usize);
+
+    impl Future for YieldN {
+        type Output = usize;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<usize> {
+            if self.0 == 0 {
+                Poll::Ready(42)
+            } else {
+                self.0 -= 1;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn test_simple_executor() {
+        let mut executor = SimpleExecutor::new();
+        assert_eq!(executor.block_on(async { 1 + 1 }), 2);
+        assert_eq!(executor.block_on(YieldN(10)), 42);
+    }
+}
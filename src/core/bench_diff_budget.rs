@@ -0,0 +1,232 @@
+//! Module implementing functions to compare the difference in latency between two closures within a wall-clock
+//! time budget.
+
+use super::{
+    BenchDiff, BenchDiffError, ClosureReporter, DiffOut, LatencyUnit, Reporter, StatusFlow,
+    StopReason,
+    bench_diff::{PlainDuos, bench_duos_rounds_x, expect_ok},
+    stderr_reporter,
+};
+use std::{
+    ops::ControlFlow,
+    time::{Duration, Instant},
+};
+
+/// Maximum fraction of the time budget used for warm-up by [`bench_diff_with_budget_x`].
+const WARMUP_BUDGET_FRACTION: f64 = 0.25;
+
+/// Fraction of the remaining time budget targeted by each data collection round of [`bench_diff_with_budget_x`].
+/// Rounds get shorter as the end of the budget approaches, which limits overruns due to variations in latency.
+const ROUND_BUDGET_FRACTION: f64 = 0.5;
+
+/// Compares latencies for two closures `f1` and `f2` within a wall-clock time budget, and *optionally* outputs
+/// information about the benchmark and its execution status.
+///
+/// The `budget` covers both warm-up and data collection. Warm-up follows the warm-up policy (see
+/// [`BenchDiff::warmup_policy`]) but lasts at most one fourth of the budget. After warm-up, this function
+/// repeatedly executes *rounds* of duos of pairs (`f1`, `f2`), (`f2`, `f1`), followed by the same number of duos
+/// of pairs (`f2`, `f1`), (`f1`, `f2`), so the forward and reversed duos remain balanced and the sample size is a
+/// multiple of 4.
+/// Each round is sized from the observed time per duo to use part of the remaining budget, and data collection
+/// stops with [`StopReason::MaxDuration`] when not even a round of one duo per phase fits in the remaining
+/// budget. The budget may be slightly exceeded if latencies increase during the benchmark.
+///
+/// The achieved sample size is available from [`DiffOut::n`].
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `budget` - total duration of the benchmark, including warm-up.
+/// - `warmup_status` - see [`crate::bench_diff_x`].
+/// - `pre_exec` - see [`crate::bench_diff_x`].
+/// - `exec_status` - see [`crate::bench_diff_x`].
+pub fn bench_diff_with_budget_x<W: StatusFlow, E: StatusFlow>(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    budget: Duration,
+    warmup_status: impl FnMut(usize, u64, u64) -> W,
+    pre_exec: impl FnOnce(),
+    exec_status: impl FnMut(usize) -> E,
+) -> DiffOut {
+    bench_diff_with_budget_and_reporter(
+        unit,
+        f1,
+        f2,
+        budget,
+        &mut ClosureReporter::new(warmup_status, pre_exec, exec_status),
+    )
+}
+
+/// Compares latencies for two closures `f1` and `f2` within a wall-clock time budget, and reports the progress of
+/// the benchmark to `reporter`.
+///
+/// This function is like [`bench_diff_with_budget_x`] except that the events of the benchmark are reported to
+/// `reporter` instead of status closures.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `budget` - total duration of the benchmark, including warm-up.
+/// - `reporter` - receives the events of the benchmark (see [`Reporter`]).
+pub fn bench_diff_with_budget_and_reporter(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    budget: Duration,
+    reporter: &mut impl Reporter,
+) -> DiffOut {
+    BenchDiff::new(unit, 0)
+        .reporter(reporter)
+        .run_with_budget(f1, f2, budget)
+}
+
+/// Compares latencies for two closures `f1` and `f2` within a wall-clock time budget.
+///
+/// This function calls [`bench_diff_with_budget_x`] with no-op closures for the arguments that support the output
+/// of benchmark status.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `budget` - total duration of the benchmark, including warm-up.
+pub fn bench_diff_with_budget(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    budget: Duration,
+) -> DiffOut {
+    bench_diff_with_budget_x(unit, f1, f2, budget, |_, _, _| {}, || (), |_| ())
+}
+
+/// Compares latencies for two closures `f1` and `f2` within a wall-clock time budget, and outputs information
+/// about the benchmark and its execution status. Execution status is output to `stderr`.
+///
+/// This function calls [`bench_diff_with_budget_and_reporter`] with [`stderr_reporter`] (see
+/// [`crate::bench_diff_with_status`]).
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `budget` - total duration of the benchmark, including warm-up.
+/// - `header` - is invoked once at the start of this function's execution; it can be used, for example,
+///   to output information about the functions being compared to `stdout` and/or `stderr`. The first
+///   argument is the the `LatencyUnit` and the second argument is the `budget`.
+pub fn bench_diff_with_budget_and_status(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    budget: Duration,
+    header: impl FnOnce(LatencyUnit, Duration),
+) -> DiffOut {
+    header(unit, budget);
+
+    bench_diff_with_budget_and_reporter(unit, f1, f2, budget, &mut stderr_reporter())
+}
+
+impl BenchDiff<'_> {
+    /// Compares latencies for two closures `f1` and `f2` within a wall-clock time budget, according to the
+    /// configuration of `self` (see [`bench_diff_with_budget_x`]). The sample size of `self` is not used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a latency of zero is measured (see [`BenchDiffError::ZeroLatency`]).
+    pub fn run_with_budget(
+        &mut self,
+        f1: impl FnMut(),
+        f2: impl FnMut(),
+        budget: Duration,
+    ) -> DiffOut {
+        expect_ok(self.try_run_with_budget(f1, f2, budget))
+    }
+
+    /// Fallible version of [`Self::run_with_budget`], which returns [`BenchDiffError::ZeroLatency`] instead of
+    /// panicking if a latency of zero is measured.
+    pub fn try_run_with_budget(
+        &mut self,
+        f1: impl FnMut(),
+        f2: impl FnMut(),
+        budget: Duration,
+    ) -> Result<DiffOut, BenchDiffError> {
+        let max_warmup_millis =
+            ((budget.as_millis() as f64 * WARMUP_BUDGET_FRACTION) as u64).max(1);
+
+        let start = Instant::now();
+        let deadline = start + budget;
+        let mut collection_start: Option<Instant> = None;
+
+        let next_round = |out: &DiffOut| {
+            let now = Instant::now();
+            let duo_count = (out.n() / 2).max(1) as u32;
+
+            let duo_latency = match collection_start {
+                None => {
+                    // `out` contains the warm-up data
+                    collection_start = Some(now);
+                    now.duration_since(start) / duo_count
+                }
+                Some(collection_start) => now.duration_since(collection_start) / duo_count,
+            };
+
+            let remaining = deadline.saturating_duration_since(now);
+            let round_latency = 2 * duo_latency;
+            if round_latency.is_zero() || remaining < round_latency {
+                return Ok(ControlFlow::Break(StopReason::MaxDuration));
+            }
+
+            let round_duos =
+                remaining.as_secs_f64() * ROUND_BUDGET_FRACTION / round_latency.as_secs_f64();
+            Ok(ControlFlow::Continue(round_duos as usize))
+        };
+
+        let warmup_plan = self.config.warmup_plan(Some(max_warmup_millis));
+        self.collect_duos(&mut PlainDuos(f1, f2), 0, |out, duo, reporter| {
+            bench_duos_rounds_x(out, warmup_plan, duo, None, reporter, next_round)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_budget() {
+        let budget = Duration::from_secs(1);
+        let f1 = || std::thread::sleep(Duration::from_micros(10));
+        let f2 = || ();
+
+        let start = Instant::now();
+        let out = bench_diff_with_budget(LatencyUnit::Nano, f1, f2, budget);
+        let elapsed = start.elapsed();
+
+        assert_eq!(out.stop_reason(), StopReason::MaxDuration);
+        assert!(out.n() > 0);
+        assert_eq!(out.n() % 4, 0);
+        assert!(
+            budget / 2 <= elapsed && elapsed <= budget * 2,
+            "elapsed={elapsed:?}"
+        );
+    }
+
+    #[test]
+    fn test_cancel() {
+        let out = bench_diff_with_budget_x(
+            LatencyUnit::Nano,
+            || std::thread::sleep(Duration::from_micros(10)),
+            || (),
+            Duration::from_millis(400),
+            |_, _, _| (),
+            || (),
+            |_| ControlFlow::Break(()),
+        );
+
+        assert_eq!(out.stop_reason(), StopReason::Cancelled);
+        // The duo after which `exec_status` breaks is kept.
+        assert_eq!(out.n(), 2);
+    }
+}
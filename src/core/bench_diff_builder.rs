@@ -0,0 +1,860 @@
+//! Module defining [`BenchDiff`], a configuration object for benchmarks comparing two closures.
+
+use super::{
+    BackgroundLoad, BenchDiffError, ClockKind, DEFAULT_HIST_HIGH, DEFAULT_HIST_SIGFIG, DiffOut,
+    DuoOrder, LatencyUnit, Reporter, SchedPriority, SilentReporter, ThreadSettings, WarmupPolicy,
+    Warning,
+    allocations::AllocTracker,
+    background_load::LoadGenerator,
+    bench_diff::{
+        DuoFlow, DuoPairs, DuoSource, PanicCatcher, Panics, Phase, PlainDuos, PropagatePanics,
+        expect_ok, get_warmup_millis, randomized, try_bench_duos_out_x,
+    },
+    counting_allocator_installed, environment_check, stderr_reporter,
+    thread_settings::ThreadSettingsGuard,
+    warmup::WarmupPlan,
+};
+
+/// Configuration of a benchmark comparing two closures, built with chained setter methods and executed with
+/// [`Self::run`] or one of the other `run_` methods.
+///
+/// Unlike the global warm-up duration (see [`crate::set_warmup_millis`]), which affects all benchmarks in the
+/// process, the settings of an instance only affect the benchmarks it runs, so differently configured
+/// comparisons can run in the same binary or in parallel tests. A new instance takes its default warm-up
+/// duration from the global setting.
+///
+/// The settings apply to all `run_` methods, each of which corresponds to a family of benchmarking functions
+/// (e.g., [`Self::run_adaptive`] and [`crate::bench_diff_adaptive`]), except where noted in the documentation of
+/// a setting. A setting that a `run_` method doesn't support is ignored and reported as a
+/// [`Warning::SettingNotSupported`] in the output.
+///
+/// The benchmarking functions (e.g., [`crate::bench_diff`] and [`crate::bench_diff_adaptive`]) are thin
+/// wrappers around this type.
+///
+/// ```no_run
+/// use bench_diff::{BenchDiff, LatencyUnit, WarmupConvergence, WarmupPolicy};
+///
+/// let out = BenchDiff::new(LatencyUnit::Nano, 10_000)
+///     .warmup_policy(WarmupPolicy::Convergence(WarmupConvergence::default()))
+///     .seed(42)
+///     .run(|| (0..100).for_each(|i| { std::hint::black_box(i); }), || ());
+/// println!("ratio of medians: {}", out.ratio_medians_f1_f2());
+/// ```
+pub struct BenchDiff<'a> {
+    pub(super) config: RunConfig,
+    pub(super) exec_count: usize,
+    seed: Option<u64>,
+    pub(super) catch_panics: bool,
+    background_load: BackgroundLoad,
+    thread_settings: ThreadSettings,
+    environment_check: bool,
+    retain_samples: bool,
+    track_allocations: bool,
+    pub(super) reporter: Box<dyn Reporter + 'a>,
+}
+
+/// Settings of a [`BenchDiff`] that apply to all benchmarking function families.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct RunConfig {
+    pub(super) unit: LatencyUnit,
+    pub(super) warmup_policy: WarmupPolicy,
+    pub(super) warmup_millis: u64,
+    pub(super) subtract_timer_overhead: bool,
+    pub(super) hist_high: u64,
+    pub(super) hist_sigfig: u8,
+    pub(super) clock: ClockKind,
+}
+
+impl RunConfig {
+    /// Returns an empty output with the histogram settings, clock, and unit of `self`.
+    pub(super) fn new_out(&self) -> DiffOut {
+        let mut out = DiffOut::new_with_hist(self.hist_high, self.hist_sigfig);
+        out.clock = self.clock;
+        out.unit = self.unit;
+        out
+    }
+
+    /// Returns the warm-up plan of `self`, with a duration further limited to `max_millis` if it is not `None`.
+    pub(super) fn warmup_plan(&self, max_millis: Option<u64>) -> WarmupPlan {
+        WarmupPlan::new(
+            self.warmup_policy,
+            self.warmup_millis,
+            max_millis,
+            self.subtract_timer_overhead,
+        )
+    }
+}
+
+impl<'a> BenchDiff<'a> {
+    /// Creates a new instance with the given `unit` and `exec_count`, [`WarmupPolicy::Fixed`] with the current
+    /// global warm-up duration (see [`crate::get_warmup_millis`]), no subtraction of the timer overhead, default
+    /// histogram settings, [`ClockKind::Instant`], the fixed duo order, and no status output.
+    pub fn new(unit: LatencyUnit, exec_count: usize) -> Self {
+        Self {
+            config: RunConfig {
+                unit,
+                warmup_policy: WarmupPolicy::Fixed,
+                warmup_millis: get_warmup_millis(),
+                subtract_timer_overhead: false,
+                hist_high: DEFAULT_HIST_HIGH,
+                hist_sigfig: DEFAULT_HIST_SIGFIG,
+                clock: ClockKind::Instant,
+            },
+            exec_count,
+            seed: None,
+            catch_panics: false,
+            background_load: BackgroundLoad::default(),
+            thread_settings: ThreadSettings::default(),
+            environment_check: false,
+            retain_samples: false,
+            track_allocations: false,
+            reporter: Box::new(SilentReporter),
+        }
+    }
+
+    /// Sets the unit used to present latency statistics.
+    pub fn unit(mut self, unit: LatencyUnit) -> Self {
+        self.config.unit = unit;
+        self
+    }
+
+    /// Sets the number of executions (sample size) for each function.
+    pub fn exec_count(mut self, exec_count: usize) -> Self {
+        self.exec_count = exec_count;
+        self
+    }
+
+    /// Sets the warm-up policy. The default is [`WarmupPolicy::Fixed`].
+    ///
+    /// [`Self::run_multi`] always uses [`WarmupPolicy::Fixed`]. [`Self::run_with_budget`] further limits the
+    /// warm-up duration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `policy` is [`WarmupPolicy::Convergence`] with invalid parameters
+    /// (see [`crate::WarmupConvergence::validate`]).
+    pub fn warmup_policy(mut self, policy: WarmupPolicy) -> Self {
+        if let WarmupPolicy::Convergence(convergence) = policy {
+            convergence.validate();
+        }
+        self.config.warmup_policy = policy;
+        self
+    }
+
+    /// Sets the warm-up duration, in milliseconds, for [`WarmupPolicy::Fixed`] (see [`crate::set_warmup_millis`]).
+    pub fn warmup_millis(mut self, millis: u64) -> Self {
+        self.config.warmup_millis = millis;
+        self
+    }
+
+    /// Sets whether the median timer overhead measured during warm-up (see [`DiffOut::timer_overhead`]) is
+    /// subtracted from each latency before it is recorded. The default is `false`.
+    ///
+    /// Subtraction reduces the bias of ratio estimates toward `1` for functions whose latencies are not much
+    /// larger than the timer overhead. Latencies that would become smaller than one nanosecond after subtraction
+    /// are recorded as one nanosecond.
+    pub fn subtract_timer_overhead(mut self, subtract: bool) -> Self {
+        self.config.subtract_timer_overhead = subtract;
+        self
+    }
+
+    /// Sets the configuration of the latency histograms in the output. Histograms are resized automatically
+    /// to accommodate latencies (in nanoseconds) above `hist_high`, so `hist_high` only determines their initial
+    /// size, while `hist_sigfig` is the number of significant decimal figures preserved for each latency. The
+    /// defaults are [`DEFAULT_HIST_HIGH`] and [`DEFAULT_HIST_SIGFIG`].
+    ///
+    /// Lower values reduce the memory used by the output, which is dominated by four histograms whose size
+    /// grows with `hist_high` and exponentially with `hist_sigfig`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hist_high < 2` or `hist_sigfig > 5`.
+    pub fn histogram(mut self, hist_high: u64, hist_sigfig: u8) -> Self {
+        assert!(hist_high >= 2, "hist_high must be >= 2");
+        assert!(hist_sigfig <= 5, "hist_sigfig must be <= 5");
+        self.config.hist_high = hist_high;
+        self.config.hist_sigfig = hist_sigfig;
+        self
+    }
+
+    /// Sets the clock used to measure latencies. The default is [`ClockKind::Instant`].
+    ///
+    /// [`Self::run_with_cpu`] always measures wall-clock time with [`crate::InstantClock`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `clock` is not supported on the current platform (see [`ClockKind::is_supported`]).
+    pub fn clock(mut self, clock: ClockKind) -> Self {
+        assert!(
+            clock.is_supported(),
+            "clock {clock:?} is not supported on this platform"
+        );
+        self.config.clock = clock;
+        self
+    }
+
+    /// Sets the seed used to choose the order of the pairs in each duo pseudo-randomly
+    /// (see [`crate::bench_diff_randomized`]). By default, the fixed order is used.
+    ///
+    /// Not supported by [`Self::run_multi`], which doesn't execute duos.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sets whether panics of `f1` and `f2` are caught (with [`std::panic::catch_unwind`]). The default is
+    /// `false`, in which case a panic of either function propagates to the caller and the data collected so far
+    /// is lost.
+    ///
+    /// If panics are caught, the first panic of either function stops the benchmark gracefully: the duo in
+    /// progress is discarded and the result contains the data of the completed duos (see
+    /// [`DiffOut::duo_count`]), with [`crate::StopReason::Panic`] as the stop reason and the function that
+    /// panicked and the panic message available from [`DiffOut::panic`]. A suite can then report the failure
+    /// and continue with its next comparison. Notice that if the panic happens during the first half of data
+    /// collection, the result only contains duos that start with `f1`, so the effect of the order of execution
+    /// is not cancelled out.
+    ///
+    /// The panic hook still runs, so the panic message is output as usual. Catching panics adds a small
+    /// overhead to each measured latency, the same for both functions.
+    ///
+    /// Only panics of `f1` and `f2` are caught: a panic of the `setup` closure of [`Self::run_with_setup`] or
+    /// the `gen_input` closure of [`Self::run_paired_input`] propagates to the caller. For [`Self::run_multi`],
+    /// the panic is recorded in the output of each comparison that involves the function that panicked.
+    pub fn catch_panics(mut self, catch_panics: bool) -> Self {
+        self.catch_panics = catch_panics;
+        self
+    }
+
+    /// Sets the background stressors that run while each benchmark executes, to compare `f1` and `f2` under
+    /// contention (see [`BackgroundLoad`]). The default is idle, i.e., no stressors.
+    ///
+    /// The stressors are started before warm-up, so that warm-up reaches the steady state under load, and stopped
+    /// at the end of data collection. The load is recorded in the output (see [`DiffOut::background_load`]).
+    pub fn background_load(mut self, load: BackgroundLoad) -> Self {
+        self.background_load = load;
+        self
+    }
+
+    /// Pins the measuring thread, i.e., the thread that runs the benchmark, to `core` while each benchmark
+    /// executes, to avoid the noise caused by migrations between cores. By default, the thread is not pinned.
+    ///
+    /// This is implemented with `sched_setaffinity` on Linux and is not supported on other platforms. The original
+    /// affinity of the thread is restored at the end of the benchmark. If the thread can't be pinned, e.g.,
+    /// because `core` doesn't exist or is not allowed for the process, the benchmark runs unpinned and the
+    /// failure is reported as a [`crate::Warning::AffinityNotApplied`]. The core actually used is recorded in
+    /// the output (see [`DiffOut::thread_settings`]).
+    pub fn pin_to_core(mut self, core: usize) -> Self {
+        self.thread_settings.core = Some(core);
+        self
+    }
+
+    /// Sets the scheduling priority of the measuring thread while each benchmark executes (see
+    /// [`SchedPriority`]). The default is [`SchedPriority::Unchanged`].
+    ///
+    /// This is implemented with `sched_setscheduler` and `setpriority` on Linux and is not supported on other
+    /// platforms. The original scheduling of the thread is restored at the end of the benchmark. If the priority
+    /// can't be applied, typically for lack of permission, the benchmark runs with the original scheduling and
+    /// the failure is reported as a [`crate::Warning::PriorityNotApplied`]. The priority actually used is
+    /// recorded in the output (see [`DiffOut::thread_settings`]).
+    pub fn priority(mut self, priority: SchedPriority) -> Self {
+        self.thread_settings.priority = priority;
+        self
+    }
+
+    /// Sets whether the environment is checked for common sources of noise before each benchmark (see
+    /// [`crate::environment_check`]). The default is `false`.
+    ///
+    /// The result of the check is passed to the reporter (see [`Reporter::environment_check`]) and recorded in
+    /// the output (see [`DiffOut::environment`]). The check happens before any background load is started (see
+    /// [`Self::background_load`]) and takes about 200 milliseconds on Linux.
+    pub fn environment_check(mut self, check: bool) -> Self {
+        self.environment_check = check;
+        self
+    }
+
+    /// Sets whether the latencies of each pair of executions are retained in the order in which they were
+    /// measured (see [`DiffOut::samples`]). The default is `false`.
+    ///
+    /// The buffer for the samples, of `exec_count` entries, is allocated before the benchmark starts, so that
+    /// retention doesn't cause allocations during data collection. For the `run_` methods whose sample size is
+    /// determined during data collection (e.g., [`Self::run_adaptive`]), the buffer is grown between the rounds
+    /// of executions instead, so no allocations happen while the functions are being timed.
+    ///
+    /// With [`Self::run_with_cpu`], the samples are the wall-clock latencies; with [`Self::run_batched`], they
+    /// are the total latencies of the batches. Not supported by [`Self::run_multi`].
+    pub fn retain_samples(mut self, retain: bool) -> Self {
+        self.retain_samples = retain;
+        self
+    }
+
+    /// Sets whether the heap allocations of `f1` and `f2` are tracked (see [`DiffOut::allocations`]). The default
+    /// is `false`.
+    ///
+    /// Tracking requires a [`crate::CountingAllocator`] installed as the global allocator; otherwise, it is
+    /// skipped with a [`crate::Warning::AllocationsNotTracked`] warning. Only allocations made by the measuring
+    /// thread are attributed to the functions. The allocation counters are read outside of the measured latencies.
+    ///
+    /// Only supported by [`Self::run`], [`Self::run_adaptive`], [`Self::run_sequential`],
+    /// [`Self::run_with_budget`], and their `try_` variants.
+    pub fn track_allocations(mut self, track: bool) -> Self {
+        self.track_allocations = track;
+        self
+    }
+
+    /// Sets the reporter that receives the events of the benchmarks run by `self` (see [`Reporter`]), which can
+    /// cancel a benchmark. The default is [`SilentReporter`].
+    ///
+    /// The status closures of [`crate::bench_diff_x`] can be set with a [`crate::ClosureReporter`].
+    pub fn reporter(mut self, reporter: impl Reporter + 'a) -> Self {
+        self.reporter = Box::new(reporter);
+        self
+    }
+
+    /// Sets the reporter to [`stderr_reporter`], which outputs the benchmark status to `stderr` as
+    /// [`crate::bench_diff_with_status`] does.
+    pub fn stderr_status(self) -> Self {
+        self.reporter(stderr_reporter())
+    }
+
+    /// Compares latencies for two closures `f1` and `f2` according to the configuration of `self`.
+    ///
+    /// This function repeatedly executes *duos* of pairs (`f1`, `f2`), (`f2`, `f1`) and collects the resulting
+    /// latency data in a [`DiffOut`] object, as described in [`crate::bench_diff_x`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a latency of zero is measured (see [`BenchDiffError::ZeroLatency`]).
+    pub fn run(&mut self, f1: impl FnMut(), f2: impl FnMut()) -> DiffOut {
+        expect_ok(self.run_plain(f1, f2))
+    }
+
+    /// Fallible version of [`Self::run`], which returns an error instead of panicking.
+    ///
+    /// Returns [`BenchDiffError::InsufficientSamples`], before any execution, if the configured `exec_count`
+    /// would produce fewer than two observations for each function, and [`BenchDiffError::ZeroLatency`] if a
+    /// latency of zero is measured.
+    pub fn try_run(
+        &mut self,
+        f1: impl FnMut(),
+        f2: impl FnMut(),
+    ) -> Result<DiffOut, BenchDiffError> {
+        self.check_exec_count()?;
+        self.run_plain(f1, f2)
+    }
+
+    /// Returns [`BenchDiffError::InsufficientSamples`] if the configured `exec_count` would produce fewer than two
+    /// observations for each function in a benchmark with a fixed number of executions.
+    pub(super) fn check_exec_count(&self) -> Result<(), BenchDiffError> {
+        // Each of the two halves of data collection executes `exec_count / 4` duos of two pairs.
+        let n = (self.exec_count / 4 * 4) as u64;
+        if n < 2 {
+            return Err(BenchDiffError::InsufficientSamples { n, required: 2 });
+        }
+        Ok(())
+    }
+
+    /// Common implementation of [`Self::run`] and [`Self::try_run`].
+    fn run_plain(&mut self, f1: impl FnMut(), f2: impl FnMut()) -> Result<DiffOut, BenchDiffError> {
+        self.collect_fixed(&mut PlainDuos(f1, f2))
+    }
+
+    /// Collects the data for the duos of `source` with the number of executions of `self` (see
+    /// [`try_bench_duos_out_x`]) and the other settings of `self` (see [`Self::collect_duos`]).
+    pub(super) fn collect_fixed(
+        &mut self,
+        source: &mut impl DuoSource,
+    ) -> Result<DiffOut, BenchDiffError> {
+        let warmup_plan = self.config.warmup_plan(None);
+        let exec_count = self.exec_count;
+        // Each of the two halves of data collection executes `exec_count / 4` duos of two pairs.
+        let sample_capacity = exec_count / 4 * 4;
+        self.collect_duos(source, sample_capacity, |out, duo, reporter| {
+            try_bench_duos_out_x(out, warmup_plan, duo, exec_count, reporter)
+        })
+    }
+
+    /// Collects the data for the duos of `source` by invoking `collect`, i.e., [`try_bench_duos_out_x`] or
+    /// [`super::bench_diff::bench_duos_rounds_x`], with an empty output, the duo, and the reporter of `self`.
+    ///
+    /// The settings of `self` that don't depend on the benchmarking function family are applied here: the clock
+    /// and histograms of the output, the duo order, the environment check, the background load, the thread
+    /// settings, the catching of panics, and the retention of samples, in a buffer with an initial capacity of
+    /// `sample_capacity`. The allocations of the functions are tracked if `source` supports it. A
+    /// [`Warning::SettingNotSupported`] is added to the output for each setting that can't be applied.
+    pub(super) fn collect_duos<S: DuoSource>(
+        &mut self,
+        source: &mut S,
+        sample_capacity: usize,
+        collect: impl FnOnce(
+            DiffOut,
+            &mut dyn FnMut(Phase) -> DuoFlow,
+            &mut Box<dyn Reporter + 'a>,
+        ) -> Result<DiffOut, BenchDiffError>,
+    ) -> Result<DiffOut, BenchDiffError> {
+        let clock = source.clock(self.config.clock);
+        let mut out = self.config.new_out();
+        out.clock = clock;
+        if self.retain_samples {
+            out.samples = Some(Vec::with_capacity(sample_capacity));
+        }
+        if let Some(seed) = self.seed {
+            out.duo_order = DuoOrder::Randomized { seed };
+        }
+        let tracker = if !self.track_allocations {
+            None
+        } else if !S::TRACKS_ALLOCATIONS {
+            out.warnings.push(Warning::SettingNotSupported {
+                setting: "track_allocations",
+            });
+            None
+        } else if !counting_allocator_installed() {
+            out.warnings.push(Warning::AllocationsNotTracked);
+            None
+        } else {
+            Some(AllocTracker::new())
+        };
+
+        let _guard = self.start_run(&mut out);
+        let mut out = if self.catch_panics {
+            let catcher = PanicCatcher::new();
+            let mut out =
+                self.collect_handled(source, &catcher, clock, tracker.as_ref(), out, collect)?;
+            out.panic = catcher.into_panic();
+            out
+        } else {
+            self.collect_handled(
+                source,
+                &PropagatePanics,
+                clock,
+                tracker.as_ref(),
+                out,
+                collect,
+            )?
+        };
+        out.allocations = tracker.map(AllocTracker::into_comparison);
+        Ok(out)
+    }
+
+    /// Part of [`Self::collect_duos`] that executes the duos of `source`, with panics handled by `panics`.
+    fn collect_handled(
+        &mut self,
+        source: &mut impl DuoSource,
+        panics: &impl Panics,
+        clock: ClockKind,
+        tracker: Option<&AllocTracker>,
+        out: DiffOut,
+        collect: impl FnOnce(
+            DiffOut,
+            &mut dyn FnMut(Phase) -> DuoFlow,
+            &mut Box<dyn Reporter + 'a>,
+        ) -> Result<DiffOut, BenchDiffError>,
+    ) -> Result<DiffOut, BenchDiffError> {
+        let duo = self.ordered(source.duos(clock, panics, tracker));
+        let mut duo = tracked(tracker, panics.flow(duo));
+        collect(out, &mut duo, &mut self.reporter)
+    }
+
+    /// Starts the environment of a benchmark as configured in `self` and records it in `out`: checks the
+    /// environment if configured, starts the background load, and applies the thread settings to the calling
+    /// thread. The returned guard restores the original thread settings and stops the background load when
+    /// dropped.
+    pub(super) fn start_run(&mut self, out: &mut DiffOut) -> RunGuard {
+        if self.environment_check {
+            let check = environment_check();
+            self.reporter.environment_check(&check);
+            out.environment = Some(check);
+        }
+        out.background_load = self.background_load;
+
+        // Stressor threads are started first so that they don't inherit the settings of the measuring thread,
+        // and the settings are restored before the stressors are stopped.
+        let load = LoadGenerator::start(&self.background_load);
+        let (thread_settings, applied, warnings) = ThreadSettingsGuard::apply(self.thread_settings);
+        out.thread_settings = applied;
+        out.warnings.extend(warnings);
+
+        RunGuard {
+            _thread_settings: thread_settings,
+            _load: load,
+        }
+    }
+
+    /// Applies the duo order configured in `self` to `duo`.
+    fn ordered<'b>(
+        &self,
+        duo: impl FnMut(Phase) -> DuoPairs + 'b,
+    ) -> Box<dyn FnMut(Phase) -> DuoPairs + 'b> {
+        match self.seed {
+            None => Box::new(duo),
+            Some(seed) => Box::new(randomized(seed, duo)),
+        }
+    }
+
+    /// Adds a [`Warning::SettingNotSupported`] to `out` for each setting of `self` that is set but not supported by
+    /// a benchmarking function family that doesn't execute duos (see [`Self::run_multi`]).
+    pub(super) fn warn_unsupported_by_multi(&self, out: &mut DiffOut) {
+        let unsupported = [
+            ("seed", self.seed.is_some()),
+            ("retain_samples", self.retain_samples),
+            ("track_allocations", self.track_allocations),
+        ];
+        for (setting, set) in unsupported {
+            if set {
+                out.warnings.push(Warning::SettingNotSupported { setting });
+            }
+        }
+    }
+}
+
+/// Environment of a running benchmark (see [`BenchDiff::start_run`]). The fields are dropped in declaration order,
+/// so the original thread settings are restored before the background load is stopped.
+pub(super) struct RunGuard {
+    _thread_settings: ThreadSettingsGuard,
+    _load: LoadGenerator,
+}
+
+/// Wraps `duo` with [`AllocTracker::flow`] if `tracker` is not `None`.
+fn tracked<'b>(
+    tracker: Option<&'b AllocTracker>,
+    duo: impl FnMut(Phase) -> DuoFlow + 'b,
+) -> Box<dyn FnMut(Phase) -> DuoFlow + 'b> {
+    match tracker {
+        None => Box::new(duo),
+        Some(tracker) => Box::new(tracker.flow(duo)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        ClosureReporter, Half, NewInputPer, PrecisionTarget, SequentialTest, SimpleExecutor,
+        StopReason, TargetFn,
+    };
+    use std::{cell::Cell, ops::ControlFlow, time::Duration};
+
+    #[test]
+    fn test_catch_panics() {
+        // Calls of `f2` after the start of data collection.
+        let calls = Cell::new(None::<u32>);
+        let f2 = || {
+            if let Some(n) = calls.get() {
+                assert!(n < 100, "f2 failed");
+                calls.set(Some(n + 1));
+            }
+        };
+
+        let out = BenchDiff::new(LatencyUnit::Nano, 1_000)
+            .warmup_millis(1)
+            .catch_panics(true)
+            .reporter(ClosureReporter::new(
+                |_, _, _| (),
+                || calls.set(Some(0)),
+                |_| (),
+            ))
+            .run(|| (), f2);
+
+        assert_eq!(out.stop_reason(), StopReason::Panic);
+        let panic = out.panic().expect("f2 panicked");
+        assert_eq!(panic.target, TargetFn::F2);
+        assert_eq!(panic.message.as_deref(), Some("f2 failed"));
+        // Each duo calls `f2` twice and the duo in progress when `f2` panicked is discarded.
+        assert_eq!(out.duo_count(), 50);
+        assert_eq!(out.n(), 100);
+    }
+
+    #[test]
+    fn test_catch_panics_of_all_families() {
+        let mut bench = BenchDiff::new(LatencyUnit::Nano, 100)
+            .warmup_millis(1)
+            .catch_panics(true);
+        let f2 = || panic!("f2 failed");
+
+        let outs = [
+            bench.run_adaptive(
+                || (),
+                f2,
+                PrecisionTarget {
+                    rel_half_width: 0.5,
+                    alpha: 0.05,
+                    max_exec_count: 200,
+                    max_duration: Duration::from_secs(1),
+                },
+            ),
+            bench.run_sequential(
+                || (),
+                f2,
+                SequentialTest {
+                    margin: 0.5,
+                    alpha: 0.05,
+                    beta: 0.05,
+                    max_exec_count: 200,
+                },
+            ),
+            bench.run_with_budget(|| (), f2, Duration::from_millis(10)),
+            bench.run_with_setup(|| 1, |_| (), |_| f2()),
+            bench.run_paired_input(1, NewInputPer::Pair, |_| 1, |_| (), |_| f2()),
+            bench.run_batched(|| (), f2),
+            bench.run_with_cpu(|| (), f2),
+            bench.run_async(&mut SimpleExecutor::new(), || async {}, || async { f2() }),
+        ];
+        for out in &outs {
+            assert_eq!(out.stop_reason(), StopReason::Panic);
+            let panic = out.panic().expect("f2 panicked");
+            assert_eq!(panic.target, TargetFn::F2);
+            assert_eq!(panic.message.as_deref(), Some("f2 failed"));
+            assert_eq!(out.n(), 0);
+            assert!(
+                !out.warnings()
+                    .iter()
+                    .any(|warning| matches!(warning, Warning::SettingNotSupported { .. }))
+            );
+        }
+
+        let multi = bench.run_multi(&mut [&mut || (), &mut || f2(), &mut || ()]);
+        for (i, j, out) in multi.diffs() {
+            assert_eq!(out.stop_reason(), StopReason::Panic);
+            assert_eq!(out.n(), 0);
+            let target = out.panic().map(|panic| panic.target);
+            let expected = match (i, j) {
+                (0, 1) => Some(TargetFn::F2),
+                (1, 2) => Some(TargetFn::F1),
+                _ => None,
+            };
+            assert_eq!(target, expected);
+        }
+    }
+
+    #[test]
+    fn test_cancel() {
+        let out = BenchDiff::new(LatencyUnit::Nano, 1_000)
+            .warmup_millis(1)
+            .reporter(ClosureReporter::new(
+                |_, _, _| (),
+                || (),
+                |i| {
+                    if i < 200 {
+                        ControlFlow::Continue(())
+                    } else {
+                        ControlFlow::Break(())
+                    }
+                },
+            ))
+            .run(|| (), || ());
+
+        assert_eq!(out.stop_reason(), StopReason::Cancelled);
+        assert!(out.is_truncated());
+        // The duo after which `exec_status` breaks is kept.
+        assert_eq!(out.n(), 200);
+
+        let out = BenchDiff::new(LatencyUnit::Nano, 1_000)
+            .reporter(ClosureReporter::new(
+                |_, _, _| ControlFlow::Break(()),
+                || (),
+                |_| (),
+            ))
+            .run(|| (), || ());
+
+        assert_eq!(out.stop_reason(), StopReason::Cancelled);
+        assert_eq!(out.n(), 0);
+    }
+
+    #[test]
+    fn test_retain_samples() {
+        let out = BenchDiff::new(LatencyUnit::Nano, 1_000)
+            .warmup_millis(1)
+            .run(|| (), || ());
+        assert!(out.samples().is_none());
+
+        let out = BenchDiff::new(LatencyUnit::Nano, 1_000)
+            .warmup_millis(1)
+            .retain_samples(true)
+            .run(|| (), || ());
+        let samples = out.samples().expect("samples are retained");
+        assert_eq!(samples.len() as u64, out.n());
+
+        let (mut sum1, mut sum2) = (0, 0);
+        for (i, sample) in samples.enumerate() {
+            assert_eq!(sample.duo_index, i as u64 / 2);
+            let half = if i < 500 {
+                Half::Forward
+            } else {
+                Half::Reversed
+            };
+            assert_eq!(sample.half, half);
+            sum1 += sample.elapsed1 as i64;
+            sum2 += sample.elapsed2 as i64;
+        }
+        assert_eq!((sum1, sum2), (out.sum_f1, out.sum_f2));
+    }
+
+    #[test]
+    fn test_allocations_not_tracked() {
+        // The lib test binary uses the system allocator; see `tests/allocations.rs` for tracked allocations.
+        let out = BenchDiff::new(LatencyUnit::Nano, 100)
+            .warmup_millis(1)
+            .track_allocations(true)
+            .run(|| (), || ());
+
+        assert!(out.allocations().is_none());
+        assert!(out.warnings().contains(&Warning::AllocationsNotTracked));
+    }
+
+    #[test]
+    fn test_settings_of_all_families() {
+        let mut bench = BenchDiff::new(LatencyUnit::Nano, 100)
+            .warmup_millis(1)
+            .seed(42)
+            .retain_samples(true)
+            .track_allocations(true);
+
+        let tracked = [
+            bench.run(|| (), || ()),
+            bench.run_adaptive(
+                || (),
+                || (),
+                PrecisionTarget {
+                    rel_half_width: 0.5,
+                    alpha: 0.05,
+                    max_exec_count: 200,
+                    max_duration: Duration::from_secs(1),
+                },
+            ),
+            bench.run_sequential(
+                || (),
+                || (),
+                SequentialTest {
+                    margin: 0.5,
+                    alpha: 0.05,
+                    beta: 0.05,
+                    max_exec_count: 200,
+                },
+            ),
+            bench.run_with_budget(|| (), || (), Duration::from_millis(10)),
+        ];
+        let untracked = [
+            bench.run_with_setup(|| 1, |_| (), |_| ()),
+            bench.run_paired_input(1, NewInputPer::Pair, |_| 1, |_| (), |_| ()),
+            bench.run_batched(|| (), || ()),
+            bench.run_async(&mut SimpleExecutor::new(), || async {}, || async {}),
+        ];
+
+        for out in tracked.iter().chain(&untracked) {
+            assert_eq!(out.duo_order(), DuoOrder::Randomized { seed: 42 });
+            let samples = out.samples().expect("samples are retained");
+            assert_eq!(samples.len() as u64, out.n());
+        }
+        // The lib test binary uses the system allocator.
+        let not_supported = Warning::SettingNotSupported {
+            setting: "track_allocations",
+        };
+        for out in &tracked {
+            assert!(out.warnings().contains(&Warning::AllocationsNotTracked));
+            assert!(!out.warnings().contains(&not_supported));
+        }
+        for out in &untracked {
+            assert!(out.warnings().contains(&not_supported));
+            assert!(!out.warnings().contains(&Warning::AllocationsNotTracked));
+        }
+
+        let multi = bench.run_multi(&mut [&mut || (), &mut || ()]);
+        let out = multi.diff(0, 1);
+        assert_eq!(out.duo_order(), DuoOrder::Fixed);
+        assert!(out.samples().is_none());
+        let settings = out
+            .warnings()
+            .iter()
+            .filter_map(|warning| match warning {
+                Warning::SettingNotSupported { setting } => Some(*setting),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(settings, ["seed", "retain_samples", "track_allocations"]);
+    }
+
+    #[test]
+    fn test_run_config() {
+        // A clock other than the default, if any is supported.
+        let clock = [ClockKind::MonotonicRaw, ClockKind::Tsc]
+            .into_iter()
+            .find(|clock| clock.is_supported())
+            .unwrap_or(ClockKind::Instant);
+        let mut bench = BenchDiff::new(LatencyUnit::Micro, 100)
+            .warmup_millis(1)
+            .clock(clock)
+            .histogram(1_000, 3);
+
+        let outs = [
+            bench.run(|| (), || ()),
+            bench.run_with_setup(|| 1, |_| (), |_| ()),
+            bench.run_paired_input(1, NewInputPer::Pair, |_| 1, |_| (), |_| ()),
+            bench.run_batched(|| (), || ()),
+            bench.run_async(&mut SimpleExecutor::new(), || async {}, || async {}),
+            bench.run_adaptive(
+                || (),
+                || (),
+                PrecisionTarget {
+                    rel_half_width: 0.5,
+                    alpha: 0.05,
+                    max_exec_count: 200,
+                    max_duration: Duration::from_secs(1),
+                },
+            ),
+            bench.run_sequential(
+                || (),
+                || (),
+                SequentialTest {
+                    margin: 0.5,
+                    alpha: 0.05,
+                    beta: 0.05,
+                    max_exec_count: 200,
+                },
+            ),
+            bench.run_with_budget(|| (), || (), Duration::from_millis(10)),
+        ];
+        let multi = bench.run_multi(&mut [&mut || (), &mut || (), &mut || ()]);
+
+        for out in outs.iter().chain(multi.diffs().map(|(_, _, out)| out)) {
+            assert_eq!(out.clock(), clock);
+            assert_eq!(out.unit(), LatencyUnit::Micro);
+            assert!(out.warmup().duration < Duration::from_millis(100));
+            assert!(out.n() > 0);
+        }
+    }
+
+    #[test]
+    fn test_try_run_insufficient_samples() {
+        // Runs that would fail if any function were executed.
+        let f1 = || panic!("f1 executed");
+        let f2 = || panic!("f2 executed");
+        let mut bench = BenchDiff::new(LatencyUnit::Nano, 3);
+
+        let results = [
+            bench.try_run(f1, f2).map(|_| ()),
+            bench
+                .try_run_with_setup(|| (), |_| f1(), |_| f2())
+                .map(|_| ()),
+            bench
+                .try_run_paired_input(1, NewInputPer::Pair, |_| (), |_| f1(), |_| f2())
+                .map(|_| ()),
+            bench.try_run_batched(f1, f2).map(|_| ()),
+            bench
+                .try_run_async(
+                    &mut SimpleExecutor::new(),
+                    || async { f1() },
+                    || async { f2() },
+                )
+                .map(|_| ()),
+            BenchDiff::new(LatencyUnit::Nano, 0)
+                .try_run_multi(&mut [&mut f1.clone(), &mut f2.clone()])
+                .map(|_| ()),
+        ];
+
+        for result in results {
+            assert!(matches!(
+                result,
+                Err(BenchDiffError::InsufficientSamples { required: 2, .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Module implementing functions to compare the difference in both wall-clock time and CPU time between two
+//! closures.
+
+use super::{
+    BenchDiff, BenchDiffError, Clock, ClockKind, ClosureReporter, DiffOut, DiffState, InstantClock,
+    LatencyUnit, Reporter, StatusFlow, TargetFn, ThreadCpuClock,
+    allocations::AllocTracker,
+    bench_diff::{DuoPairs, DuoSource, Panics, Phase, expect_ok},
+    stderr_reporter,
+};
+use std::time::Duration;
+
+/// Invokes `f` once and returns its wall-clock latency and its thread CPU time, in that order.
+#[inline(always)]
+fn latency_wall_cpu(f: impl FnOnce()) -> (Duration, Duration) {
+    let wall_start = InstantClock::now();
+    let cpu_start = ThreadCpuClock::now();
+    f();
+    let cpu_end = ThreadCpuClock::now();
+    let wall_end = InstantClock::now();
+    (
+        InstantClock::elapsed(wall_start, wall_end),
+        ThreadCpuClock::elapsed(cpu_start, cpu_end),
+    )
+}
+
+/// Invokes `f1` then `f2` then `f2` then `f1` and returns two pairs of wall-clock latencies followed by the
+/// corresponding two pairs of thread CPU times. For each pair, the first component is an `f1` value and the second
+/// component is an `f2` value.
+#[inline(always)]
+fn duo_exec_wall_cpu(mut f1: impl FnMut(), mut f2: impl FnMut()) -> (DuoPairs, DuoPairs) {
+    let (w01, c01) = latency_wall_cpu(&mut f1);
+    let (w02, c02) = latency_wall_cpu(&mut f2);
+
+    let (w12, c12) = latency_wall_cpu(&mut f2);
+    let (w11, c11) = latency_wall_cpu(&mut f1);
+
+    ([(w01, w02), (w11, w12)], [(c01, c02), (c11, c12)])
+}
+
+/// Minimum thread CPU time recorded by [`capture_cpu_times`].
+const MIN_CPU_TIME: Duration = Duration::from_nanos(1);
+
+/// Captures a pair of thread CPU times in `state`. The thread CPU clock may not advance during a short invocation,
+/// as its resolution is platform-dependent, so CPU times of `0` are recorded as [`MIN_CPU_TIME`].
+fn capture_cpu_times(state: &mut DiffState, cpu1: Duration, cpu2: Duration) {
+    state
+        .capture_latencies(cpu1.max(MIN_CPU_TIME), cpu2.max(MIN_CPU_TIME))
+        .expect("can't happen: CPU times are at least 1 ns");
+}
+
+/// [`DuoSource`] of two closures whose wall-clock latencies and thread CPU times are measured by
+/// [`duo_exec_wall_cpu`]. The CPU times are collected into `cpu_out`.
+struct CpuDuos<F1, F2> {
+    f1: F1,
+    f2: F2,
+    cpu_out: DiffOut,
+}
+
+impl<F1: FnMut(), F2: FnMut()> DuoSource for CpuDuos<F1, F2> {
+    fn clock(&self, _configured: ClockKind) -> ClockKind {
+        ClockKind::Instant
+    }
+
+    fn duos<'a>(
+        &'a mut self,
+        _clock: ClockKind,
+        panics: &'a impl Panics,
+        _tracker: Option<&'a AllocTracker>,
+    ) -> impl FnMut(Phase) -> DuoPairs + 'a {
+        let Self { f1, f2, cpu_out } = self;
+        let mut f1 = panics.wrap(TargetFn::F1, f1);
+        let mut f2 = panics.wrap(TargetFn::F2, f2);
+        let mut cpu_state = DiffState::new(cpu_out);
+        let mut prev_phase = Phase::Warmup;
+
+        move |phase| {
+            if prev_phase == Phase::Warmup && phase != Phase::Warmup {
+                cpu_state.reset();
+            }
+            prev_phase = phase;
+
+            let (wall_pairs, cpu_pairs) = match phase {
+                Phase::Warmup | Phase::Forward => duo_exec_wall_cpu(&mut f1, &mut f2),
+                Phase::Reversed => duo_exec_wall_cpu(&mut f2, &mut f1),
+            };
+            if panics.panicked() {
+                // The duo is discarded (see `Panics::flow`).
+                return wall_pairs;
+            }
+
+            for (cpu1, cpu2) in cpu_pairs {
+                let (cpu1, cpu2) = match phase {
+                    Phase::Warmup | Phase::Forward => (cpu1, cpu2),
+                    Phase::Reversed => (cpu2, cpu1),
+                };
+                capture_cpu_times(&mut cpu_state, cpu1, cpu2);
+            }
+
+            wall_pairs
+        }
+    }
+}
+
+/// Compares both the wall-clock latencies and the CPU times of two closures `f1` and `f2`, and *optionally*
+/// outputs information about the benchmark and its execution status.
+///
+/// This function is like [`crate::bench_diff_x`] except that each invocation of `f1` or `f2` is timed with both
+/// [`InstantClock`] and [`ThreadCpuClock`], regardless of the clock selected with [`BenchDiff::clock`]. The
+/// returned [`DiffOut`] contains the wall-clock data and [`DiffOut::cpu`] contains the parallel CPU time data,
+/// so the same statistics (e.g., [`DiffOut::welch_ratio_ci`]) are available for both. The time during which
+/// a function is not running on the CPU (e.g., waiting for locks or I/O, or preempted) is summarized by
+/// [`DiffOut::off_cpu_fraction_f1`] and [`DiffOut::off_cpu_fraction_f2`].
+///
+/// CPU times are recorded in nanoseconds; an invocation during which the thread CPU clock does not advance is
+/// recorded as taking one nanosecond.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `warmup_status` - see [`crate::bench_diff_x`].
+/// - `pre_exec` - see [`crate::bench_diff_x`].
+/// - `exec_status` - see [`crate::bench_diff_x`].
+///
+/// # Panics
+///
+/// Panics if [`ClockKind::ThreadCpu`] is not supported on the current platform.
+pub fn bench_diff_with_cpu_x<W: StatusFlow, E: StatusFlow>(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+    warmup_status: impl FnMut(usize, u64, u64) -> W,
+    pre_exec: impl FnOnce(),
+    exec_status: impl FnMut(usize) -> E,
+) -> DiffOut {
+    bench_diff_with_cpu_and_reporter(
+        unit,
+        f1,
+        f2,
+        exec_count,
+        &mut ClosureReporter::new(warmup_status, pre_exec, exec_status),
+    )
+}
+
+/// Compares both the wall-clock latencies and the CPU times of two closures `f1` and `f2`, and reports the progress
+/// of the benchmark to `reporter`.
+///
+/// This function is like [`bench_diff_with_cpu_x`] except that the events of the benchmark are reported to
+/// `reporter` instead of status closures.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `reporter` - receives the events of the benchmark (see [`Reporter`]).
+///
+/// # Panics
+///
+/// Panics if [`ClockKind::ThreadCpu`] is not supported on the current platform.
+pub fn bench_diff_with_cpu_and_reporter(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+    reporter: &mut impl Reporter,
+) -> DiffOut {
+    BenchDiff::new(unit, exec_count)
+        .reporter(reporter)
+        .run_with_cpu(f1, f2)
+}
+
+/// Compares both the wall-clock latencies and the CPU times of two closures `f1` and `f2`.
+///
+/// This function calls [`bench_diff_with_cpu_x`] with no-op closures for the arguments that support the output of
+/// benchmark status.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+pub fn bench_diff_with_cpu(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+) -> DiffOut {
+    bench_diff_with_cpu_x(unit, f1, f2, exec_count, |_, _, _| {}, || (), |_| ())
+}
+
+/// Compares both the wall-clock latencies and the CPU times of two closures `f1` and `f2`, and outputs
+/// information about the benchmark and its execution status. Execution status is output to `stderr`.
+///
+/// This function calls [`bench_diff_with_cpu_and_reporter`] with [`stderr_reporter`] (see
+/// [`crate::bench_diff_with_status`]).
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `exec_count` - number of executions (sample size) for each function.
+/// - `header` - see [`crate::bench_diff_with_status`].
+pub fn bench_diff_with_cpu_and_status(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    exec_count: usize,
+    header: impl FnOnce(LatencyUnit, usize),
+) -> DiffOut {
+    header(unit, exec_count);
+
+    bench_diff_with_cpu_and_reporter(unit, f1, f2, exec_count, &mut stderr_reporter())
+}
+
+impl BenchDiff<'_> {
+    /// Compares both the wall-clock latencies and the CPU times of two closures `f1` and `f2`, according to the
+    /// configuration of `self` (see [`bench_diff_with_cpu_x`]). The clock of `self` is not used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`ClockKind::ThreadCpu`] is not supported on the current platform or a wall-clock latency of zero
+    /// is measured (see [`BenchDiffError::ZeroLatency`]).
+    pub fn run_with_cpu(&mut self, f1: impl FnMut(), f2: impl FnMut()) -> DiffOut {
+        expect_ok(self.try_run_with_cpu(f1, f2))
+    }
+
+    /// Fallible version of [`Self::run_with_cpu`], which returns an error instead of panicking on a latency of zero
+    /// or an insufficient sample size (see [`Self::try_run`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`ClockKind::ThreadCpu`] is not supported on the current platform.
+    pub fn try_run_with_cpu(
+        &mut self,
+        f1: impl FnMut(),
+        f2: impl FnMut(),
+    ) -> Result<DiffOut, BenchDiffError> {
+        assert!(
+            ClockKind::ThreadCpu.is_supported(),
+            "clock {:?} is not supported on this platform",
+            ClockKind::ThreadCpu
+        );
+
+        self.check_exec_count()?;
+
+        let mut cpu_out = self.config.new_out();
+        cpu_out.clock = ClockKind::ThreadCpu;
+        let mut source = CpuDuos { f1, f2, cpu_out };
+        let mut out = self.collect_fixed(&mut source)?;
+
+        out.cpu = Some(Box::new(source.cpu_out));
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_zero_cpu_time() {
+        let mut out = DiffOut::new();
+        let mut state = DiffState::new(&mut out);
+        capture_cpu_times(&mut state, Duration::ZERO, Duration::from_nanos(5));
+        capture_cpu_times(&mut state, Duration::from_nanos(5), Duration::ZERO);
+
+        assert_eq!(out.n(), 2);
+        assert_eq!(out.summary_f1().min, 1);
+        assert_eq!(out.summary_f2().min, 1);
+        assert_eq!(out.summary_f1().max, 5);
+    }
+}
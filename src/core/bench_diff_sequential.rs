@@ -0,0 +1,457 @@
+//! Module implementing functions to compare the difference in latency between two closures using a sequential
+//! hypothesis test, which stops data collection as soon as a decision can be made.
+
+use super::{
+    BenchDiff, BenchDiffError, ClosureReporter, DiffOut, LatencyUnit, Reporter, StatusFlow,
+    StopReason,
+    bench_diff::{PlainDuos, bench_duos_rounds_x, expect_ok},
+    stderr_reporter,
+};
+use std::ops::ControlFlow;
+
+/// Number of duos per phase in the first data collection round of [`bench_diff_sequential_x`], chosen so that
+/// the variance estimate used by the test is based on a reasonable number of observations.
+const FIRST_ROUND_DUOS: usize = 25;
+
+/// Number of duos per phase in each subsequent data collection round of [`bench_diff_sequential_x`].
+const ROUND_DUOS: usize = 5;
+
+/// Parameters of the sequential test used by the sequential benchmarking functions
+/// (e.g., [`bench_diff_sequential`]).
+#[derive(Clone, Copy, Debug)]
+pub struct SequentialTest {
+    /// Relative equivalence margin for the ratio of medians. For example, `0.01` means that the functions are
+    /// considered equivalent if the ratio of their median latencies is within 1% of `1`, and that a ratio of
+    /// `1.01` (or `1 / 1.01`) is a difference that the test should detect.
+    pub margin: f64,
+    /// Nominal probability of declaring a difference when the medians are equal.
+    pub alpha: f64,
+    /// Nominal probability of failing to declare a difference when the ratio of medians is `1 + margin`
+    /// (or `1 / (1 + margin)`).
+    pub beta: f64,
+    /// Maximum number of executions (sample size) for each function. If it is reached before a decision is
+    /// made, the outcome is [`SequentialDecision::Undecided`].
+    pub max_exec_count: usize,
+}
+
+/// Decision of a sequential test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SequentialDecision {
+    /// `f1` is faster than `f2`, i.e., `median(latency(f1)) / median(latency(f2))` is below `1`.
+    Faster,
+    /// `f1` is slower than `f2`, i.e., `median(latency(f1)) / median(latency(f2))` is above `1`.
+    Slower,
+    /// `f1` and `f2` are equivalent within the margin.
+    Equivalent,
+    /// No decision was made before the maximum number of executions was reached.
+    Undecided,
+}
+
+/// Outcome of a sequential test, available from [`DiffOut::sequential_outcome`].
+#[derive(Clone, Copy, Debug)]
+pub struct SequentialOutcome {
+    /// The decision of the test.
+    pub decision: SequentialDecision,
+    /// The test's parameters.
+    pub test: SequentialTest,
+    /// Upper bound on the probability of declaring [`SequentialDecision::Faster`] (or, symmetrically,
+    /// [`SequentialDecision::Slower`]) when the medians are equal, i.e., `alpha / (1 - beta)`.
+    pub alpha_bound: f64,
+    /// Upper bound on the probability of *not* declaring [`SequentialDecision::Faster`] (or, symmetrically,
+    /// [`SequentialDecision::Slower`]) when the ratio of medians is `1 / (1 + margin)` (or `1 + margin`),
+    /// i.e., `beta / (1 - alpha)`.
+    pub beta_bound: f64,
+}
+
+impl SequentialTest {
+    /// Returns [`BenchDiffError::Statistics`] if `alpha` or `beta` is not in the open interval `(0, 1)`, their sum
+    /// is not less than `1`, or `margin` is not positive, and [`BenchDiffError::InsufficientSamples`] if
+    /// `max_exec_count` doesn't allow a single duo in each half of data collection.
+    fn check(&self) -> Result<(), BenchDiffError> {
+        let in_unit_interval = |p: f64| p > 0. && p < 1.;
+        if !in_unit_interval(self.alpha) {
+            return Err(BenchDiffError::Statistics(
+                "alpha must be in the interval (0, 1)",
+            ));
+        }
+        if !in_unit_interval(self.beta) {
+            return Err(BenchDiffError::Statistics(
+                "beta must be in the interval (0, 1)",
+            ));
+        }
+        if self.alpha + self.beta >= 1. {
+            return Err(BenchDiffError::Statistics(
+                "alpha + beta must be less than 1",
+            ));
+        }
+        if !(self.margin > 0. && self.margin.is_finite()) {
+            return Err(BenchDiffError::Statistics(
+                "margin must be positive and finite",
+            ));
+        }
+        // Each round executes the same number of duos of two pairs in each half of data collection.
+        let n = (self.max_exec_count / 4 * 4) as u64;
+        if n < 4 {
+            return Err(BenchDiffError::InsufficientSamples { n, required: 4 });
+        }
+        Ok(())
+    }
+
+    /// Returns the decision of the test given `n` paired observations, the sum `sum_diff_ln` of the differences of
+    /// the natural logarithms of the paired latencies, and the variance `var_diff_ln` of those differences.
+    ///
+    /// The test is a Sobel-Wald three-decision procedure made of two sequential probability ratio tests (SPRTs)
+    /// for the mean `mu` of the differences, assumed normal: one of `mu == -delta` vs. `mu == 0` and one of
+    /// `mu == 0` vs. `mu == delta`, where `delta = ln(1 + margin)`. Both SPRTs use Wald's boundaries
+    /// for `alpha` and `beta`, with `mu == 0` as the null hypothesis, so [`SequentialDecision::Faster`] and
+    /// [`SequentialDecision::Slower`] are each declared wrongly with probability at most `alpha / (1 - beta)`.
+    pub(crate) fn decision(
+        &self,
+        n: u64,
+        sum_diff_ln: f64,
+        var_diff_ln: f64,
+    ) -> SequentialDecision {
+        let delta = self.margin.ln_1p();
+        let nf = n as f64;
+        let var = var_diff_ln.max(f64::MIN_POSITIVE);
+
+        let upper = ((1. - self.beta) / self.alpha).ln();
+        let lower = (self.beta / (1. - self.alpha)).ln();
+
+        // Log-likelihood ratios of `mu == 0` to `mu == -delta`, and of `mu == delta` to `mu == 0`. The boundaries
+        // apply to the ratio of the alternative to the null hypothesis, which is `-llr_lo` for the lower SPRT.
+        let llr_lo = delta / var * (sum_diff_ln + nf * delta / 2.);
+        let llr_hi = delta / var * (sum_diff_ln - nf * delta / 2.);
+
+        if llr_lo <= -upper {
+            SequentialDecision::Faster
+        } else if llr_hi >= upper {
+            SequentialDecision::Slower
+        } else if llr_lo >= -lower && llr_hi <= lower {
+            SequentialDecision::Equivalent
+        } else {
+            SequentialDecision::Undecided
+        }
+    }
+
+    /// Returns the outcome of the test with decision `decision`.
+    fn outcome(&self, decision: SequentialDecision) -> SequentialOutcome {
+        SequentialOutcome {
+            decision,
+            test: *self,
+            alpha_bound: self.alpha / (1. - self.beta),
+            beta_bound: self.beta / (1. - self.alpha),
+        }
+    }
+}
+
+/// Compares latencies for two closures `f1` and `f2` using a sequential test that stops data collection as soon as
+/// `f1` can be declared faster than, slower than, or equivalent to `f2`, and *optionally* outputs information
+/// about the benchmark and its execution status.
+///
+/// After warm-up, this function repeatedly executes *rounds* of duos of pairs (`f1`, `f2`), (`f2`, `f1`),
+/// followed by the same number of duos of pairs (`f2`, `f1`), (`f1`, `f2`). After each round, the sequential
+/// test (see [`SequentialTest`]) is applied to the differences of the natural logarithms of the paired latencies
+/// collected so far, with their variance estimated from the same differences (see
+/// [`DiffOut::stdev_diff_ln_f1_f2`]).
+///
+/// The outcome of the test, including the error probability bounds that apply to it, is recorded in the output
+/// (see [`DiffOut::sequential_outcome`]). These bounds, rather than the nominal `alpha` of the fixed-sample-size
+/// statistics of [`DiffOut`], are the ones that apply to the decision. The bounds are approximate because the
+/// variance is estimated from the data.
+///
+/// Data collection stops with [`StopReason::SequentialDecision`] if a decision is made or with
+/// [`StopReason::MaxExecCount`] if `test.max_exec_count` is reached first.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `test` - parameters of the sequential test.
+/// - `warmup_status` - see [`crate::bench_diff_x`].
+/// - `pre_exec` - see [`crate::bench_diff_x`].
+/// - `exec_status` - see [`crate::bench_diff_x`].
+pub fn bench_diff_sequential_x<W: StatusFlow, E: StatusFlow>(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    test: SequentialTest,
+    warmup_status: impl FnMut(usize, u64, u64) -> W,
+    pre_exec: impl FnOnce(),
+    exec_status: impl FnMut(usize) -> E,
+) -> DiffOut {
+    bench_diff_sequential_with_reporter(
+        unit,
+        f1,
+        f2,
+        test,
+        &mut ClosureReporter::new(warmup_status, pre_exec, exec_status),
+    )
+}
+
+/// Compares latencies for two closures `f1` and `f2` using a sequential test that stops data collection as soon as
+/// `f1` can be declared faster than, slower than, or equivalent to `f2`, and reports the progress of the benchmark
+/// to `reporter`.
+///
+/// This function is like [`bench_diff_sequential_x`] except that the events of the benchmark are reported to
+/// `reporter` instead of status closures.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `test` - parameters of the sequential test.
+/// - `reporter` - receives the events of the benchmark (see [`Reporter`]).
+pub fn bench_diff_sequential_with_reporter(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    test: SequentialTest,
+    reporter: &mut impl Reporter,
+) -> DiffOut {
+    BenchDiff::new(unit, test.max_exec_count)
+        .reporter(reporter)
+        .run_sequential(f1, f2, test)
+}
+
+/// Compares latencies for two closures `f1` and `f2` using a sequential test that stops data collection as soon as
+/// `f1` can be declared faster than, slower than, or equivalent to `f2`.
+///
+/// This function calls [`bench_diff_sequential_x`] with no-op closures for the arguments that support the output
+/// of benchmark status.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `test` - parameters of the sequential test.
+pub fn bench_diff_sequential(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    test: SequentialTest,
+) -> DiffOut {
+    bench_diff_sequential_x(unit, f1, f2, test, |_, _, _| {}, || (), |_| ())
+}
+
+/// Compares latencies for two closures `f1` and `f2` using a sequential test that stops data collection as soon as
+/// `f1` can be declared faster than, slower than, or equivalent to `f2`, and outputs information about the
+/// benchmark and its execution status. Execution status is output to `stderr`.
+///
+/// This function calls [`bench_diff_sequential_with_reporter`] with [`stderr_reporter`] (see
+/// [`crate::bench_diff_with_status`]).
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `f1` - first target for comparison.
+/// - `f2` - second target for comparison.
+/// - `test` - parameters of the sequential test.
+/// - `header` - see [`crate::bench_diff_with_status`]; its second argument is `test.max_exec_count`.
+pub fn bench_diff_sequential_with_status(
+    unit: LatencyUnit,
+    f1: impl FnMut(),
+    f2: impl FnMut(),
+    test: SequentialTest,
+    header: impl FnOnce(LatencyUnit, usize),
+) -> DiffOut {
+    header(unit, test.max_exec_count);
+
+    bench_diff_sequential_with_reporter(unit, f1, f2, test, &mut stderr_reporter())
+}
+
+impl BenchDiff<'_> {
+    /// Compares latencies for two closures `f1` and `f2` using a sequential test, according to the configuration
+    /// of `self` (see [`bench_diff_sequential_x`]). The sample size of `self` is not used; the maximum sample size
+    /// is `test.max_exec_count`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a latency of zero is measured (see [`BenchDiffError::ZeroLatency`]) or `test` is invalid (see
+    /// [`Self::try_run_sequential`]).
+    pub fn run_sequential(
+        &mut self,
+        f1: impl FnMut(),
+        f2: impl FnMut(),
+        test: SequentialTest,
+    ) -> DiffOut {
+        expect_ok(self.try_run_sequential(f1, f2, test))
+    }
+
+    /// Fallible version of [`Self::run_sequential`], which returns [`BenchDiffError::ZeroLatency`] instead of
+    /// panicking if a latency of zero is measured. Before running the benchmark, it returns
+    /// [`BenchDiffError::Statistics`] if `test.alpha` or `test.beta` is not in the open interval `(0, 1)`, their
+    /// sum is not less than `1`, or `test.margin` is not positive, and [`BenchDiffError::InsufficientSamples`] if
+    /// `test.max_exec_count` is less than `4`.
+    pub fn try_run_sequential(
+        &mut self,
+        f1: impl FnMut(),
+        f2: impl FnMut(),
+        test: SequentialTest,
+    ) -> Result<DiffOut, BenchDiffError> {
+        test.check()?;
+
+        let mut decision = SequentialDecision::Undecided;
+        let mut started = false;
+
+        let next_round = |out: &DiffOut| {
+            let n = out.n();
+
+            if !started {
+                // `out` contains the warm-up data
+                started = true;
+                return Ok(ControlFlow::Continue(
+                    FIRST_ROUND_DUOS.min(test.max_exec_count / 4),
+                ));
+            }
+
+            let sum_diff_ln = out.sum_ln_f1 - out.sum_ln_f2;
+            let var_diff_ln = out.try_stdev_diff_ln_f1_f2()?.powi(2);
+            decision = test.decision(n, sum_diff_ln, var_diff_ln);
+            if decision != SequentialDecision::Undecided {
+                return Ok(ControlFlow::Break(StopReason::SequentialDecision));
+            }
+
+            let max_duos = test.max_exec_count.saturating_sub(n as usize) / 4;
+            if max_duos == 0 {
+                return Ok(ControlFlow::Break(StopReason::MaxExecCount));
+            }
+
+            Ok(ControlFlow::Continue(ROUND_DUOS.min(max_duos)))
+        };
+
+        let warmup_plan = self.config.warmup_plan(None);
+        let mut out = self.collect_duos(&mut PlainDuos(f1, f2), 0, |out, duo, reporter| {
+            bench_duos_rounds_x(
+                out,
+                warmup_plan,
+                duo,
+                Some(test.max_exec_count),
+                reporter,
+                next_round,
+            )
+        })?;
+
+        out.sequential_outcome = Some(test.outcome(decision));
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SequentialDecision, SequentialTest};
+    use crate::{BenchDiff, BenchDiffError, LatencyUnit};
+
+    const TEST: SequentialTest = SequentialTest {
+        margin: 0.01,
+        alpha: 0.05,
+        beta: 0.05,
+        max_exec_count: 10_000,
+    };
+
+    #[test]
+    fn test_sequential_decision() {
+        let delta = TEST.margin.ln_1p();
+        let var = 0.01;
+
+        // Mean difference far below `-delta`.
+        assert_eq!(
+            TEST.decision(1000, 1000. * -3. * delta, var),
+            SequentialDecision::Faster
+        );
+
+        // Mean difference far above `delta`.
+        assert_eq!(
+            TEST.decision(1000, 1000. * 3. * delta, var),
+            SequentialDecision::Slower
+        );
+
+        // Mean difference of `0` with enough data.
+        assert_eq!(TEST.decision(1000, 0., var), SequentialDecision::Equivalent);
+
+        // Mean difference of `0` with too little data.
+        assert_eq!(TEST.decision(10, 0., var), SequentialDecision::Undecided);
+
+        // Mean difference between `0` and `delta` with too little data to separate them.
+        assert_eq!(
+            TEST.decision(100, 100. * delta / 2., var),
+            SequentialDecision::Undecided
+        );
+    }
+
+    #[test]
+    fn test_sequential_decision_asymmetric_errors() {
+        // With `alpha != beta`, Wald's boundaries are asymmetric, so they must be applied to each SPRT in the
+        // direction of its alternative hypothesis.
+        let test = SequentialTest {
+            alpha: 0.05,
+            beta: 0.2,
+            ..TEST
+        };
+        let delta = test.margin.ln_1p();
+        let var = 0.01;
+
+        // Sum of `n` differences for which the log-likelihood ratio of `mu == -delta` to `mu == 0` is `llr`.
+        let sum_for_faster_llr = |n: u64, llr: f64| -llr * var / delta - n as f64 * delta / 2.;
+
+        // `ln((1 - beta) / alpha)` is about `2.77` and `ln(beta / (1 - alpha))` is about `-1.56`, so a ratio of `2`
+        // in favor of `mu == -delta` is not enough evidence to declare `Faster`.
+        assert_eq!(
+            test.decision(100, sum_for_faster_llr(100, 2.), var),
+            SequentialDecision::Undecided
+        );
+        assert_eq!(
+            test.decision(100, sum_for_faster_llr(100, 3.), var),
+            SequentialDecision::Faster
+        );
+
+        // With a mean difference of `0`, both ratios in favor of `mu == 0` are about `2` for this `n`, which is
+        // enough to accept `mu == 0` in both SPRTs.
+        let n = (4. * var / delta.powi(2)).ceil() as u64;
+        assert_eq!(test.decision(n, 0., var), SequentialDecision::Equivalent);
+    }
+
+    #[test]
+    fn test_sequential_outcome_bounds() {
+        let outcome = TEST.outcome(SequentialDecision::Equivalent);
+        assert!(outcome.alpha_bound > TEST.alpha && outcome.alpha_bound < 0.053);
+        assert!(outcome.beta_bound > TEST.beta && outcome.beta_bound < 0.053);
+    }
+
+    #[test]
+    fn test_invalid_test() {
+        let run = |test: SequentialTest| {
+            BenchDiff::new(LatencyUnit::Nano, 0)
+                .warmup_millis(1)
+                .try_run_sequential(|| (), || (), test)
+        };
+
+        for test in [
+            SequentialTest { alpha: 0., ..TEST },
+            SequentialTest { beta: 1., ..TEST },
+            SequentialTest {
+                alpha: 0.5,
+                beta: 0.5,
+                ..TEST
+            },
+            SequentialTest { margin: 0., ..TEST },
+            SequentialTest {
+                margin: f64::NAN,
+                ..TEST
+            },
+        ] {
+            assert!(
+                matches!(run(test), Err(BenchDiffError::Statistics(_))),
+                "{test:?}"
+            );
+        }
+
+        let test = SequentialTest {
+            max_exec_count: 3,
+            ..TEST
+        };
+        assert!(matches!(
+            run(test),
+            Err(BenchDiffError::InsufficientSamples { n: 0, required: 4 })
+        ));
+    }
+}
@@ -0,0 +1,438 @@
+//! Module implementing functions to compare the latencies of more than two closures in a single benchmark.
+
+use super::{
+    BenchDiff, BenchDiffError, ClockKind, ClosureReporter, DiffOut, DiffState, LatencyUnit,
+    Reporter, RunInfo, StatusFlow, StopReason, TargetFn, TargetPanic, WarmupOutcome, WarmupPolicy,
+    bench_diff::{PanicCatcher, check_timer_overhead, expect_ok},
+    stderr_reporter,
+};
+use std::{
+    ops::ControlFlow,
+    time::{Duration, Instant},
+};
+
+/// Returns the rows of a balanced Latin square (Williams design) for `n` treatments.
+///
+/// Each row is a permutation of `0..n`. Each treatment appears once in each position across the rows and, for any
+/// two distinct treatments `i` and `j`, `i` immediately precedes `j` in exactly as many rows as `j` immediately
+/// precedes `i`. For even `n`, the design has `n` rows and each ordered pair of distinct treatments is adjacent
+/// exactly once; for odd `n`, the design has `2 * n` rows (the square and its mirror image) and each ordered pair of
+/// distinct treatments is adjacent exactly twice.
+fn williams_design(n: usize) -> Vec<Vec<usize>> {
+    let base = (0..n)
+        .map(|k| {
+            if k % 2 == 1 {
+                k.div_ceil(2)
+            } else {
+                (n - k / 2) % n
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut rows = (0..n)
+        .map(|i| base.iter().map(|&t| (t + i) % n).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    if n % 2 == 1 {
+        let mirrors = rows
+            .iter()
+            .map(|row| row.iter().rev().copied().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        rows.extend(mirrors);
+    }
+
+    rows
+}
+
+/// Index in [`MultiDiffOut::diffs`] of the pair `(i, j)`, with `i < j < n`.
+fn pair_index(n: usize, i: usize, j: usize) -> usize {
+    i * n - i * (i + 1) / 2 + (j - i - 1)
+}
+
+/// Contains the data resulting from a benchmark comparing more than two closures (see [`bench_multi`]).
+///
+/// It holds one [`DiffOut`] for each pair of distinct functions, with all of them collected in the same benchmark
+/// run.
+pub struct MultiDiffOut {
+    fn_count: usize,
+    diffs: Vec<DiffOut>,
+}
+
+impl MultiDiffOut {
+    /// Number of functions compared.
+    pub fn fn_count(&self) -> usize {
+        self.fn_count
+    }
+
+    /// The [`DiffOut`] comparing the `i`-th function (as `f1`) with the `j`-th function (as `f2`).
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `i < j < self.fn_count()`.
+    pub fn diff(&self, i: usize, j: usize) -> &DiffOut {
+        assert!(
+            i < j && j < self.fn_count,
+            "must have i < j < fn_count, but i={i}, j={j}, fn_count={}",
+            self.fn_count
+        );
+        &self.diffs[pair_index(self.fn_count, i, j)]
+    }
+
+    /// Iterator over all pairs `(i, j)` of function indices, with `i < j`, and their corresponding
+    /// [`DiffOut`] (see [`Self::diff`]).
+    pub fn diffs(&self) -> impl Iterator<Item = (usize, usize, &DiffOut)> {
+        let n = self.fn_count;
+        (0..n)
+            .flat_map(move |i| (i + 1..n).map(move |j| (i, j)))
+            .map(move |(i, j)| (i, j, &self.diffs[pair_index(n, i, j)]))
+    }
+}
+
+/// Executes the functions in `fs` once in the order given by `row` and captures their latencies, as measured by
+/// `clock`, in `states`, one for each pair of distinct functions (see [`pair_index`]). Returns
+/// [`BenchDiffError::ZeroLatency`] if a latency of zero is measured.
+///
+/// If `catcher` is not `None`, the panics of the functions are caught by it, identified by their indices in `fs`.
+/// In that case, if a function panics, the remaining functions of the row are not executed, no latency is
+/// captured, and [`StopReason::Panic`] is returned.
+fn execute_row(
+    clock: ClockKind,
+    fs: &mut [&mut dyn FnMut()],
+    row: &[usize],
+    catcher: Option<&PanicCatcher<usize>>,
+    latencies: &mut [Duration],
+    states: &mut [DiffState],
+) -> Result<ControlFlow<StopReason>, BenchDiffError> {
+    for &k in row {
+        let f = &mut fs[k];
+        latencies[k] = match catcher {
+            None => clock.latency(f),
+            Some(catcher) => clock.latency(|| {
+                catcher.catch_from(k, f);
+            }),
+        };
+    }
+    if catcher.is_some_and(PanicCatcher::has_panicked) {
+        return Ok(ControlFlow::Break(StopReason::Panic));
+    }
+
+    let n = fs.len();
+    for i in 0..n {
+        for j in i + 1..n {
+            states[pair_index(n, i, j)].capture_latencies(latencies[i], latencies[j])?;
+        }
+    }
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Compares latencies for the closures in `fs` in a single benchmark and *optionally* outputs information about
+/// the benchmark and its execution status.
+///
+/// This function generalizes the duos of pairs of [`crate::bench_diff_x`] to more than two functions. It
+/// repeatedly executes the rows of a balanced Latin square (Williams design), where each row executes every
+/// function once and, across the rows of the square, each function occupies every position equally often and
+/// immediately precedes every other function equally often. This removes the ordering effect for every pair of
+/// functions. Each row produces a paired observation for every pair of functions, so all pairwise comparisons are
+/// obtained from a single run, with a single warm-up.
+///
+/// The result contains a [`DiffOut`] for each pair of distinct functions (see [`MultiDiffOut::diff`]). Notice that
+/// memory usage grows with the number of pairs, i.e., quadratically with the number of functions.
+///
+/// Prior to data collection, the benchmark is "warmed-up" by executing the rows of the square for
+/// [`crate::get_warmup_millis`] milliseconds, after which the timer overhead is measured for each pair (see
+/// [`DiffOut::timer_overhead`]).
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `fs` - targets for comparison; there must be at least two.
+/// - `exec_count` - number of executions (sample size) for each function. It is rounded up to a multiple of the
+///   number of rows in the square, which is the number of functions if it is even and twice the number of
+///   functions if it is odd.
+/// - `warmup_status` - see [`crate::bench_diff_x`].
+/// - `pre_exec` - see [`crate::bench_diff_x`].
+/// - `exec_status` - is invoked after the execution of each row of the square; its argument is the current number
+///   of executions performed for each function.
+pub fn bench_multi_x<W: StatusFlow, E: StatusFlow>(
+    unit: LatencyUnit,
+    fs: &mut [&mut dyn FnMut()],
+    exec_count: usize,
+    warmup_status: impl FnMut(usize, u64, u64) -> W,
+    pre_exec: impl FnOnce(),
+    exec_status: impl FnMut(usize) -> E,
+) -> MultiDiffOut {
+    bench_multi_with_reporter(
+        unit,
+        fs,
+        exec_count,
+        &mut ClosureReporter::new(warmup_status, pre_exec, exec_status),
+    )
+}
+
+/// Compares latencies for the closures in `fs` in a single benchmark and reports the progress of the benchmark to
+/// `reporter`.
+///
+/// This function is like [`bench_multi_x`] except that the events of the benchmark are reported to `reporter`
+/// instead of status closures. [`Reporter::progress`] is invoked after the execution of each row of the square and
+/// [`Reporter::half_start`] is not invoked. If `reporter` cancels the benchmark during data collection, it stops
+/// at the end of the current row, so the ordering effect may not be fully removed.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `fs` - targets for comparison; there must be at least two.
+/// - `exec_count` - number of executions (sample size) for each function; see [`bench_multi_x`].
+/// - `reporter` - receives the events of the benchmark (see [`Reporter`]).
+pub fn bench_multi_with_reporter(
+    unit: LatencyUnit,
+    fs: &mut [&mut dyn FnMut()],
+    exec_count: usize,
+    reporter: &mut impl Reporter,
+) -> MultiDiffOut {
+    BenchDiff::new(unit, exec_count)
+        .reporter(reporter)
+        .run_multi(fs)
+}
+
+/// Compares latencies for the closures in `fs` in a single benchmark.
+///
+/// This function calls [`bench_multi_x`] with no-op closures for the arguments that support the output of
+/// benchmark status.
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `fs` - targets for comparison; there must be at least two.
+/// - `exec_count` - number of executions (sample size) for each function; see [`bench_multi_x`].
+pub fn bench_multi(
+    unit: LatencyUnit,
+    fs: &mut [&mut dyn FnMut()],
+    exec_count: usize,
+) -> MultiDiffOut {
+    bench_multi_x(unit, fs, exec_count, |_, _, _| {}, || (), |_| ())
+}
+
+/// Compares latencies for the closures in `fs` in a single benchmark and outputs information about the benchmark
+/// and its execution status. Execution status is output to `stderr`.
+///
+/// This function calls [`bench_multi_with_reporter`] with [`stderr_reporter`] (see
+/// [`crate::bench_diff_with_status`]).
+///
+/// Arguments:
+/// - `unit` - the unit used to present latency statistics.
+/// - `fs` - targets for comparison; there must be at least two.
+/// - `exec_count` - number of executions (sample size) for each function; see [`bench_multi_x`].
+/// - `header` - see [`crate::bench_diff_with_status`].
+pub fn bench_multi_with_status(
+    unit: LatencyUnit,
+    fs: &mut [&mut dyn FnMut()],
+    exec_count: usize,
+    header: impl FnOnce(LatencyUnit, usize),
+) -> MultiDiffOut {
+    header(unit, exec_count);
+
+    bench_multi_with_reporter(unit, fs, exec_count, &mut stderr_reporter())
+}
+
+impl BenchDiff<'_> {
+    /// Compares latencies for the closures in `fs` in a single benchmark, according to the configuration of `self`
+    /// (see [`bench_multi_x`]). The warm-up policy of `self` is not used; warm-up always follows
+    /// [`WarmupPolicy::Fixed`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fs` has fewer than two functions or a latency of zero is measured (see
+    /// [`BenchDiffError::ZeroLatency`]).
+    pub fn run_multi(&mut self, fs: &mut [&mut dyn FnMut()]) -> MultiDiffOut {
+        expect_ok(self.try_run_multi(fs))
+    }
+
+    /// Fallible version of [`Self::run_multi`], which returns an error instead of panicking on a latency of zero.
+    /// Returns [`BenchDiffError::InsufficientSamples`], before any execution, if the configured `exec_count` would
+    /// produce fewer than two observations for each function.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fs` has fewer than two functions.
+    pub fn try_run_multi(
+        &mut self,
+        fs: &mut [&mut dyn FnMut()],
+    ) -> Result<MultiDiffOut, BenchDiffError> {
+        let n = fs.len();
+        assert!(n >= 2, "at least two functions must be compared");
+
+        let rows = williams_design(n);
+        let square_count = self.exec_count.div_ceil(rows.len());
+        let sample_size = (square_count * rows.len()) as u64;
+        if sample_size < 2 {
+            return Err(BenchDiffError::InsufficientSamples {
+                n: sample_size,
+                required: 2,
+            });
+        }
+        let config = self.config;
+
+        let mut diffs = (0..n * (n - 1) / 2)
+            .map(|_| config.new_out())
+            .collect::<Vec<_>>();
+        // The settings of the run are recorded in the first output and copied to the others.
+        self.warn_unsupported_by_multi(&mut diffs[0]);
+        let _guard = self.start_run(&mut diffs[0]);
+        let (first, others) = diffs.split_at_mut(1);
+        for diff in others {
+            diff.environment = first[0].environment.clone();
+            diff.background_load = first[0].background_load;
+            diff.thread_settings = first[0].thread_settings;
+            diff.warnings = first[0].warnings.clone();
+        }
+
+        let panic_catcher = self.catch_panics.then(PanicCatcher::new);
+        let catcher = panic_catcher.as_ref();
+        let reporter = &mut self.reporter;
+        let mut states = diffs.iter_mut().map(DiffState::new).collect::<Vec<_>>();
+        let mut latencies = vec![Duration::ZERO; n];
+
+        reporter.run_start(&RunInfo {
+            unit: config.unit,
+            exec_count: Some(square_count * rows.len()),
+        });
+
+        let mut stop_reason = StopReason::ExecCount;
+        let warmup_millis = config.warmup_millis;
+        let start = Instant::now();
+        let mut warmup_exec_count = 0;
+        'warmup: for i in 1.. {
+            for row in &rows {
+                let flow =
+                    execute_row(config.clock, fs, row, catcher, &mut latencies, &mut states)?;
+                if let ControlFlow::Break(reason) = flow {
+                    stop_reason = reason;
+                    break 'warmup;
+                }
+            }
+            warmup_exec_count += rows.len() as u64;
+            let elapsed = Instant::now().duration_since(start);
+            if reporter
+                .warmup_progress(i, elapsed.as_millis() as u64, warmup_millis)
+                .is_break()
+            {
+                stop_reason = StopReason::Cancelled;
+                break;
+            }
+            if elapsed.ge(&Duration::from_millis(warmup_millis)) {
+                break;
+            }
+        }
+        let warmup_duration = Instant::now().duration_since(start);
+        for state in states.iter_mut() {
+            state.reset();
+            if stop_reason == StopReason::ExecCount {
+                state.calibrate_timer_overhead(config.subtract_timer_overhead);
+            }
+        }
+
+        let mut count = 0;
+        if stop_reason == StopReason::ExecCount {
+            reporter.measurement_start();
+
+            'squares: for _ in 0..square_count {
+                for row in &rows {
+                    let flow =
+                        execute_row(config.clock, fs, row, catcher, &mut latencies, &mut states)?;
+                    if let ControlFlow::Break(reason) = flow {
+                        stop_reason = reason;
+                        break 'squares;
+                    }
+                    count += 1;
+                    if reporter.progress(count).is_break() {
+                        stop_reason = StopReason::Cancelled;
+                        break 'squares;
+                    }
+                }
+            }
+        }
+
+        drop(states);
+        for diff in diffs.iter_mut() {
+            diff.warmup = WarmupOutcome {
+                policy: WarmupPolicy::Fixed,
+                exec_count: warmup_exec_count,
+                duration: warmup_duration,
+                converged: false,
+                trace: Vec::new(),
+            };
+            diff.stop_reason = stop_reason;
+            check_timer_overhead(diff);
+        }
+        reporter.complete(count, stop_reason);
+
+        let mut multi_out = MultiDiffOut { fn_count: n, diffs };
+        if let Some((k, payload)) = panic_catcher.and_then(PanicCatcher::into_caught) {
+            let panic = TargetPanic::new(TargetFn::F1, payload);
+            for i in 0..n {
+                for j in i + 1..n {
+                    let target = if k == i {
+                        TargetFn::F1
+                    } else if k == j {
+                        TargetFn::F2
+                    } else {
+                        continue;
+                    };
+                    multi_out.diffs[pair_index(n, i, j)].panic = Some(TargetPanic {
+                        target,
+                        ..panic.clone()
+                    });
+                }
+            }
+        }
+        Ok(multi_out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{pair_index, williams_design};
+
+    #[test]
+    fn test_williams_design() {
+        for n in 2..=9 {
+            let rows = williams_design(n);
+            let row_count = if n % 2 == 0 { n } else { 2 * n };
+            assert_eq!(rows.len(), row_count, "n={n}");
+
+            let mut position_counts = vec![vec![0; n]; n];
+            let mut precedence_counts = vec![vec![0; n]; n];
+            for row in &rows {
+                let mut sorted = row.clone();
+                sorted.sort();
+                assert_eq!(sorted, (0..n).collect::<Vec<_>>(), "n={n}, row={row:?}");
+
+                for (pos, &t) in row.iter().enumerate() {
+                    position_counts[t][pos] += 1;
+                }
+                for w in row.windows(2) {
+                    precedence_counts[w[0]][w[1]] += 1;
+                }
+            }
+
+            let per_position = row_count / n;
+            let per_precedence = row_count / n;
+            for (i, counts) in position_counts.iter().enumerate() {
+                assert!(counts.iter().all(|&c| c == per_position), "n={n}, i={i}");
+            }
+            for (i, counts) in precedence_counts.iter().enumerate() {
+                for (j, &c) in counts.iter().enumerate() {
+                    if i != j {
+                        assert_eq!(c, per_precedence, "n={n}, i={i}, j={j}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pair_index() {
+        let n = 5;
+        let indices = (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| pair_index(n, i, j)))
+            .collect::<Vec<_>>();
+        assert_eq!(indices, (0..n * (n - 1) / 2).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,254 @@
+//! Module defining the clocks that can be used to measure latencies.
+
+use std::time::{Duration, Instant};
+
+/// Source of time readings used to measure latencies.
+///
+/// Implementations are zero-sized types whose associated functions read the underlying clock.
+pub trait Clock {
+    /// A reading of the clock.
+    type Reading: Copy;
+
+    /// Kind of this clock, as recorded in benchmark outputs.
+    const KIND: ClockKind;
+
+    /// Reads the clock.
+    fn now() -> Self::Reading;
+
+    /// Time elapsed between the readings `start` and `end`.
+    fn elapsed(start: Self::Reading, end: Self::Reading) -> Duration;
+
+    /// Invokes `f` once and returns its latency as measured by this clock.
+    #[inline(always)]
+    fn latency(f: impl FnOnce()) -> Duration {
+        let start = Self::now();
+        f();
+        let end = Self::now();
+        Self::elapsed(start, end)
+    }
+}
+
+/// Identifies a [`Clock`] implementation. Used to select the clock used by a benchmark
+/// (see [`crate::BenchDiff::clock`]) and recorded in its output (see [`crate::DiffOut::clock`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockKind {
+    /// [`InstantClock`], the default.
+    Instant,
+    /// [`MonotonicRawClock`].
+    MonotonicRaw,
+    /// [`ThreadCpuClock`].
+    ThreadCpu,
+    /// [`ProcessCpuClock`].
+    ProcessCpu,
+    /// [`TscClock`].
+    Tsc,
+}
+
+impl ClockKind {
+    /// Whether this clock is supported on the current platform.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Self::Instant => true,
+            Self::MonotonicRaw | Self::ThreadCpu | Self::ProcessCpu => HAS_CLOCK_GETTIME,
+            Self::Tsc => cfg!(target_arch = "x86_64"),
+        }
+    }
+
+    /// Invokes `f` once and returns its latency as measured by this clock.
+    #[inline(always)]
+    pub(crate) fn latency(self, f: impl FnOnce()) -> Duration {
+        match self {
+            Self::Instant => InstantClock::latency(f),
+            Self::MonotonicRaw => MonotonicRawClock::latency(f),
+            Self::ThreadCpu => ThreadCpuClock::latency(f),
+            Self::ProcessCpu => ProcessCpuClock::latency(f),
+            Self::Tsc => TscClock::latency(f),
+        }
+    }
+}
+
+/// Wall-clock time from [`std::time::Instant`], which is monotonic but may be subject to frequency adjustments.
+pub struct InstantClock;
+
+impl Clock for InstantClock {
+    type Reading = Instant;
+    const KIND: ClockKind = ClockKind::Instant;
+
+    #[inline(always)]
+    fn now() -> Instant {
+        Instant::now()
+    }
+
+    #[inline(always)]
+    fn elapsed(start: Instant, end: Instant) -> Duration {
+        end.duration_since(start)
+    }
+}
+
+/// Wall-clock time from `clock_gettime(CLOCK_MONOTONIC_RAW)`, which is not subject to frequency adjustments.
+///
+/// Supported on Linux, Android, and macOS.
+pub struct MonotonicRawClock;
+
+impl Clock for MonotonicRawClock {
+    type Reading = Duration;
+    const KIND: ClockKind = ClockKind::MonotonicRaw;
+
+    #[inline(always)]
+    fn now() -> Duration {
+        clock_gettime(ClockKind::MonotonicRaw)
+    }
+
+    #[inline(always)]
+    fn elapsed(start: Duration, end: Duration) -> Duration {
+        end.saturating_sub(start)
+    }
+}
+
+/// CPU time consumed by the current thread, from `clock_gettime(CLOCK_THREAD_CPUTIME_ID)`.
+///
+/// Unlike wall-clock time, it excludes the time during which the thread is preempted or blocked, so it is
+/// suitable for comparing CPU-bound functions on a busy machine. Notice that it also excludes time spent
+/// waiting for I/O or locks.
+///
+/// Supported on Linux, Android, and macOS.
+pub struct ThreadCpuClock;
+
+impl Clock for ThreadCpuClock {
+    type Reading = Duration;
+    const KIND: ClockKind = ClockKind::ThreadCpu;
+
+    #[inline(always)]
+    fn now() -> Duration {
+        clock_gettime(ClockKind::ThreadCpu)
+    }
+
+    #[inline(always)]
+    fn elapsed(start: Duration, end: Duration) -> Duration {
+        end.saturating_sub(start)
+    }
+}
+
+/// CPU time consumed by all threads of the current process, from `clock_gettime(CLOCK_PROCESS_CPUTIME_ID)`.
+///
+/// Supported on Linux, Android, and macOS.
+pub struct ProcessCpuClock;
+
+impl Clock for ProcessCpuClock {
+    type Reading = Duration;
+    const KIND: ClockKind = ClockKind::ProcessCpu;
+
+    #[inline(always)]
+    fn now() -> Duration {
+        clock_gettime(ClockKind::ProcessCpu)
+    }
+
+    #[inline(always)]
+    fn elapsed(start: Duration, end: Duration) -> Duration {
+        end.saturating_sub(start)
+    }
+}
+
+/// Time stamp counter cycles, from the x86 `rdtsc` instruction.
+///
+/// The elapsed *cycles* are reported as a [`Duration`] with one nanosecond per cycle, so latencies should be
+/// collected with [`crate::LatencyUnit::Nano`] and interpreted as cycles. On CPUs with an invariant TSC, cycles
+/// are counted at a constant rate that may differ from the actual core frequency.
+///
+/// Supported on `x86_64`.
+pub struct TscClock;
+
+impl Clock for TscClock {
+    type Reading = u64;
+    const KIND: ClockKind = ClockKind::Tsc;
+
+    #[inline(always)]
+    fn now() -> u64 {
+        rdtsc()
+    }
+
+    #[inline(always)]
+    fn elapsed(start: u64, end: u64) -> Duration {
+        Duration::from_nanos(end.saturating_sub(start))
+    }
+}
+
+const HAS_CLOCK_GETTIME: bool = cfg!(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos"
+));
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+#[inline(always)]
+fn clock_gettime(clock: ClockKind) -> Duration {
+    let clock_id = match clock {
+        ClockKind::MonotonicRaw => libc::CLOCK_MONOTONIC_RAW,
+        ClockKind::ThreadCpu => libc::CLOCK_THREAD_CPUTIME_ID,
+        ClockKind::ProcessCpu => libc::CLOCK_PROCESS_CPUTIME_ID,
+        ClockKind::Instant | ClockKind::Tsc => unreachable!("not a clock_gettime clock: {clock:?}"),
+    };
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `ts` is a valid, writable `timespec` and `clock_id` is a valid clock id for this platform.
+    let res = unsafe { libc::clock_gettime(clock_id, &mut ts) };
+    assert_eq!(res, 0, "clock_gettime failed for clock {clock:?}");
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+fn clock_gettime(clock: ClockKind) -> Duration {
+    panic!("clock {clock:?} is not supported on this platform")
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+fn rdtsc() -> u64 {
+    use std::arch::x86_64::{_mm_lfence, _rdtsc};
+
+    // The fences prevent the reading from being reordered with respect to the surrounding instructions.
+    // SAFETY: `lfence` and `rdtsc` are available on all `x86_64` CPUs.
+    unsafe {
+        _mm_lfence();
+        let tsc = _rdtsc();
+        _mm_lfence();
+        tsc
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn rdtsc() -> u64 {
+    panic!(
+        "clock {:?} is not supported on this platform",
+        ClockKind::Tsc
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check_clock<C: Clock>() {
+        if !C::KIND.is_supported() {
+            return;
+        }
+        let latency = C::latency(|| {
+            let mut x = 0_u64;
+            for i in 0..100_000 {
+                x = std::hint::black_box(x.wrapping_mul(31).wrapping_add(i));
+            }
+        });
+        assert!(latency > Duration::ZERO, "clock {:?}", C::KIND);
+    }
+
+    #[test]
+    fn test_clocks() {
+        check_clock::<InstantClock>();
+        check_clock::<MonotonicRawClock>();
+        check_clock::<ThreadCpuClock>();
+        check_clock::<ProcessCpuClock>();
+        check_clock::<TscClock>();
+    }
+}
@@ -1,7 +1,13 @@
 //! Module defining the key data structure produced by [`crate::bench_diff`].
 
+use super::{
+    bench_diff::TIMER_OVERHEAD_WARNING_FACTOR,
+    outliers::{LnFences, RATIO_HIST_SCALE, ln_outlier_counts},
+};
 use crate::{
-    SummaryStats, Timing, new_timing,
+    AllocComparison, BackgroundLoad, BenchDiffError, ClockKind, EnvironmentCheck, Half,
+    LatencyUnit, Outliers, SchedPriority, SequentialOutcome, SummaryStats, TargetFn,
+    ThreadSettings, Timing, WarmupOutcome, new_timing,
     stats_types::{AltHyp, Ci, HypTestResult, PositionWrtCi},
     summary_stats,
 };
@@ -14,10 +20,152 @@ use basic_stats::{
     },
 };
 use hdrhistogram::Histogram;
+use std::{
+    any::Any,
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
 
 #[cfg(feature = "_dev_support")]
 use basic_stats::{binomial, wilcoxon::RankSum};
 
+/// Reason why data collection stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// The requested number of executions was performed.
+    ExecCount,
+    /// The target precision was reached (see [`crate::bench_diff_adaptive`]).
+    TargetPrecision,
+    /// The maximum number of executions was reached before any other stopping rule fired.
+    MaxExecCount,
+    /// The maximum duration or time budget was reached before any other stopping rule fired.
+    MaxDuration,
+    /// A sequential test reached a decision (see [`crate::bench_diff_sequential`]).
+    SequentialDecision,
+    /// A target function panicked (see [`crate::BenchDiff::catch_panics`] and [`DiffOut::panic`]).
+    Panic,
+    /// A status closure requested that the benchmark stop (see [`crate::StatusFlow`]).
+    Cancelled,
+}
+
+/// Information about a panic of a target function caught during a benchmark (see
+/// [`crate::BenchDiff::catch_panics`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TargetPanic {
+    /// Function that panicked.
+    pub target: TargetFn,
+    /// Panic payload if it is a string, as is the case for panics raised with [`panic!`]; `None` otherwise.
+    pub message: Option<String>,
+}
+
+impl TargetPanic {
+    pub(super) fn new(target: TargetFn, payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => Some(*message),
+            Err(payload) => payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string()),
+        };
+        Self { target, message }
+    }
+}
+
+/// Condition detected during a benchmark that may compromise the reliability of its results.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning {
+    /// The median latency of a function is close to the timer overhead, so the timer overhead may bias the
+    /// comparison (see [`DiffOut::timer_overhead`]). Values are in nanoseconds and are per call in batched mode.
+    NearTimerOverhead {
+        /// Smaller of the medians of the latencies of `f1` and `f2`, before any timer overhead subtraction.
+        median: f64,
+        /// Median timer overhead.
+        timer_overhead: f64,
+    },
+    /// The measuring thread could not be pinned to the requested core (see [`crate::BenchDiff::pin_to_core`]),
+    /// so it may have migrated between cores during the benchmark.
+    AffinityNotApplied {
+        /// Requested core.
+        core: usize,
+        /// Description of the error.
+        error: String,
+    },
+    /// The requested scheduling priority could not be applied to the measuring thread (see
+    /// [`crate::BenchDiff::priority`]), so the benchmark ran with the original scheduling of the thread.
+    PriorityNotApplied {
+        /// Requested priority.
+        priority: SchedPriority,
+        /// Description of the error.
+        error: String,
+    },
+    /// Allocations were not tracked (see [`crate::BenchDiff::track_allocations`]) because a
+    /// [`crate::CountingAllocator`] is not installed as the global allocator.
+    AllocationsNotTracked,
+    /// A setting of the [`crate::BenchDiff`] that ran the benchmark is not supported by its benchmarking function
+    /// family, so it was ignored (e.g., [`crate::BenchDiff::seed`] with [`crate::BenchDiff::run_multi`]).
+    SettingNotSupported {
+        /// Name of the method that configures the setting, e.g., `"seed"`.
+        setting: &'static str,
+    },
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NearTimerOverhead {
+                median,
+                timer_overhead,
+            } => write!(
+                f,
+                "median latency {median} ns is less than {TIMER_OVERHEAD_WARNING_FACTOR} times the timer overhead \
+                {timer_overhead} ns; \
+                consider batched mode or timer overhead subtraction"
+            ),
+            Self::AffinityNotApplied { core, error } => {
+                write!(f, "measuring thread not pinned to core {core}: {error}")
+            }
+            Self::PriorityNotApplied { priority, error } => {
+                write!(f, "scheduling priority {priority} not applied: {error}")
+            }
+            Self::AllocationsNotTracked => write!(
+                f,
+                "allocations not tracked: CountingAllocator is not the global allocator"
+            ),
+            Self::SettingNotSupported { setting } => {
+                write!(
+                    f,
+                    "setting {setting} not supported by this benchmark; ignored"
+                )
+            }
+        }
+    }
+}
+
+/// Order in which the functions are executed within the duos of a benchmark.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuoOrder {
+    /// Duos are (`f1`, `f2`), (`f2`, `f1`) in the first half of data collection and (`f2`, `f1`), (`f1`, `f2`)
+    /// in the second half.
+    Fixed,
+    /// The order of the two pairs in each duo is chosen pseudo-randomly from `seed`
+    /// (see [`crate::bench_diff_randomized`]).
+    Randomized { seed: u64 },
+}
+
+/// Latencies of a pair of executions of `f1` and `f2` within a duo, retained in time order on request (see
+/// [`DiffOut::samples`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PairedSample {
+    /// Latency of `f1`, in nanoseconds; in batched mode, the total latency of the batch.
+    pub elapsed1: u64,
+    /// Latency of `f2`, in nanoseconds; in batched mode, the total latency of the batch.
+    pub elapsed2: u64,
+    /// Index of the duo that produced the pair, starting at `0` at the beginning of data collection. Each duo
+    /// produces two pairs.
+    pub duo_index: u64,
+    /// Half of data collection in which the pair was produced.
+    pub half: Half,
+}
+
 /// Contains the data resulting from a benchmark comparing two closures `f1` and `f2`.
 ///
 /// It is returned by the core benchmarking functions in this library.
@@ -26,6 +174,15 @@ use basic_stats::{binomial, wilcoxon::RankSum};
 ///
 /// All statistics involving differences refer to a value for `f1` minus the corresponding
 /// value for `f2`.
+///
+/// When the benchmark executes the functions in batches (see [`Self::batch_size`]), each recorded latency is
+/// the total latency of a batch, and the statistics with a time dimension are divided by the batch size, so all
+/// statistics are per call.
+///
+/// Latencies are recorded in nanoseconds. Statistics with a time dimension (e.g., [`Self::median_f1`] and
+/// [`Self::mean_diff_f1_f2`]) are expressed in [`Self::unit`], which can be changed at any time with
+/// [`Self::set_unit`]; dimensionless statistics (e.g., ratios and statistics of differences of logarithms) do not
+/// depend on it.
 pub struct DiffOut {
     pub(super) hist_f1: Timing,
     pub(super) hist_f2: Timing,
@@ -38,14 +195,74 @@ pub struct DiffOut {
     pub(super) sum2_ln_f1: f64,
     pub(super) sum_ln_f2: f64,
     pub(super) sum2_ln_f2: f64,
-    pub(super) sum2_diff_f1_f2: i64,
+    pub(super) sum2_diff_f1_f2: f64,
     pub(super) sum2_diff_ln_f1_f2: f64,
+    pub(super) hist_ratio_f1_f2: Timing,
+    pub(super) samples: Option<Vec<PairedSample>>,
+    pub(super) allocations: Option<AllocComparison>,
+    pub(super) batch_size: u64,
+    pub(super) stop_reason: StopReason,
+    pub(super) sequential_outcome: Option<SequentialOutcome>,
+    pub(super) duo_order: DuoOrder,
+    pub(super) clock: ClockKind,
+    pub(super) background_load: BackgroundLoad,
+    pub(super) thread_settings: ThreadSettings,
+    pub(super) environment: Option<EnvironmentCheck>,
+    pub(super) cpu: Option<Box<DiffOut>>,
+    pub(super) hist_timer_overhead: Timing,
+    pub(super) timer_overhead_subtracted: u64,
+    pub(super) warnings: Vec<Warning>,
+    pub(super) warmup: WarmupOutcome,
+    pub(super) unit: LatencyUnit,
+    pub(super) panic: Option<TargetPanic>,
+}
+
+/// Returns the sample sum of squares `sum2`, raised if necessary so that the sample variance computed from `n`,
+/// `sum`, and the returned value is not negative. Rounding errors can make the computed variance of constant
+/// values slightly negative, so that the standard deviation would be `NaN` instead of `0`.
+fn sum2_non_negative_var(n: u64, sum: f64, sum2: f64) -> f64 {
+    if n == 0 {
+        return sum2;
+    }
+    sum2.max(sum.powi(2) / n as f64)
+}
+
+/// Sample moments of the natural logarithms of the latencies recorded in `hist`, excluding severe outliers (see
+/// [`DiffOut::outliers`]).
+fn moments_ln_excl_severe(hist: &Timing) -> SampleMoments {
+    if hist.is_empty() {
+        return SampleMoments::new_empty();
+    }
+    let fences = LnFences::new(hist, 1.);
+    let (mut n, mut sum, mut sum2) = (0, 0., 0.);
+    for v in hist.iter_recorded() {
+        let ln_value = (v.value_iterated_to() as f64).ln();
+        if !fences.is_severe(ln_value) {
+            let count = v.count_at_value();
+            n += count;
+            sum += count as f64 * ln_value;
+            sum2 += count as f64 * ln_value.powi(2);
+        }
+    }
+    SampleMoments::new(n, sum, sum2_non_negative_var(n, sum, sum2))
 }
 
+/// Default upper bound of the latency histograms; see [`crate::BenchDiff::histogram`].
+pub const DEFAULT_HIST_HIGH: u64 = 20 * 1000 * 1000;
+
+/// Default number of significant figures of the latency histograms; see [`crate::BenchDiff::histogram`].
+pub const DEFAULT_HIST_SIGFIG: u8 = 5;
+
 impl DiffOut {
     /// Creates a new empty instance.
+    #[cfg(any(test, feature = "_bench"))]
     pub(crate) fn new() -> Self {
-        let hist_f1 = new_timing(20 * 1000 * 1000, 5);
+        Self::new_with_hist(DEFAULT_HIST_HIGH, DEFAULT_HIST_SIGFIG)
+    }
+
+    /// Creates a new empty instance whose latency histograms are constructed with [`new_timing`].
+    pub(crate) fn new_with_hist(hist_high: u64, hist_sigfig: u8) -> Self {
+        let hist_f1 = new_timing(hist_high, hist_sigfig);
         let hist_f2 = Histogram::<u64>::new_from(&hist_f1);
         let hist_f1_lt_f2 = Histogram::<u64>::new_from(&hist_f1);
         let count_f1_eq_f2 = 0;
@@ -56,8 +273,26 @@ impl DiffOut {
         let sum2_ln_f1 = 0.;
         let sum_ln_f2 = 0.;
         let sum2_ln_f2 = 0.;
-        let sum2_diff_f1_f2 = 0;
+        let sum2_diff_f1_f2 = 0.;
         let sum2_diff_ln_f1_f2 = 0.;
+        let hist_ratio_f1_f2 = new_timing(10 * RATIO_HIST_SCALE as u64, 3);
+        let samples = None;
+        let allocations = None;
+        let batch_size = 1;
+        let stop_reason = StopReason::ExecCount;
+        let sequential_outcome = None;
+        let duo_order = DuoOrder::Fixed;
+        let clock = ClockKind::Instant;
+        let background_load = BackgroundLoad::default();
+        let thread_settings = ThreadSettings::default();
+        let environment = None;
+        let cpu = None;
+        let hist_timer_overhead = new_timing(1000 * 1000, 3);
+        let timer_overhead_subtracted = 0;
+        let warnings = Vec::new();
+        let warmup = WarmupOutcome::new();
+        let unit = LatencyUnit::Nano;
+        let panic = None;
 
         Self {
             hist_f1,
@@ -73,9 +308,71 @@ impl DiffOut {
             sum2_ln_f2,
             sum2_diff_f1_f2,
             sum2_diff_ln_f1_f2,
+            hist_ratio_f1_f2,
+            samples,
+            allocations,
+            batch_size,
+            stop_reason,
+            sequential_outcome,
+            duo_order,
+            clock,
+            background_load,
+            thread_settings,
+            environment,
+            cpu,
+            hist_timer_overhead,
+            timer_overhead_subtracted,
+            warnings,
+            warmup,
+            unit,
+            panic,
+        }
+    }
+
+    /// Unit in which the statistics with a time dimension are expressed.
+    ///
+    /// It is initially the `unit` argument of the benchmarking function that produced `self`.
+    pub fn unit(&self) -> LatencyUnit {
+        self.unit
+    }
+
+    /// Sets the unit in which the statistics with a time dimension are expressed, including those of
+    /// [`Self::cpu`]. For example, `out.set_unit(out.best_unit())` selects the most readable unit.
+    pub fn set_unit(&mut self, unit: LatencyUnit) {
+        self.unit = unit;
+        if let Some(cpu) = self.cpu.as_mut() {
+            cpu.set_unit(unit);
+        }
+    }
+
+    /// Most readable unit for the observed latencies: [`LatencyUnit::Nano`] if the smaller of the medians of
+    /// `f1` and `f2` is less than a microsecond, [`LatencyUnit::Micro`] if it is less than a millisecond, and
+    /// [`LatencyUnit::Milli`] otherwise.
+    pub fn best_unit(&self) -> LatencyUnit {
+        let median = self
+            .hist_f1
+            .value_at_quantile(0.5)
+            .min(self.hist_f2.value_at_quantile(0.5))
+            / self.batch_size;
+        match median {
+            ..1_000 => LatencyUnit::Nano,
+            1_000..1_000_000 => LatencyUnit::Micro,
+            _ => LatencyUnit::Milli,
         }
     }
 
+    /// Number of nanoseconds of recorded latency per [`Self::unit`] of latency per call.
+    #[inline(always)]
+    fn recorded_per_unit(&self) -> f64 {
+        self.unit.nanos() * self.batch_size as f64
+    }
+
+    /// Converts a recorded value in nanoseconds to [`Self::unit`], per call.
+    #[inline(always)]
+    fn in_unit(&self, nanos: f64) -> f64 {
+        nanos / self.recorded_per_unit()
+    }
+
     /// Number of observations (sample size) for a function, as an integer.
     ///
     /// It is the same value for `f1` and `f2`.
@@ -92,18 +389,173 @@ impl DiffOut {
         self.hist_f1.len() as f64
     }
 
+    /// Number of consecutive calls of a function timed together to produce each recorded latency.
+    ///
+    /// It is `1` unless the benchmark was run in batched mode (e.g., with [`crate::bench_diff_batched`]),
+    /// in which case each recorded latency is the total latency of a batch.
+    pub fn batch_size(&self) -> u64 {
+        self.batch_size
+    }
+
+    /// Whether data collection stopped before the requested number of executions was performed because the
+    /// benchmark was cancelled ([`StopReason::Cancelled`]) or a target function panicked ([`StopReason::Panic`]).
+    pub fn is_truncated(&self) -> bool {
+        matches!(self.stop_reason, StopReason::Cancelled | StopReason::Panic)
+    }
+
+    /// Reason why data collection stopped.
+    ///
+    /// It is [`StopReason::ExecCount`] for benchmarks with a fixed number of executions that were not truncated
+    /// (see [`Self::is_truncated`]).
+    pub fn stop_reason(&self) -> StopReason {
+        self.stop_reason
+    }
+
+    /// Number of duos whose latencies were recorded. Each duo records two latencies for each function.
+    pub fn duo_count(&self) -> u64 {
+        self.n() / 2
+    }
+
+    /// Function that panicked and panic message, for benchmarks that catch panics (see
+    /// [`crate::BenchDiff::catch_panics`]) and were stopped by one; `None` otherwise.
+    pub fn panic(&self) -> Option<&TargetPanic> {
+        self.panic.as_ref()
+    }
+
+    /// Outcome of the sequential test, for benchmarks run with a sequential test
+    /// (e.g., with [`crate::bench_diff_sequential`]); `None` otherwise.
+    ///
+    /// When present, its error probability bounds apply to its decision instead of the nominal `alpha`
+    /// of the fixed-sample-size statistics.
+    pub fn sequential_outcome(&self) -> Option<&SequentialOutcome> {
+        self.sequential_outcome.as_ref()
+    }
+
+    /// Order in which the functions were executed within duos, including the seed for randomized orders.
+    pub fn duo_order(&self) -> DuoOrder {
+        self.duo_order
+    }
+
+    /// Clock used to measure the latencies (see [`crate::BenchDiff::clock`]).
+    pub fn clock(&self) -> ClockKind {
+        self.clock
+    }
+
+    /// Background stressors that ran during the benchmark (see [`crate::BenchDiff::background_load`]), which
+    /// distinguishes results obtained under load from results obtained on an otherwise idle machine.
+    pub fn background_load(&self) -> BackgroundLoad {
+        self.background_load
+    }
+
+    /// CPU affinity and scheduling settings applied to the measuring thread during the benchmark (see
+    /// [`crate::BenchDiff::pin_to_core`] and [`crate::BenchDiff::priority`]). Settings that could not be applied
+    /// are not included here and are reported in [`Self::warnings`] instead.
+    pub fn thread_settings(&self) -> ThreadSettings {
+        self.thread_settings
+    }
+
+    /// Conditions of the environment checked before the benchmark, including warnings about sources of noise,
+    /// if the check was enabled (see [`crate::environment_check`] and [`crate::BenchDiff::environment_check`]).
+    pub fn environment(&self) -> Option<&EnvironmentCheck> {
+        self.environment.as_ref()
+    }
+
+    /// Thread CPU time data collected in parallel with the latencies in `self`, for benchmarks that measure both
+    /// wall-clock time and CPU time (e.g., [`crate::bench_diff_with_cpu`]); `None` otherwise.
+    ///
+    /// The returned instance provides the same statistics as `self`, computed from the CPU times of the
+    /// functions instead of their wall-clock latencies.
+    pub fn cpu(&self) -> Option<&DiffOut> {
+        self.cpu.as_deref()
+    }
+
+    /// Fraction of `f1`'s total wall-clock latency during which `f1` was not running on the CPU (e.g., waiting
+    /// for locks or I/O, or preempted), for benchmarks that measure both wall-clock time and CPU time
+    /// (see [`Self::cpu`]); `None` otherwise.
+    pub fn off_cpu_fraction_f1(&self) -> Option<f64> {
+        self.cpu
+            .as_ref()
+            .map(|cpu| 1. - cpu.sum_f1 as f64 / self.sum_f1 as f64)
+    }
+
+    /// Fraction of `f2`'s total wall-clock latency during which `f2` was not running on the CPU (e.g., waiting
+    /// for locks or I/O, or preempted), for benchmarks that measure both wall-clock time and CPU time
+    /// (see [`Self::cpu`]); `None` otherwise.
+    pub fn off_cpu_fraction_f2(&self) -> Option<f64> {
+        self.cpu
+            .as_ref()
+            .map(|cpu| 1. - cpu.sum_f2 as f64 / self.sum_f2 as f64)
+    }
+
+    /// Difference between [`Self::off_cpu_fraction_f1`] and [`Self::off_cpu_fraction_f2`]. A positive value
+    /// indicates that a larger share of `f1`'s latency than of `f2`'s latency is spent off the CPU.
+    pub fn diff_off_cpu_fractions_f1_f2(&self) -> Option<f64> {
+        Some(self.off_cpu_fraction_f1()? - self.off_cpu_fraction_f2()?)
+    }
+
+    /// Information about the warm-up of the benchmark, including, for [`crate::WarmupPolicy::Convergence`],
+    /// the trace of the medians used to determine convergence.
+    pub fn warmup(&self) -> &WarmupOutcome {
+        &self.warmup
+    }
+
+    /// The latencies of each pair of executions of `f1` and `f2`, in the order in which they were measured, if
+    /// their retention was requested (see [`crate::BenchDiff::retain_samples`]), or `None` otherwise.
+    ///
+    /// Unlike the statistics, which don't depend on the order of the observations, the sequence reveals drift,
+    /// autocorrelation, or bursts of noise during data collection. The latencies are the ones recorded, i.e.,
+    /// after any timer overhead subtraction and, in batched mode, for whole batches (see [`Self::batch_size`]).
+    pub fn samples(&self) -> Option<impl ExactSizeIterator<Item = PairedSample> + '_> {
+        self.samples.as_ref().map(|samples| samples.iter().copied())
+    }
+
+    /// Heap allocations of `f1` and `f2` during data collection, if they were tracked (see
+    /// [`crate::BenchDiff::track_allocations`]), or `None` otherwise.
+    ///
+    /// Allocations are attributed to the invocations whose latencies are recorded, so [`AllocComparison::n`]
+    /// equals [`Self::n`], and each pair of invocations compared by the latency statistics is also compared by
+    /// [`AllocComparison`].
+    pub fn allocations(&self) -> Option<&AllocComparison> {
+        self.allocations.as_ref()
+    }
+
+    /// Summary descriptive statistics for the latency of an empty closure, measured at the end of warm-up.
+    ///
+    /// It estimates the overhead of reading the clock, which is included in every recorded latency unless it
+    /// is subtracted (see [`Self::timer_overhead_subtracted`]).
+    pub fn timer_overhead(&self) -> SummaryStats {
+        summary_stats(&self.hist_timer_overhead).scaled(self.unit.nanos())
+    }
+
+    /// Value subtracted from each latency before it was recorded
+    /// (see [`crate::BenchDiff::subtract_timer_overhead`]).
+    ///
+    /// It is the median of [`Self::timer_overhead`] if subtraction was enabled; zero otherwise. In batched mode,
+    /// it is subtracted from the total latency of each batch.
+    pub fn timer_overhead_subtracted(&self) -> Duration {
+        Duration::from_nanos(self.timer_overhead_subtracted)
+    }
+
+    /// Warnings about conditions detected during the benchmark that may compromise the reliability of its
+    /// results.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
     /// Summary descriptive statistics for `f1`.
     ///
-    /// Includes sample size, mean, standard deviation, median, several percentiles, min, and max.
+    /// Includes sample size, mean, standard deviation, median, several percentiles, min, and max, in
+    /// [`Self::unit`]. The median, percentiles, min, and max are rounded to the nearest unit.
     pub fn summary_f1(&self) -> SummaryStats {
-        summary_stats(&self.hist_f1)
+        summary_stats(&self.hist_f1).scaled(self.recorded_per_unit())
     }
 
     /// Summary descriptive statistics for `f2`.
     ///
-    /// Includes sample size, mean, standard deviation, median, several percentiles, min, and max.
+    /// Includes sample size, mean, standard deviation, median, several percentiles, min, and max, in
+    /// [`Self::unit`]. The median, percentiles, min, and max are rounded to the nearest unit.
     pub fn summary_f2(&self) -> SummaryStats {
-        summary_stats(&self.hist_f2)
+        summary_stats(&self.hist_f2).scaled(self.recorded_per_unit())
     }
 
     fn sum_diff_f1_f2(&self) -> f64 {
@@ -116,22 +568,46 @@ impl DiffOut {
 
     /// Mean of `f1`'s latencies.
     pub fn mean_f1(&self) -> f64 {
-        self.summary_f1().mean
+        self.in_unit(self.hist_f1.mean())
     }
 
     /// Mean of `f1`'s latencies.
     pub fn mean_f2(&self) -> f64 {
-        self.summary_f2().mean
+        self.in_unit(self.hist_f2.mean())
     }
 
     /// Median of `f1`'s latencies.
     pub fn median_f1(&self) -> f64 {
-        self.summary_f1().median as f64
+        self.in_unit(self.hist_f1.value_at_quantile(0.5) as f64)
     }
 
     /// Median of `f2`'s latencies.
     pub fn median_f2(&self) -> f64 {
-        self.summary_f2().median as f64
+        self.in_unit(self.hist_f2.value_at_quantile(0.5) as f64)
+    }
+
+    /// Mean of `f1`'s latencies, as a [`Duration`].
+    pub fn mean_f1_duration(&self) -> Duration {
+        Duration::from_secs_f64(self.hist_f1.mean() / self.batch_size as f64 / 1e9)
+    }
+
+    /// Mean of `f2`'s latencies, as a [`Duration`].
+    pub fn mean_f2_duration(&self) -> Duration {
+        Duration::from_secs_f64(self.hist_f2.mean() / self.batch_size as f64 / 1e9)
+    }
+
+    /// Median of `f1`'s latencies, as a [`Duration`].
+    pub fn median_f1_duration(&self) -> Duration {
+        Duration::from_secs_f64(
+            self.hist_f1.value_at_quantile(0.5) as f64 / self.batch_size as f64 / 1e9,
+        )
+    }
+
+    /// Median of `f2`'s latencies, as a [`Duration`].
+    pub fn median_f2_duration(&self) -> Duration {
+        Duration::from_secs_f64(
+            self.hist_f2.value_at_quantile(0.5) as f64 / self.batch_size as f64 / 1e9,
+        )
     }
 
     /// Difference between the median of `f1`'s latencies and the median of `f2`'s latencies.
@@ -147,7 +623,7 @@ impl DiffOut {
     #[cfg(feature = "_dev_support")]
     /// Ratio of the minimum of `f1`'s latencies to the minimum of `f2`'s latencies.
     pub fn ratio_mins_f1_f2(&self) -> f64 {
-        self.summary_f1().min as f64 / self.summary_f2().min as f64
+        self.hist_f1.min() as f64 / self.hist_f2.min() as f64
     }
 
     /// Count of paired observations where `f1`'s latency is less than `f2`'s.
@@ -165,51 +641,123 @@ impl DiffOut {
         self.hist_f1_gt_f2.len()
     }
 
+    /// Returns [`BenchDiffError::InsufficientSamples`] if the sample size is less than `required`.
+    fn check_n(&self, required: u64) -> Result<(), BenchDiffError> {
+        match self.n() {
+            n if n < required => Err(BenchDiffError::InsufficientSamples { n, required }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns an error if the sample size is less than `2` or the natural logarithms of the latencies of both
+    /// functions have zero variance, as required by Welch's statistics.
+    fn check_welch(&self) -> Result<(), BenchDiffError> {
+        self.check_n(2)?;
+        if self.try_stdev_ln_f1()? + self.try_stdev_ln_f2()? == 0. {
+            return Err(BenchDiffError::DegenerateVariance);
+        }
+        Ok(())
+    }
+
     /// Mean of the natural logarithms of `f1`'s latencies.
     pub fn mean_ln_f1(&self) -> f64 {
-        sample_mean(self.n(), self.sum_ln_f1).aok()
+        self.try_mean_ln_f1().aok()
+    }
+
+    /// Fallible version of [`Self::mean_ln_f1`]; requires a sample size of at least `1`.
+    pub fn try_mean_ln_f1(&self) -> Result<f64, BenchDiffError> {
+        self.check_n(1)?;
+        Ok(sample_mean(self.n(), self.sum_ln_f1)? - self.recorded_per_unit().ln())
     }
 
     /// Standard deviation of the natural logarithms `f1`'s latecies.
     pub fn stdev_ln_f1(&self) -> f64 {
-        sample_stdev(self.n(), self.sum_ln_f1, self.sum2_ln_f1).aok()
+        self.try_stdev_ln_f1().aok()
+    }
+
+    /// Fallible version of [`Self::stdev_ln_f1`]; requires a sample size of at least `2`.
+    pub fn try_stdev_ln_f1(&self) -> Result<f64, BenchDiffError> {
+        self.check_n(2)?;
+        let sum2 = sum2_non_negative_var(self.n(), self.sum_ln_f1, self.sum2_ln_f1);
+        Ok(sample_stdev(self.n(), self.sum_ln_f1, sum2)?)
     }
 
     /// Mean of the natural logarithms of `f2`'s latencies.
     pub fn mean_ln_f2(&self) -> f64 {
-        sample_mean(self.n(), self.sum_ln_f2).aok()
+        self.try_mean_ln_f2().aok()
+    }
+
+    /// Fallible version of [`Self::mean_ln_f2`]; requires a sample size of at least `1`.
+    pub fn try_mean_ln_f2(&self) -> Result<f64, BenchDiffError> {
+        self.check_n(1)?;
+        Ok(sample_mean(self.n(), self.sum_ln_f2)? - self.recorded_per_unit().ln())
     }
 
     /// Standard deviation of the natural logarithms `f2`'s latecies.
     pub fn stdev_ln_f2(&self) -> f64 {
-        sample_stdev(self.n(), self.sum_ln_f2, self.sum2_ln_f2).aok()
+        self.try_stdev_ln_f2().aok()
+    }
+
+    /// Fallible version of [`Self::stdev_ln_f2`]; requires a sample size of at least `2`.
+    pub fn try_stdev_ln_f2(&self) -> Result<f64, BenchDiffError> {
+        self.check_n(2)?;
+        let sum2 = sum2_non_negative_var(self.n(), self.sum_ln_f2, self.sum2_ln_f2);
+        Ok(sample_stdev(self.n(), self.sum_ln_f2, sum2)?)
     }
 
     /// Mean of the differences between paired latencies of `f1` and `f2`.
     /// Equal to the difference between the mean of `f1`'s latencies and the mean of `f2`'s latencies.
     pub fn mean_diff_f1_f2(&self) -> f64 {
-        sample_mean(self.n(), self.sum_diff_f1_f2()).aok()
+        self.try_mean_diff_f1_f2().aok()
+    }
+
+    /// Fallible version of [`Self::mean_diff_f1_f2`]; requires a sample size of at least `1`.
+    pub fn try_mean_diff_f1_f2(&self) -> Result<f64, BenchDiffError> {
+        self.check_n(1)?;
+        Ok(self.in_unit(sample_mean(self.n(), self.sum_diff_f1_f2())?))
     }
 
     /// Standard deviation of the differences between paired latencies of `f1` and `f2`.
     /// (*Not* the difference between the standard deviation of `f1`'s latencies and
     /// the standard deviation of`f2`'s latencies.)
     pub fn stdev_diff_f1_f2(&self) -> f64 {
-        sample_stdev(self.n(), self.sum_diff_f1_f2(), self.sum2_diff_f1_f2 as f64).aok()
+        self.try_stdev_diff_f1_f2().aok()
+    }
+
+    /// Fallible version of [`Self::stdev_diff_f1_f2`]; requires a sample size of at least `2`.
+    pub fn try_stdev_diff_f1_f2(&self) -> Result<f64, BenchDiffError> {
+        self.check_n(2)?;
+        let sum = self.sum_diff_f1_f2();
+        let sum2 = sum2_non_negative_var(self.n(), sum, self.sum2_diff_f1_f2);
+        Ok(self.in_unit(sample_stdev(self.n(), sum, sum2)?))
     }
 
     /// Mean of the differences between the natural logarithms of paired latencies of `f1` and `f2`.
     /// (Same as the difference between the mean of the natural logarithms of `f1`'s latencies and
     /// the mean of the natural logarithms of`f2`'s latencies.)
     pub fn mean_diff_ln_f1_f2(&self) -> f64 {
-        sample_mean(self.n(), self.sum_diff_ln_f1_f2()).aok()
+        self.try_mean_diff_ln_f1_f2().aok()
+    }
+
+    /// Fallible version of [`Self::mean_diff_ln_f1_f2`]; requires a sample size of at least `1`.
+    pub fn try_mean_diff_ln_f1_f2(&self) -> Result<f64, BenchDiffError> {
+        self.check_n(1)?;
+        Ok(sample_mean(self.n(), self.sum_diff_ln_f1_f2())?)
     }
 
     /// Standard deviation of the differences between the natural logarithms of paired latencies of `f1` and `f2`.
     /// (*Not* the difference between the standard deviation of the natural logarithms of `f1`'s latencies and
     /// the standard deviation of the natural logarithms of`f2`'s latencies.)
     pub fn stdev_diff_ln_f1_f2(&self) -> f64 {
-        sample_stdev(self.n(), self.sum_diff_ln_f1_f2(), self.sum2_diff_ln_f1_f2).aok()
+        self.try_stdev_diff_ln_f1_f2().aok()
+    }
+
+    /// Fallible version of [`Self::stdev_diff_ln_f1_f2`]; requires a sample size of at least `2`.
+    pub fn try_stdev_diff_ln_f1_f2(&self) -> Result<f64, BenchDiffError> {
+        self.check_n(2)?;
+        let sum = self.sum_diff_ln_f1_f2();
+        let sum2 = sum2_non_negative_var(self.n(), sum, self.sum2_diff_ln_f1_f2);
+        Ok(sample_stdev(self.n(), sum, sum2)?)
     }
 
     /// Estimated ratio of the median `f1` latency to the median `f2` latency,
@@ -218,6 +766,11 @@ impl DiffOut {
         self.mean_diff_ln_f1_f2().exp()
     }
 
+    /// Fallible version of [`Self::ratio_medians_f1_f2_from_lns`]; requires a sample size of at least `1`.
+    pub fn try_ratio_medians_f1_f2_from_lns(&self) -> Result<f64, BenchDiffError> {
+        Ok(self.try_mean_diff_ln_f1_f2()?.exp())
+    }
+
     #[cfg(feature = "_dev_support")]
     /// Proportion of paired observations where `f1`s latency is greater than `f2`s.
     pub fn prop_f1_gt_f2(&self) -> f64 {
@@ -278,17 +831,29 @@ impl DiffOut {
     /// Welch's t statistic for
     /// `mean(ln(latency(f1))) - mean(ln(latency(f2)))` (where `ln` is the natural logarithm).
     pub fn welch_ln_t(&self) -> f64 {
-        let moments1 = SampleMoments::new(self.hist_f1.len(), self.sum_ln_f1, self.sum2_ln_f1);
-        let moments2 = SampleMoments::new(self.hist_f2.len(), self.sum_ln_f2, self.sum2_ln_f2);
-        welch_t(&moments1, &moments2).aok()
+        self.try_welch_ln_t().aok()
+    }
+
+    /// Fallible version of [`Self::welch_ln_t`]; requires a sample size of at least `2` and log-latencies that
+    /// are not constant for both functions.
+    pub fn try_welch_ln_t(&self) -> Result<f64, BenchDiffError> {
+        self.check_welch()?;
+        let (moments1, moments2) = self.moments_ln();
+        Ok(welch_t(&moments1, &moments2)?)
     }
 
     /// Degrees of freedom for Welch's t-test for
     /// `mean(ln(latency(f1))) - mean(ln(latency(f2)))` (where `ln` is the natural logarithm).
     pub fn welch_ln_df(&self) -> f64 {
-        let moments1 = SampleMoments::new(self.hist_f1.len(), self.sum_ln_f1, self.sum2_ln_f1);
-        let moments2 = SampleMoments::new(self.hist_f2.len(), self.sum_ln_f2, self.sum2_ln_f2);
-        welch_df(&moments1, &moments2).aok()
+        self.try_welch_ln_df().aok()
+    }
+
+    /// Fallible version of [`Self::welch_ln_df`]; requires a sample size of at least `2` and log-latencies that
+    /// are not constant for both functions.
+    pub fn try_welch_ln_df(&self) -> Result<f64, BenchDiffError> {
+        self.check_welch()?;
+        let (moments1, moments2) = self.moments_ln();
+        Ok(welch_df(&moments1, &moments2)?)
     }
 
     /// Welch confidence interval for
@@ -300,9 +865,15 @@ impl DiffOut {
     ///
     /// This is also the confidence interval for the difference of medians of logarithms under the above assumption.
     pub fn welch_ln_ci(&self, alpha: f64) -> Ci {
-        let moments1 = SampleMoments::new(self.hist_f1.len(), self.sum_ln_f1, self.sum2_ln_f1);
-        let moments2 = SampleMoments::new(self.hist_f2.len(), self.sum_ln_f2, self.sum2_ln_f2);
-        welch_ci(&moments1, &moments2, alpha).aok()
+        self.try_welch_ln_ci(alpha).aok()
+    }
+
+    /// Fallible version of [`Self::welch_ln_ci`]; requires a sample size of at least `2` and log-latencies that
+    /// are not constant for both functions.
+    pub fn try_welch_ln_ci(&self, alpha: f64) -> Result<Ci, BenchDiffError> {
+        self.check_welch()?;
+        let (moments1, moments2) = self.moments_ln();
+        Ok(welch_ci(&moments1, &moments2, alpha)?)
     }
 
     /// Welch confidence interval for
@@ -312,10 +883,34 @@ impl DiffOut {
     /// Assumes that both `latency(f1)` and `latency(f2)` are approximately log-normal.
     /// This assumption is widely supported by performance analysis theory and empirical data.
     pub fn welch_ratio_ci(&self, alpha: f64) -> Ci {
-        let Ci(log_low, log_high) = self.welch_ln_ci(alpha);
+        self.try_welch_ratio_ci(alpha).aok()
+    }
+
+    /// Fallible version of [`Self::welch_ratio_ci`]; requires a sample size of at least `2` and log-latencies
+    /// that are not constant for both functions.
+    pub fn try_welch_ratio_ci(&self, alpha: f64) -> Result<Ci, BenchDiffError> {
+        let Ci(log_low, log_high) = self.try_welch_ln_ci(alpha)?;
         let low = log_low.exp();
         let high = log_high.exp();
-        Ci(low, high)
+        Ok(Ci(low, high))
+    }
+
+    /// Relative half-width of the Welch confidence interval for
+    /// `median(latency(f1)) / median(latency(f2))`,
+    /// with confidence level `(1 - alpha)` (see [`Self::welch_ratio_ci`]).
+    ///
+    /// The interval is symmetric around the estimated ratio in logarithmic scale, so its bounds are the estimated
+    /// ratio divided and multiplied by `1 +` this value. For example, a value of `0.005` corresponds to a
+    /// precision of approximately ±0.5%.
+    pub fn welch_ratio_ci_rel_half_width(&self, alpha: f64) -> f64 {
+        self.try_welch_ratio_ci_rel_half_width(alpha).aok()
+    }
+
+    /// Fallible version of [`Self::welch_ratio_ci_rel_half_width`]; requires a sample size of at least `2` and
+    /// log-latencies that are not constant for both functions.
+    pub fn try_welch_ratio_ci_rel_half_width(&self, alpha: f64) -> Result<f64, BenchDiffError> {
+        let Ci(low, high) = self.try_welch_ratio_ci(alpha)?;
+        Ok((high / low).sqrt() - 1.)
     }
 
     /// Position of `value` with respect to the
@@ -337,9 +932,74 @@ impl DiffOut {
     /// Assumes that both `latency(f1)` and `latency(f2)` are approximately log-normal.
     /// This assumption is widely supported by performance analysis theory and empirical data.
     pub fn welch_ln_test(&self, alt_hyp: AltHyp, alpha: f64) -> HypTestResult {
-        let moments1 = SampleMoments::new(self.hist_f1.len(), self.sum_ln_f1, self.sum2_ln_f1);
-        let moments2 = SampleMoments::new(self.hist_f2.len(), self.sum_ln_f2, self.sum2_ln_f2);
-        welch_test(&moments1, &moments2, alt_hyp, alpha).aok()
+        self.try_welch_ln_test(alt_hyp, alpha).aok()
+    }
+
+    /// Fallible version of [`Self::welch_ln_test`]; requires a sample size of at least `2` and log-latencies
+    /// that are not constant for both functions.
+    pub fn try_welch_ln_test(
+        &self,
+        alt_hyp: AltHyp,
+        alpha: f64,
+    ) -> Result<HypTestResult, BenchDiffError> {
+        self.check_welch()?;
+        let (moments1, moments2) = self.moments_ln();
+        Ok(welch_test(&moments1, &moments2, alt_hyp, alpha)?)
+    }
+
+    /// Sample moments of the natural logarithms of the latencies of `f1` and `f2`.
+    fn moments_ln(&self) -> (SampleMoments, SampleMoments) {
+        let n = self.n();
+        let sum2_ln_f1 = sum2_non_negative_var(n, self.sum_ln_f1, self.sum2_ln_f1);
+        let sum2_ln_f2 = sum2_non_negative_var(n, self.sum_ln_f2, self.sum2_ln_f2);
+        let moments1 = SampleMoments::new(n, self.sum_ln_f1, sum2_ln_f1);
+        let moments2 = SampleMoments::new(n, self.sum_ln_f2, sum2_ln_f2);
+        (moments1, moments2)
+    }
+
+    /// Outliers of the natural logarithms of the latencies of `f1` and `f2` and of their paired differences,
+    /// classified as mild or severe with Tukey fences (see [`Outliers`]).
+    ///
+    /// A few extreme latencies, e.g., caused by preemption or page faults, hardly change the summary statistics
+    /// but inflate the standard deviations of the log-latencies (e.g., [`Self::stdev_ln_f1`]) and widen the
+    /// Welch confidence intervals; see [`Self::robust_welch_ratio_ci`] for an interval that excludes them.
+    ///
+    /// The quartiles and the classification are computed from histograms, so they are subject to their precision
+    /// (see [`crate::BenchDiff::histogram`]). The paired ratios `latency(f1) / latency(f2)` are recorded with
+    /// 3 significant figures.
+    pub fn outliers(&self) -> Outliers {
+        Outliers {
+            f1: ln_outlier_counts(&self.hist_f1, 1.),
+            f2: ln_outlier_counts(&self.hist_f2, 1.),
+            diff_ln_f1_f2: ln_outlier_counts(&self.hist_ratio_f1_f2, RATIO_HIST_SCALE),
+        }
+    }
+
+    /// Robust version of [`Self::welch_ratio_ci`], computed with the severe outliers of the log-latencies of each
+    /// function excluded (see [`Self::outliers`]).
+    ///
+    /// Comparing it with [`Self::welch_ratio_ci`] shows how much the result depends on a few extreme latencies.
+    /// The log-latencies are computed from the latency histograms, so they are subject to their precision (see
+    /// [`crate::BenchDiff::histogram`]).
+    pub fn robust_welch_ratio_ci(&self, alpha: f64) -> Ci {
+        self.try_robust_welch_ratio_ci(alpha).aok()
+    }
+
+    /// Fallible version of [`Self::robust_welch_ratio_ci`]; requires at least `2` observations of each function
+    /// after the exclusion of severe outliers and remaining log-latencies that are not constant for both
+    /// functions.
+    pub fn try_robust_welch_ratio_ci(&self, alpha: f64) -> Result<Ci, BenchDiffError> {
+        let moments1 = moments_ln_excl_severe(&self.hist_f1);
+        let moments2 = moments_ln_excl_severe(&self.hist_f2);
+        let n = moments1.n().min(moments2.n());
+        if n < 2 {
+            return Err(BenchDiffError::InsufficientSamples { n, required: 2 });
+        }
+        if moments1.stdev()? + moments2.stdev()? == 0. {
+            return Err(BenchDiffError::DegenerateVariance);
+        }
+        let Ci(log_low, log_high) = welch_ci(&moments1, &moments2, alpha)?;
+        Ok(Ci(log_low.exp(), log_high.exp()))
     }
 
     #[cfg(feature = "_dev_support")]
@@ -349,7 +1009,7 @@ impl DiffOut {
         let moments = SampleMoments::new(
             self.hist_f1.len(),
             self.sum_diff_f1_f2(),
-            self.sum2_diff_f1_f2 as f64,
+            self.sum2_diff_f1_f2,
         );
         student_1samp_t(&moments, 0.).aok()
     }
@@ -372,9 +1032,10 @@ impl DiffOut {
         let moments = SampleMoments::new(
             self.hist_f1.len(),
             self.sum_diff_f1_f2(),
-            self.sum2_diff_f1_f2 as f64,
+            self.sum2_diff_f1_f2,
         );
-        student_1samp_ci(&moments, alpha).aok()
+        let Ci(low, high) = student_1samp_ci(&moments, alpha).aok();
+        Ci(self.in_unit(low), self.in_unit(high))
     }
 
     #[cfg(feature = "_dev_support")]
@@ -401,7 +1062,7 @@ impl DiffOut {
         let moments = SampleMoments::new(
             self.hist_f1.len(),
             self.sum_diff_f1_f2(),
-            self.sum2_diff_f1_f2 as f64,
+            self.sum2_diff_f1_f2,
         );
         student_1samp_test(&moments, 0., alt_hyp, alpha).aok()
     }
@@ -530,3 +1191,124 @@ impl DiffOut {
         self.rank_sum().z_test(alt_hyp, alpha).aok()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DiffState, OutlierCounts, TargetFn};
+
+    #[test]
+    fn test_unit() {
+        let mut out = DiffOut::new();
+        let mut state = DiffState::new(&mut out);
+        for _ in 0..10 {
+            state.capture_data(2_000, 1_500).expect("latencies are > 0");
+        }
+
+        assert_eq!(out.unit(), LatencyUnit::Nano);
+        assert_eq!(out.median_f1(), 2_000.);
+        assert_eq!(out.best_unit(), LatencyUnit::Micro);
+        assert_eq!(out.median_f1_duration(), Duration::from_micros(2));
+
+        let ratio = out.ratio_medians_f1_f2();
+        out.set_unit(out.best_unit());
+        assert_eq!(out.median_f1(), 2.);
+        assert_eq!(out.mean_diff_f1_f2(), 0.5);
+        assert_eq!(out.summary_f2().median, 2);
+        assert!((out.mean_ln_f1() - 2_f64.ln()).abs() < 1e-12);
+        assert_eq!(out.ratio_medians_f1_f2(), ratio);
+    }
+
+    #[test]
+    fn test_large_diffs() {
+        // Differences of about 10 s in nanoseconds; the sum of their squares exceeds `i64::MAX`.
+        let mut out = DiffOut::new();
+        let mut state = DiffState::new(&mut out);
+        for i in 0..1_000 {
+            let diff = if i % 2 == 0 {
+                9_900_000_000
+            } else {
+                10_100_000_000
+            };
+            state
+                .capture_data(1_000 + diff, 1_000)
+                .expect("latencies are > 0");
+        }
+
+        assert!(out.sum2_diff_f1_f2 > i64::MAX as f64);
+        assert!((out.mean_diff_f1_f2() / 1e10 - 1.).abs() < 1e-9);
+        let stdev = 1e8 * (1_000. / 999_f64).sqrt();
+        assert!((out.stdev_diff_f1_f2() / stdev - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_try_stats() {
+        let mut out = DiffOut::new();
+        assert!(matches!(
+            out.try_mean_ln_f1(),
+            Err(BenchDiffError::InsufficientSamples { n: 0, required: 1 })
+        ));
+
+        let mut state = DiffState::new(&mut out);
+        assert!(matches!(
+            state.capture_data(0, 1),
+            Err(BenchDiffError::ZeroLatency {
+                target: TargetFn::F1
+            })
+        ));
+        assert!(matches!(
+            state.reversed().capture_data(1, 0),
+            Err(BenchDiffError::ZeroLatency {
+                target: TargetFn::F1
+            })
+        ));
+
+        let mut state = DiffState::new(&mut out);
+        for _ in 0..10 {
+            state.capture_data(2_000, 1_500).expect("latencies are > 0");
+        }
+        assert_eq!(out.n(), 10);
+        assert!(matches!(
+            out.try_welch_ln_ci(0.05),
+            Err(BenchDiffError::DegenerateVariance)
+        ));
+        assert!(out.welch_ln_ci(0.05).0.is_nan());
+        assert!(out.try_mean_diff_ln_f1_f2().is_ok());
+    }
+
+    #[test]
+    fn test_outliers() {
+        let mut out = DiffOut::new();
+        let mut state = DiffState::new(&mut out);
+        for i in 0..100 {
+            state
+                .capture_data(1_000 + i % 10, 1_000 + i * 3 % 10)
+                .expect("latencies are > 0");
+        }
+        for _ in 0..2 {
+            state
+                .capture_data(50_000, 1_000)
+                .expect("latencies are > 0");
+        }
+        // Captures a latency of 1,200 for `f2` and 1,000 for `f1`.
+        state
+            .reversed()
+            .capture_data(1_200, 1_000)
+            .expect("latencies are > 0");
+
+        let outliers = out.outliers();
+        let high_severe = |high_severe| OutlierCounts {
+            high_severe,
+            ..Default::default()
+        };
+        assert_eq!(outliers.f1, high_severe(2));
+        assert_eq!(outliers.f2, high_severe(1));
+        assert_eq!(outliers.diff_ln_f1_f2.high_severe, 2);
+        assert_eq!(outliers.diff_ln_f1_f2.low_severe, 1);
+
+        let Ci(low, high) = out.welch_ratio_ci(0.05);
+        let Ci(robust_low, robust_high) = out.robust_welch_ratio_ci(0.05);
+        assert!(robust_low < 1. && robust_high > 1.);
+        assert!(robust_high / robust_low < high / low);
+    }
+}
@@ -0,0 +1,376 @@
+//! Module implementing a pre-flight check of the sources of environment noise that commonly compromise
+//! benchmark results, e.g., CPU frequency scaling and concurrent load.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    fs,
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+/// Fraction of the CPUs above which the 1-minute load average causes a [`EnvironmentWarning::HighLoad`].
+pub const HIGH_LOAD_FRACTION: f64 = 0.5;
+
+/// Fraction of busy CPU time above which a [`EnvironmentWarning::BusyCpus`] is issued.
+pub const BUSY_CPU_FRACTION: f64 = 0.25;
+
+/// Temperature, in degrees Celsius, above which a [`EnvironmentWarning::HighTemperature`] is issued.
+pub const HIGH_TEMPERATURE_CELSIUS: f64 = 80.;
+
+/// Interval over which the busy CPU time is measured from `/proc/stat`.
+const BUSY_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Condition of the environment that may compromise the reliability of benchmark results, detected by
+/// [`environment_check`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum EnvironmentWarning {
+    /// CPUs use a frequency governor other than `performance`, e.g., `powersave` or `ondemand`, so their
+    /// frequency can change during the benchmark.
+    Governor {
+        /// Name of the governor.
+        governor: String,
+        /// Number of CPUs that use the governor.
+        cpu_count: usize,
+    },
+    /// Turbo boost is enabled, so the CPU frequency depends on the temperature and on the load of the other
+    /// cores.
+    TurboBoost,
+    /// The 1-minute load average is above [`HIGH_LOAD_FRACTION`] of the number of CPUs.
+    HighLoad {
+        /// 1-minute load average.
+        load_avg: f64,
+        /// Number of CPUs.
+        cpu_count: usize,
+    },
+    /// The fraction of busy CPU time, measured over a short interval, is above [`BUSY_CPU_FRACTION`], i.e.,
+    /// other processes are competing for the CPUs.
+    BusyCpus {
+        /// Fraction of busy CPU time.
+        busy_fraction: f64,
+    },
+    /// A thermal zone is above [`HIGH_TEMPERATURE_CELSIUS`], so the CPUs may be throttled.
+    HighTemperature {
+        /// Type of the thermal zone, e.g., `x86_pkg_temp`.
+        zone: String,
+        /// Temperature, in degrees Celsius.
+        celsius: f64,
+    },
+}
+
+impl Display for EnvironmentWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Governor {
+                governor,
+                cpu_count,
+            } => write!(
+                f,
+                "{cpu_count} CPU(s) use the '{governor}' frequency governor instead of 'performance'"
+            ),
+            Self::TurboBoost => write!(f, "turbo boost is enabled"),
+            Self::HighLoad {
+                load_avg,
+                cpu_count,
+            } => write!(
+                f,
+                "1-minute load average {load_avg} is high for {cpu_count} CPU(s)"
+            ),
+            Self::BusyCpus { busy_fraction } => {
+                write!(f, "CPUs are {:.0}% busy", busy_fraction * 100.)
+            }
+            Self::HighTemperature { zone, celsius } => {
+                write!(f, "thermal zone '{zone}' is at {celsius} °C")
+            }
+        }
+    }
+}
+
+/// Conditions of the environment read by [`environment_check`], together with the resulting warnings.
+///
+/// Readings that are not available, e.g., on platforms other than Linux or in containers that don't expose
+/// the corresponding files, are `None` or empty.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EnvironmentCheck {
+    /// Frequency governor of each CPU with frequency scaling, as `(cpu, governor)` pairs.
+    pub governors: Vec<(usize, String)>,
+    /// Whether turbo boost is enabled.
+    pub turbo_boost: Option<bool>,
+    /// Number of online CPUs.
+    pub cpu_count: Option<usize>,
+    /// 1-, 5-, and 15-minute load averages.
+    pub load_avg: Option<[f64; 3]>,
+    /// Fraction of busy CPU time, measured over a short interval.
+    pub busy_fraction: Option<f64>,
+    /// Temperature, in degrees Celsius, of each thermal zone, as `(zone type, temperature)` pairs.
+    pub temperatures: Vec<(String, f64)>,
+    /// Warnings about the conditions above.
+    pub warnings: Vec<EnvironmentWarning>,
+}
+
+/// Checks the environment for common sources of benchmark noise and returns the conditions read, together
+/// with warnings about the ones that may compromise the reliability of benchmark results (see
+/// [`EnvironmentWarning`]).
+///
+/// On Linux, reads the CPU frequency governors (`/sys/devices/system/cpu/cpu*/cpufreq`), the turbo boost
+/// setting (`/sys/devices/system/cpu/intel_pstate/no_turbo` or `/sys/devices/system/cpu/cpufreq/boost`), the
+/// load averages (`/proc/loadavg`), the busy CPU time over 200 milliseconds (`/proc/stat`), and the thermal
+/// zones (`/sys/class/thermal`). On other platforms, no conditions are read and there are no warnings.
+///
+/// This check runs automatically before [`crate::bench_diff_with_status`] and can be enabled for any benchmark
+/// with [`crate::BenchDiff::environment_check`], with the result available from
+/// [`crate::DiffOut::environment`].
+pub fn environment_check() -> EnvironmentCheck {
+    if cfg!(target_os = "linux") {
+        environment_check_at(Path::new("/"), BUSY_INTERVAL)
+    } else {
+        EnvironmentCheck::default()
+    }
+}
+
+/// Implementation of [`environment_check`] for a file system rooted at `root`, measuring the busy CPU time over
+/// `busy_interval`.
+fn environment_check_at(root: &Path, busy_interval: Duration) -> EnvironmentCheck {
+    let read = |path: &str| fs::read_to_string(root.join(path)).ok();
+    let cpu_dir = root.join("sys/devices/system/cpu");
+
+    let mut governors = Vec::new();
+    for entry in fs::read_dir(&cpu_dir).into_iter().flatten().flatten() {
+        let name = entry.file_name();
+        let Some(cpu) = name
+            .to_str()
+            .and_then(|name| name.strip_prefix("cpu"))
+            .and_then(|n| n.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        if let Ok(governor) = fs::read_to_string(entry.path().join("cpufreq/scaling_governor")) {
+            governors.push((cpu, governor.trim().to_string()));
+        }
+    }
+    governors.sort();
+
+    let turbo_boost = match read("sys/devices/system/cpu/intel_pstate/no_turbo") {
+        Some(no_turbo) => Some(no_turbo.trim() == "0"),
+        None => read("sys/devices/system/cpu/cpufreq/boost").map(|boost| boost.trim() == "1"),
+    };
+
+    let cpu_count =
+        read("sys/devices/system/cpu/online").and_then(|online| parse_cpu_list(&online));
+
+    let load_avg = read("proc/loadavg").and_then(|loadavg| parse_loadavg(&loadavg));
+
+    let busy_fraction = read("proc/stat")
+        .and_then(|stat| parse_stat(&stat))
+        .and_then(|(busy0, total0)| {
+            thread::sleep(busy_interval);
+            let (busy1, total1) = read("proc/stat").and_then(|stat| parse_stat(&stat))?;
+            let total = total1.checked_sub(total0).filter(|&total| total > 0)?;
+            Some(busy1.saturating_sub(busy0) as f64 / total as f64)
+        });
+
+    let mut temperatures = Vec::new();
+    let thermal_dir = root.join("sys/class/thermal");
+    let mut zones = fs::read_dir(&thermal_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("thermal_zone"))
+        })
+        .collect::<Vec<_>>();
+    zones.sort();
+    for zone in zones {
+        let Some(millidegrees) = fs::read_to_string(zone.join("temp"))
+            .ok()
+            .and_then(|temp| temp.trim().parse::<i64>().ok())
+        else {
+            continue;
+        };
+        let zone_type = fs::read_to_string(zone.join("type"))
+            .map(|zone_type| zone_type.trim().to_string())
+            .unwrap_or_default();
+        temperatures.push((zone_type, millidegrees as f64 / 1000.));
+    }
+
+    let mut check = EnvironmentCheck {
+        governors,
+        turbo_boost,
+        cpu_count,
+        load_avg,
+        busy_fraction,
+        temperatures,
+        warnings: Vec::new(),
+    };
+    check.warnings = warnings(&check);
+    check
+}
+
+/// Computes the warnings about the conditions read in `check`.
+fn warnings(check: &EnvironmentCheck) -> Vec<EnvironmentWarning> {
+    let mut warnings = Vec::new();
+
+    let mut governors = Vec::<(&str, usize)>::new();
+    for (_, governor) in &check.governors {
+        if governor == "performance" {
+            continue;
+        }
+        match governors.iter_mut().find(|(g, _)| g == governor) {
+            Some((_, cpu_count)) => *cpu_count += 1,
+            None => governors.push((governor, 1)),
+        }
+    }
+    warnings.extend(governors.into_iter().map(|(governor, cpu_count)| {
+        EnvironmentWarning::Governor {
+            governor: governor.to_string(),
+            cpu_count,
+        }
+    }));
+
+    if check.turbo_boost == Some(true) {
+        warnings.push(EnvironmentWarning::TurboBoost);
+    }
+
+    if let (Some([load_avg, _, _]), Some(cpu_count)) = (check.load_avg, check.cpu_count)
+        && load_avg > HIGH_LOAD_FRACTION * cpu_count as f64
+    {
+        warnings.push(EnvironmentWarning::HighLoad {
+            load_avg,
+            cpu_count,
+        });
+    }
+
+    if let Some(busy_fraction) = check.busy_fraction
+        && busy_fraction > BUSY_CPU_FRACTION
+    {
+        warnings.push(EnvironmentWarning::BusyCpus { busy_fraction });
+    }
+
+    for (zone, celsius) in &check.temperatures {
+        if *celsius > HIGH_TEMPERATURE_CELSIUS {
+            warnings.push(EnvironmentWarning::HighTemperature {
+                zone: zone.clone(),
+                celsius: *celsius,
+            });
+        }
+    }
+
+    warnings
+}
+
+/// Parses the number of CPUs in a CPU list such as `0-3,6,8-11`.
+fn parse_cpu_list(list: &str) -> Option<usize> {
+    list.trim()
+        .split(',')
+        .map(|range| match range.split_once('-') {
+            Some((first, last)) => {
+                let first = first.parse::<usize>().ok()?;
+                let last = last.parse::<usize>().ok()?;
+                last.checked_sub(first).map(|n| n + 1)
+            }
+            None => range.parse::<usize>().ok().map(|_| 1),
+        })
+        .sum()
+}
+
+/// Parses the 1-, 5-, and 15-minute load averages from the contents of `/proc/loadavg`.
+fn parse_loadavg(loadavg: &str) -> Option<[f64; 3]> {
+    let mut fields = loadavg
+        .split_whitespace()
+        .map(|field| field.parse::<f64>().ok());
+    Some([fields.next()??, fields.next()??, fields.next()??])
+}
+
+/// Parses the busy and total CPU time, in clock ticks, from the aggregate `cpu` line of the contents of
+/// `/proc/stat`. Idle time includes the time waiting for I/O.
+fn parse_stat(stat: &str) -> Option<(u64, u64)> {
+    let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+    let ticks = line
+        .split_whitespace()
+        .skip(1)
+        // Guest time is already included in user time.
+        .take(8)
+        .map(|field| field.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let total = ticks.iter().sum::<u64>();
+    let idle = ticks.get(3)? + ticks.get(4)?;
+    Some((total - idle, total))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{BenchDiff, LatencyUnit};
+    use std::env;
+
+    #[test]
+    fn test_environment_check() {
+        let root = env::temp_dir().join(format!("bench_diff_environment_{}", std::process::id()));
+        let write = |path: &str, contents: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().expect("path has a parent")).expect("dir is created");
+            fs::write(path, contents).expect("file is written");
+        };
+        write("sys/devices/system/cpu/online", "0-3\n");
+        write(
+            "sys/devices/system/cpu/cpu0/cpufreq/scaling_governor",
+            "performance\n",
+        );
+        for cpu in 1..4 {
+            write(
+                &format!("sys/devices/system/cpu/cpu{cpu}/cpufreq/scaling_governor"),
+                "powersave\n",
+            );
+        }
+        write("sys/devices/system/cpu/intel_pstate/no_turbo", "0\n");
+        write("proc/loadavg", "2.50 1.00 0.50 3/512 12345\n");
+        write(
+            "proc/stat",
+            "cpu  100 0 50 800 50 0 0 0 0 0\ncpu0 100 0 50 800 50 0 0 0 0 0\n",
+        );
+        write("sys/class/thermal/thermal_zone0/type", "x86_pkg_temp\n");
+        write("sys/class/thermal/thermal_zone0/temp", "85000\n");
+        write("sys/class/thermal/thermal_zone1/type", "acpitz\n");
+        write("sys/class/thermal/thermal_zone1/temp", "40000\n");
+
+        let check = environment_check_at(&root, Duration::ZERO);
+        fs::remove_dir_all(&root).expect("dir is removed");
+
+        assert_eq!(check.governors.len(), 4);
+        assert_eq!(check.turbo_boost, Some(true));
+        assert_eq!(check.cpu_count, Some(4));
+        assert_eq!(check.load_avg, Some([2.5, 1., 0.5]));
+        // `/proc/stat` doesn't change during the check.
+        assert_eq!(check.busy_fraction, None);
+        assert_eq!(
+            parse_stat("cpu  100 0 50 800 50 0 0 0 0 0"),
+            Some((150, 1000))
+        );
+        assert_eq!(
+            check.warnings,
+            [
+                EnvironmentWarning::Governor {
+                    governor: "powersave".into(),
+                    cpu_count: 3
+                },
+                EnvironmentWarning::TurboBoost,
+                EnvironmentWarning::HighLoad {
+                    load_avg: 2.5,
+                    cpu_count: 4
+                },
+                EnvironmentWarning::HighTemperature {
+                    zone: "x86_pkg_temp".into(),
+                    celsius: 85.
+                },
+            ]
+        );
+        let out = BenchDiff::new(LatencyUnit::Nano, 1_000)
+            .warmup_millis(1)
+            .environment_check(true)
+            .run(|| (), || ());
+        assert!(out.environment().is_some());
+    }
+}
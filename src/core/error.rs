@@ -0,0 +1,100 @@
+//! Module defining the error type returned by the fallible (`try_`) functions and methods of this library.
+
+use basic_stats::core::StatsError;
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io,
+};
+
+/// Identifies one of the two closures compared by a benchmark.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetFn {
+    /// The first target for comparison.
+    F1,
+    /// The second target for comparison.
+    F2,
+}
+
+impl TargetFn {
+    /// Returns the other target.
+    pub(crate) fn other(self) -> Self {
+        match self {
+            Self::F1 => Self::F2,
+            Self::F2 => Self::F1,
+        }
+    }
+}
+
+impl Display for TargetFn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::F1 => write!(f, "f1"),
+            Self::F2 => write!(f, "f2"),
+        }
+    }
+}
+
+/// Error returned by the fallible (`try_`) benchmarking functions (e.g., [`crate::try_bench_diff`]) and
+/// statistics methods of [`crate::DiffOut`] (e.g., [`crate::DiffOut::try_welch_ln_ci`]).
+#[derive(Debug)]
+pub enum BenchDiffError {
+    /// A latency of zero nanoseconds was measured, which can't be used in statistics of logarithms. This
+    /// typically indicates a clock whose resolution is too coarse for the function; consider batched mode
+    /// (see [`crate::bench_diff_batched`]) or another clock (see [`crate::BenchDiff::clock`]).
+    ZeroLatency {
+        /// Function whose latency was zero.
+        target: TargetFn,
+    },
+    /// The sample size is too small for the requested computation.
+    InsufficientSamples {
+        /// Number of observations for each function.
+        n: u64,
+        /// Minimum number of observations for each function required by the computation.
+        required: u64,
+    },
+    /// The natural logarithms of the latencies of both functions have zero variance, so the requested
+    /// statistic is undefined.
+    DegenerateVariance,
+    /// A statistical computation failed for another reason, e.g., an `alpha` outside the open interval `(0, 1)`.
+    Statistics(&'static str),
+    /// Output of the benchmark status failed.
+    Io(io::Error),
+}
+
+impl Display for BenchDiffError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroLatency { target } => write!(f, "{target} latency must be > 0"),
+            Self::InsufficientSamples { n, required } => {
+                write!(f, "sample size {n} is less than the required {required}")
+            }
+            Self::DegenerateVariance => {
+                write!(f, "log-latencies of both functions have zero variance")
+            }
+            Self::Statistics(msg) => write!(f, "statistical computation failed: {msg}"),
+            Self::Io(e) => write!(f, "status output failed: {e}"),
+        }
+    }
+}
+
+impl Error for BenchDiffError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BenchDiffError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<StatsError> for BenchDiffError {
+    fn from(e: StatsError) -> Self {
+        Self::Statistics(e.0)
+    }
+}
@@ -3,8 +3,59 @@
 mod diff_out;
 pub use diff_out::*;
 
+mod error;
+pub use error::*;
+
+mod reporter;
+pub use reporter::*;
+
 mod bench_diff;
 pub use bench_diff::*;
 
+mod bench_diff_builder;
+pub use bench_diff_builder::*;
+
+mod bench_diff_adaptive;
+pub use bench_diff_adaptive::*;
+
+mod bench_diff_sequential;
+pub use bench_diff_sequential::*;
+
+mod bench_diff_budget;
+pub use bench_diff_budget::*;
+
+mod bench_diff_async;
+pub use bench_diff_async::*;
+
+mod bench_diff_cpu;
+pub use bench_diff_cpu::*;
+
+mod bench_multi;
+pub use bench_multi::*;
+
 mod summary_stats;
 pub use summary_stats::*;
+
+mod outliers;
+pub use outliers::*;
+
+mod warmup;
+pub use warmup::*;
+
+mod clock;
+pub use clock::*;
+
+mod background_load;
+pub use background_load::*;
+
+mod thread_settings;
+pub use thread_settings::*;
+
+mod environment;
+pub use environment::*;
+
+mod allocations;
+pub use allocations::*;
+
+mod seeded_rng;
+pub use seeded_rng::*;
@@ -0,0 +1,115 @@
+//! Module implementing the classification of outliers with Tukey fences (see [`crate::DiffOut::outliers`]).
+
+use super::Timing;
+
+/// Multiple of the interquartile range that separates the inner fences from the quartiles.
+const INNER_FENCE_FACTOR: f64 = 1.5;
+
+/// Multiple of the interquartile range that separates the outer fences from the quartiles.
+const OUTER_FENCE_FACTOR: f64 = 3.;
+
+/// Scale applied to the ratios `latency(f1) / latency(f2)` recorded in a histogram (see
+/// [`crate::DiffOut::outliers`]), so that ratios below `1` are recorded with enough precision.
+pub(super) const RATIO_HIST_SCALE: f64 = 1_000_000.;
+
+/// Counts of the outliers of a sample, classified with Tukey fences.
+///
+/// With `q1` and `q3` the first and third quartiles of the sample and `iqr = q3 - q1`, an observation is a *mild*
+/// outlier if it lies between the inner fences `q1 - 1.5 * iqr` or `q3 + 1.5 * iqr` and the corresponding outer
+/// fences `q1 - 3 * iqr` or `q3 + 3 * iqr`, and a *severe* outlier if it lies beyond an outer fence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutlierCounts {
+    /// Number of observations below the lower outer fence.
+    pub low_severe: u64,
+    /// Number of observations between the lower outer and inner fences.
+    pub low_mild: u64,
+    /// Number of observations between the upper inner and outer fences.
+    pub high_mild: u64,
+    /// Number of observations above the upper outer fence.
+    pub high_severe: u64,
+}
+
+impl OutlierCounts {
+    /// Number of mild outliers, low or high.
+    pub fn mild(&self) -> u64 {
+        self.low_mild + self.high_mild
+    }
+
+    /// Number of severe outliers, low or high.
+    pub fn severe(&self) -> u64 {
+        self.low_severe + self.high_severe
+    }
+
+    /// Number of outliers, mild or severe.
+    pub fn total(&self) -> u64 {
+        self.mild() + self.severe()
+    }
+}
+
+/// Outliers of a benchmark, classified with Tukey fences (see [`OutlierCounts`] and
+/// [`crate::DiffOut::outliers`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Outliers {
+    /// Outliers of `ln(latency(f1))`.
+    pub f1: OutlierCounts,
+    /// Outliers of `ln(latency(f2))`.
+    pub f2: OutlierCounts,
+    /// Outliers of the paired differences `ln(latency(f1)) - ln(latency(f2))`.
+    pub diff_ln_f1_f2: OutlierCounts,
+}
+
+/// Tukey fences of the natural logarithms of the values recorded in a histogram.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct LnFences {
+    low_outer: f64,
+    low_inner: f64,
+    high_inner: f64,
+    high_outer: f64,
+}
+
+impl LnFences {
+    /// Computes the fences of `ln(value / scale)` for the values recorded in `hist`, which must not be empty.
+    pub(super) fn new(hist: &Timing, scale: f64) -> Self {
+        let q1 = (hist.value_at_quantile(0.25) as f64 / scale).ln();
+        let q3 = (hist.value_at_quantile(0.75) as f64 / scale).ln();
+        let iqr = q3 - q1;
+        Self {
+            low_outer: q1 - OUTER_FENCE_FACTOR * iqr,
+            low_inner: q1 - INNER_FENCE_FACTOR * iqr,
+            high_inner: q3 + INNER_FENCE_FACTOR * iqr,
+            high_outer: q3 + OUTER_FENCE_FACTOR * iqr,
+        }
+    }
+
+    /// Whether `ln_value` is a severe outlier.
+    pub(super) fn is_severe(&self, ln_value: f64) -> bool {
+        ln_value < self.low_outer || ln_value > self.high_outer
+    }
+
+    /// Adds `count` observations of `ln_value` to `counts` if it is an outlier.
+    fn classify(&self, ln_value: f64, count: u64, counts: &mut OutlierCounts) {
+        if ln_value < self.low_outer {
+            counts.low_severe += count;
+        } else if ln_value < self.low_inner {
+            counts.low_mild += count;
+        } else if ln_value > self.high_outer {
+            counts.high_severe += count;
+        } else if ln_value > self.high_inner {
+            counts.high_mild += count;
+        }
+    }
+}
+
+/// Classifies the outliers of `ln(value / scale)` for the values recorded in `hist`.
+pub(super) fn ln_outlier_counts(hist: &Timing, scale: f64) -> OutlierCounts {
+    let mut counts = OutlierCounts::default();
+    if hist.is_empty() {
+        return counts;
+    }
+    let fences = LnFences::new(hist, scale);
+    for v in hist.iter_recorded() {
+        let ln_value = (v.value_iterated_to() as f64 / scale).ln();
+        fences.classify(ln_value, v.count_at_value(), &mut counts);
+    }
+    counts
+}
@@ -0,0 +1,753 @@
+//! Module defining [`Reporter`], the interface through which benchmarks report their progress, and its built-in
+//! implementations.
+
+use super::{EnvironmentCheck, LatencyUnit, StatusFlow, StopReason};
+use std::{
+    io::{self, IsTerminal, Stderr, Write, stderr},
+    ops::ControlFlow,
+    time::{Duration, Instant},
+};
+
+/// Width, in characters, of the progress bars output by [`TtyReporter`].
+const BAR_WIDTH: usize = 30;
+
+/// Minimum interval between progress outputs when the number of executions is not known in advance.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Minimum interval between redraws of the progress bars of [`TtyReporter`].
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Information about a benchmark, passed to [`Reporter::run_start`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunInfo {
+    /// The unit used to present latency statistics.
+    pub unit: LatencyUnit,
+    /// Number of executions (sample size) for each function or, for benchmarks whose sample size is determined
+    /// during data collection, its maximum; `None` if it is not known in advance
+    /// (e.g., for [`crate::bench_diff_with_budget`]).
+    pub exec_count: Option<usize>,
+}
+
+/// Identifies a half of data collection (see [`Reporter::half_start`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Half {
+    /// Duos start with `f1`, i.e., (`f1`, `f2`), (`f2`, `f1`).
+    Forward,
+    /// Duos start with `f2`, i.e., (`f2`, `f1`), (`f1`, `f2`).
+    Reversed,
+}
+
+impl Half {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Forward => "forward",
+            Self::Reversed => "reversed",
+        }
+    }
+}
+
+/// Receives the events of a benchmark, e.g., to output its progress.
+///
+/// Every benchmarking function family has a `_with_reporter` variant (e.g., [`crate::bench_diff_with_reporter`])
+/// and [`crate::BenchDiff::reporter`] sets the reporter of a configured benchmark. The events of a benchmark
+/// occur in the following order: [`Self::environment_check`] (only if the environment check is enabled),
+/// [`Self::run_start`], [`Self::warmup_progress`] (repeatedly),
+/// [`Self::measurement_start`], [`Self::half_start`] and [`Self::progress`] (repeatedly), and [`Self::complete`].
+///
+/// All methods have default implementations that do nothing, so implementations only need to define the events
+/// they are interested in. [`Self::warmup_progress`] and [`Self::progress`] can cancel the benchmark by returning
+/// [`ControlFlow::Break`] (see [`StatusFlow`]).
+///
+/// The built-in implementations are [`SilentReporter`], [`TtyReporter`], [`PlainReporter`], [`JsonReporter`], and
+/// [`ClosureReporter`], which adapts the status closures of the `_x` functions (e.g., [`crate::bench_diff_x`]).
+pub trait Reporter {
+    /// Invoked once before the start of the benchmark with the result of the environment check, if it is enabled
+    /// (see [`crate::BenchDiff::environment_check`]).
+    fn environment_check(&mut self, _check: &EnvironmentCheck) {}
+
+    /// Invoked once at the start of the benchmark, before warm-up.
+    fn run_start(&mut self, _run: &RunInfo) {}
+
+    /// Invoked every so often during warm-up. The first argument is the warm-up execution iteration, the second
+    /// is the elapsed warm-up time, and the third is the target warm-up time, both in milliseconds.
+    fn warmup_progress(
+        &mut self,
+        _iteration: usize,
+        _elapsed_millis: u64,
+        _warmup_millis: u64,
+    ) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Invoked once at the beginning of data collection, after warm-up.
+    fn measurement_start(&mut self) {}
+
+    /// Invoked at the start of each half of data collection. Benchmarks whose sample size is determined during
+    /// data collection (e.g., [`crate::bench_diff_adaptive`]) collect data in rounds, each with a forward and a
+    /// reversed half. [`crate::bench_multi`], which has no halves, does not invoke this method.
+    fn half_start(&mut self, _half: Half) {}
+
+    /// Invoked after the execution of each duo (or of each row, for [`crate::bench_multi`]). Its argument is the
+    /// current number of executions performed for each function.
+    fn progress(&mut self, _exec_count: usize) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Invoked once at the end of the benchmark with the number of executions performed for each function and the
+    /// reason why data collection stopped.
+    fn complete(&mut self, _exec_count: usize, _stop_reason: StopReason) {}
+
+    /// Takes the first I/O error that occurred while outputting events, if any. Reporters that output events stop
+    /// doing so after an error. The default implementation returns `None`.
+    fn take_io_error(&mut self) -> Option<io::Error> {
+        None
+    }
+}
+
+impl<R: Reporter + ?Sized> Reporter for &mut R {
+    fn environment_check(&mut self, check: &EnvironmentCheck) {
+        (**self).environment_check(check)
+    }
+
+    fn run_start(&mut self, run: &RunInfo) {
+        (**self).run_start(run)
+    }
+
+    fn warmup_progress(
+        &mut self,
+        iteration: usize,
+        elapsed_millis: u64,
+        warmup_millis: u64,
+    ) -> ControlFlow<()> {
+        (**self).warmup_progress(iteration, elapsed_millis, warmup_millis)
+    }
+
+    fn measurement_start(&mut self) {
+        (**self).measurement_start()
+    }
+
+    fn half_start(&mut self, half: Half) {
+        (**self).half_start(half)
+    }
+
+    fn progress(&mut self, exec_count: usize) -> ControlFlow<()> {
+        (**self).progress(exec_count)
+    }
+
+    fn complete(&mut self, exec_count: usize, stop_reason: StopReason) {
+        (**self).complete(exec_count, stop_reason)
+    }
+
+    fn take_io_error(&mut self) -> Option<io::Error> {
+        (**self).take_io_error()
+    }
+}
+
+impl<R: Reporter + ?Sized> Reporter for Box<R> {
+    fn environment_check(&mut self, check: &EnvironmentCheck) {
+        (**self).environment_check(check)
+    }
+
+    fn run_start(&mut self, run: &RunInfo) {
+        (**self).run_start(run)
+    }
+
+    fn warmup_progress(
+        &mut self,
+        iteration: usize,
+        elapsed_millis: u64,
+        warmup_millis: u64,
+    ) -> ControlFlow<()> {
+        (**self).warmup_progress(iteration, elapsed_millis, warmup_millis)
+    }
+
+    fn measurement_start(&mut self) {
+        (**self).measurement_start()
+    }
+
+    fn half_start(&mut self, half: Half) {
+        (**self).half_start(half)
+    }
+
+    fn progress(&mut self, exec_count: usize) -> ControlFlow<()> {
+        (**self).progress(exec_count)
+    }
+
+    fn complete(&mut self, exec_count: usize, stop_reason: StopReason) {
+        (**self).complete(exec_count, stop_reason)
+    }
+
+    fn take_io_error(&mut self) -> Option<io::Error> {
+        (**self).take_io_error()
+    }
+}
+
+/// Returns the reporter used by the `_with_status` functions (e.g., [`crate::bench_diff_with_status`]): a
+/// [`TtyReporter`] if `stderr` is a terminal, or a [`PlainReporter`] that outputs to `stderr` otherwise, e.g.,
+/// in CI logs.
+pub fn stderr_reporter() -> Box<dyn Reporter> {
+    if stderr().is_terminal() {
+        Box::new(TtyReporter::new())
+    } else {
+        Box::new(PlainReporter::new())
+    }
+}
+
+/// Reporter that ignores all events.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SilentReporter;
+
+impl Reporter for SilentReporter {}
+
+/// Reporter that forwards events to status closures like the ones taken by the `_x` functions (e.g.,
+/// [`crate::bench_diff_x`]): [`Reporter::warmup_progress`] to `warmup_status`, [`Reporter::measurement_start`]
+/// to `pre_exec`, and [`Reporter::progress`] to `exec_status`. The other events are ignored.
+///
+/// `warmup_status` and `exec_status` can return `()` or a [`ControlFlow`] (see [`StatusFlow`]).
+pub struct ClosureReporter<W, P, E> {
+    warmup_status: W,
+    pre_exec: Option<P>,
+    exec_status: E,
+}
+
+impl<W, P, E> ClosureReporter<W, P, E> {
+    /// Creates a new instance that forwards events to the given closures.
+    pub fn new(warmup_status: W, pre_exec: P, exec_status: E) -> Self {
+        Self {
+            warmup_status,
+            pre_exec: Some(pre_exec),
+            exec_status,
+        }
+    }
+}
+
+impl<W, P, E, SW, SE> Reporter for ClosureReporter<W, P, E>
+where
+    W: FnMut(usize, u64, u64) -> SW,
+    P: FnOnce(),
+    E: FnMut(usize) -> SE,
+    SW: StatusFlow,
+    SE: StatusFlow,
+{
+    fn warmup_progress(
+        &mut self,
+        iteration: usize,
+        elapsed_millis: u64,
+        warmup_millis: u64,
+    ) -> ControlFlow<()> {
+        (self.warmup_status)(iteration, elapsed_millis, warmup_millis).into_flow()
+    }
+
+    fn measurement_start(&mut self) {
+        if let Some(pre_exec) = self.pre_exec.take() {
+            pre_exec();
+        }
+    }
+
+    fn progress(&mut self, exec_count: usize) -> ControlFlow<()> {
+        (self.exec_status)(exec_count).into_flow()
+    }
+}
+
+/// Percentage of `total` represented by `done`, capped at 100.
+fn percent(done: u64, total: u64) -> u64 {
+    match done.saturating_mul(100).checked_div(total) {
+        Some(pct) => pct.min(100),
+        None => 100,
+    }
+}
+
+/// Tracks the progress of a benchmark to limit how often it is output.
+#[derive(Debug, Default)]
+struct Throttle {
+    last_percent: Option<u64>,
+    last_output: Option<Instant>,
+}
+
+impl Throttle {
+    /// Returns `Some(pct)` if the percentage `pct` of `total` represented by `done`, rounded down to a multiple of
+    /// `step`, differs from the last one returned, and `None` otherwise.
+    fn percent(&mut self, done: u64, total: u64, step: u64) -> Option<u64> {
+        let pct = percent(done, total) / step * step;
+        if self.last_percent == Some(pct) {
+            return None;
+        }
+        self.last_percent = Some(pct);
+        Some(pct)
+    }
+
+    /// Returns `true` if at least `interval` has elapsed since the last time this method returned `true`.
+    fn interval(&mut self, interval: Duration) -> bool {
+        let now = Instant::now();
+        match self.last_output {
+            Some(last) if now.duration_since(last) < interval => false,
+            _ => {
+                self.last_output = Some(now);
+                true
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Formats `s` as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Writer that records the first I/O error and ignores all output after it.
+#[derive(Debug)]
+struct EventWriter<W: Write> {
+    writer: W,
+    error: Option<io::Error>,
+    failed: bool,
+}
+
+impl<W: Write> EventWriter<W> {
+    fn new(writer: W) -> Self {
+        Self {
+            writer,
+            error: None,
+            failed: false,
+        }
+    }
+
+    /// Writes `s` and flushes the writer, unless an error occurred previously.
+    fn write(&mut self, s: &str) {
+        if self.failed {
+            return;
+        }
+        let result = self
+            .writer
+            .write_all(s.as_bytes())
+            .and_then(|_| self.writer.flush());
+        if let Err(e) = result {
+            self.error = Some(e);
+            self.failed = true;
+        }
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+}
+
+/// Reporter that outputs progress bars to `stderr`, redrawn in place, for interactive use in a terminal.
+///
+/// Progress is redrawn at most every 100 milliseconds and each benchmark ends with a line break.
+#[derive(Debug)]
+pub struct TtyReporter {
+    out: EventWriter<Stderr>,
+    exec_count: Option<usize>,
+    half: Option<Half>,
+    throttle: Throttle,
+    line_len: usize,
+}
+
+impl TtyReporter {
+    /// Creates a new instance.
+    pub fn new() -> Self {
+        Self {
+            out: EventWriter::new(stderr()),
+            exec_count: None,
+            half: None,
+            throttle: Throttle::default(),
+            line_len: 0,
+        }
+    }
+
+    /// Replaces the current line with `line`.
+    fn redraw(&mut self, line: String) {
+        let len = line.chars().count();
+        let padding = " ".repeat(self.line_len.saturating_sub(len));
+        self.line_len = len;
+        self.out.write(&format!("\r{line}{padding}"));
+    }
+
+    /// Ends the current line, if any.
+    fn end_line(&mut self) {
+        if self.line_len > 0 {
+            self.out.write("\n");
+            self.line_len = 0;
+        }
+    }
+
+    fn bar(done: u64, total: u64) -> String {
+        let filled = (percent(done, total) as usize * BAR_WIDTH) / 100;
+        format!("[{}{}]", "#".repeat(filled), " ".repeat(BAR_WIDTH - filled))
+    }
+
+    fn draw_progress(&mut self, exec_count: usize) {
+        let half = self.half.map(|half| half.name()).unwrap_or("measuring");
+        let line = match self.exec_count {
+            Some(total) => format!(
+                "Executing ({half}) {} {}% ({exec_count} of {total})",
+                Self::bar(exec_count as u64, total as u64),
+                percent(exec_count as u64, total as u64)
+            ),
+            None => format!("Executing ({half}) {exec_count} executions"),
+        };
+        self.redraw(line);
+    }
+}
+
+impl Default for TtyReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reporter for TtyReporter {
+    fn environment_check(&mut self, check: &EnvironmentCheck) {
+        self.end_line();
+        for warning in &check.warnings {
+            self.out
+                .write(&format!("Environment warning: {warning}.\n"));
+        }
+    }
+
+    fn run_start(&mut self, run: &RunInfo) {
+        self.exec_count = run.exec_count;
+        self.half = None;
+        self.throttle.reset();
+    }
+
+    fn warmup_progress(
+        &mut self,
+        _iteration: usize,
+        elapsed_millis: u64,
+        warmup_millis: u64,
+    ) -> ControlFlow<()> {
+        if self.throttle.interval(REDRAW_INTERVAL) {
+            let line = format!(
+                "Warming up {} {elapsed_millis} of {warmup_millis} millis",
+                Self::bar(elapsed_millis, warmup_millis)
+            );
+            self.redraw(line);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn measurement_start(&mut self) {
+        self.end_line();
+        self.throttle.reset();
+    }
+
+    fn half_start(&mut self, half: Half) {
+        self.half = Some(half);
+    }
+
+    fn progress(&mut self, exec_count: usize) -> ControlFlow<()> {
+        if self.throttle.interval(REDRAW_INTERVAL) {
+            self.draw_progress(exec_count);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn complete(&mut self, exec_count: usize, stop_reason: StopReason) {
+        self.draw_progress(exec_count);
+        if stop_reason != StopReason::ExecCount {
+            self.out.write(&format!(" ({stop_reason:?})"));
+        }
+        self.end_line();
+    }
+
+    fn take_io_error(&mut self) -> Option<io::Error> {
+        self.out.take_error()
+    }
+}
+
+/// Reporter that outputs one line of plain text per event, suitable for logs that are not terminals, e.g., CI
+/// logs. By default, it outputs to `stderr`.
+///
+/// To limit the size of the output, warm-up progress is output at every 10% of the target warm-up time and
+/// execution progress at every 1% of the number of executions or, if the number of executions is not known in
+/// advance, at most once per second.
+#[derive(Debug)]
+pub struct PlainReporter<W: Write = Stderr> {
+    out: EventWriter<W>,
+    exec_count: Option<usize>,
+    throttle: Throttle,
+}
+
+impl PlainReporter {
+    /// Creates a new instance that outputs to `stderr`.
+    pub fn new() -> Self {
+        Self::with_writer(stderr())
+    }
+}
+
+impl Default for PlainReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> PlainReporter<W> {
+    /// Creates a new instance that outputs to `writer`.
+    pub fn with_writer(writer: W) -> Self {
+        Self {
+            out: EventWriter::new(writer),
+            exec_count: None,
+            throttle: Throttle::default(),
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.out.writer
+    }
+}
+
+impl<W: Write> Reporter for PlainReporter<W> {
+    fn environment_check(&mut self, check: &EnvironmentCheck) {
+        for warning in &check.warnings {
+            self.out
+                .write(&format!("Environment warning: {warning}.\n"));
+        }
+    }
+
+    fn run_start(&mut self, run: &RunInfo) {
+        self.exec_count = run.exec_count;
+        self.throttle.reset();
+        let line = match run.exec_count {
+            Some(exec_count) => format!("Run started: {exec_count} executions per function.\n"),
+            None => "Run started.\n".to_string(),
+        };
+        self.out.write(&line);
+    }
+
+    fn warmup_progress(
+        &mut self,
+        _iteration: usize,
+        elapsed_millis: u64,
+        warmup_millis: u64,
+    ) -> ControlFlow<()> {
+        if let Some(pct) = self.throttle.percent(elapsed_millis, warmup_millis, 10) {
+            self.out.write(&format!(
+                "Warm-up: {pct}% ({elapsed_millis} of {warmup_millis} millis).\n"
+            ));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn measurement_start(&mut self) {
+        self.throttle.reset();
+        self.out.write("Measurement started.\n");
+    }
+
+    fn half_start(&mut self, half: Half) {
+        self.out
+            .write(&format!("Measurement: {} half started.\n", half.name()));
+    }
+
+    fn progress(&mut self, exec_count: usize) -> ControlFlow<()> {
+        match self.exec_count {
+            Some(total) => {
+                if let Some(pct) = self.throttle.percent(exec_count as u64, total as u64, 1) {
+                    self.out.write(&format!(
+                        "Measurement: {pct}% ({exec_count} of {total} executions).\n"
+                    ));
+                }
+            }
+            None => {
+                if self.throttle.interval(PROGRESS_INTERVAL) {
+                    self.out
+                        .write(&format!("Measurement: {exec_count} executions.\n"));
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn complete(&mut self, exec_count: usize, stop_reason: StopReason) {
+        self.out.write(&format!(
+            "Run complete: {exec_count} executions per function ({stop_reason:?}).\n"
+        ));
+    }
+
+    fn take_io_error(&mut self) -> Option<io::Error> {
+        self.out.take_error()
+    }
+}
+
+/// Reporter that outputs one JSON object per line (JSON Lines) for each event, for consumption by other tools.
+///
+/// Each object has an `"event"` field with the name of the [`Reporter`] method that received the event, e.g.,
+/// `"run_start"`, and a field for each argument of the method, e.g.:
+///
+/// ```text
+/// {"event":"environment_check","warnings":["turbo boost is enabled"]}
+/// {"event":"run_start","unit":"ns","exec_count":10000}
+/// {"event":"warmup_progress","iteration":1,"elapsed_millis":0,"warmup_millis":3000}
+/// {"event":"measurement_start"}
+/// {"event":"half_start","half":"forward"}
+/// {"event":"progress","exec_count":100}
+/// {"event":"complete","exec_count":10000,"stop_reason":"ExecCount"}
+/// ```
+///
+/// `exec_count` in `run_start` is `null` if the number of executions is not known in advance. Progress events are
+/// throttled like the lines of [`PlainReporter`], except that warm-up progress is output at every 1% of the target
+/// warm-up time.
+#[derive(Debug)]
+pub struct JsonReporter<W: Write> {
+    out: EventWriter<W>,
+    exec_count: Option<usize>,
+    throttle: Throttle,
+}
+
+impl<W: Write> JsonReporter<W> {
+    /// Creates a new instance that outputs to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            out: EventWriter::new(writer),
+            exec_count: None,
+            throttle: Throttle::default(),
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.out.writer
+    }
+}
+
+impl<W: Write> Reporter for JsonReporter<W> {
+    fn environment_check(&mut self, check: &EnvironmentCheck) {
+        let warnings = check
+            .warnings
+            .iter()
+            .map(|warning| json_string(&warning.to_string()))
+            .collect::<Vec<_>>()
+            .join(",");
+        self.out.write(&format!(
+            "{{\"event\":\"environment_check\",\"warnings\":[{warnings}]}}\n"
+        ));
+    }
+
+    fn run_start(&mut self, run: &RunInfo) {
+        self.exec_count = run.exec_count;
+        self.throttle.reset();
+        let exec_count = match run.exec_count {
+            Some(exec_count) => exec_count.to_string(),
+            None => "null".to_string(),
+        };
+        self.out.write(&format!(
+            "{{\"event\":\"run_start\",\"unit\":\"{}\",\"exec_count\":{exec_count}}}\n",
+            run.unit.symbol()
+        ));
+    }
+
+    fn warmup_progress(
+        &mut self,
+        iteration: usize,
+        elapsed_millis: u64,
+        warmup_millis: u64,
+    ) -> ControlFlow<()> {
+        if self
+            .throttle
+            .percent(elapsed_millis, warmup_millis, 1)
+            .is_some()
+        {
+            self.out.write(&format!(
+                "{{\"event\":\"warmup_progress\",\"iteration\":{iteration},\
+                 \"elapsed_millis\":{elapsed_millis},\"warmup_millis\":{warmup_millis}}}\n"
+            ));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn measurement_start(&mut self) {
+        self.throttle.reset();
+        self.out.write("{\"event\":\"measurement_start\"}\n");
+    }
+
+    fn half_start(&mut self, half: Half) {
+        self.out.write(&format!(
+            "{{\"event\":\"half_start\",\"half\":\"{}\"}}\n",
+            half.name()
+        ));
+    }
+
+    fn progress(&mut self, exec_count: usize) -> ControlFlow<()> {
+        let output = match self.exec_count {
+            Some(total) => self
+                .throttle
+                .percent(exec_count as u64, total as u64, 1)
+                .is_some(),
+            None => self.throttle.interval(PROGRESS_INTERVAL),
+        };
+        if output {
+            self.out.write(&format!(
+                "{{\"event\":\"progress\",\"exec_count\":{exec_count}}}\n"
+            ));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn complete(&mut self, exec_count: usize, stop_reason: StopReason) {
+        self.out.write(&format!(
+            "{{\"event\":\"complete\",\"exec_count\":{exec_count},\"stop_reason\":\"{stop_reason:?}\"}}\n"
+        ));
+    }
+
+    fn take_io_error(&mut self) -> Option<io::Error> {
+        self.out.take_error()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::BenchDiff;
+
+    #[test]
+    fn test_json_reporter_events() {
+        let mut reporter = JsonReporter::new(Vec::new());
+        let out = BenchDiff::new(LatencyUnit::Nano, 1_000)
+            .warmup_millis(1)
+            .reporter(&mut reporter)
+            .run(|| (), || ());
+        assert_eq!(out.n(), 1_000);
+
+        let output = String::from_utf8(reporter.into_inner()).expect("output is UTF-8");
+        let events = output
+            .lines()
+            .filter(|line| !line.contains("progress"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                r#"{"event":"run_start","unit":"ns","exec_count":1000}"#,
+                r#"{"event":"measurement_start"}"#,
+                r#"{"event":"half_start","half":"forward"}"#,
+                r#"{"event":"half_start","half":"reversed"}"#,
+                r#"{"event":"complete","exec_count":1000,"stop_reason":"ExecCount"}"#,
+            ]
+        );
+        // Execution progress is output at every 1% of the executions, from 0% to 100%.
+        let progress = output
+            .lines()
+            .filter(|line| line.starts_with(r#"{"event":"progress""#))
+            .collect::<Vec<_>>();
+        assert_eq!(progress.len(), 101);
+        assert_eq!(
+            progress.last(),
+            Some(&r#"{"event":"progress","exec_count":1000}"#)
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Module defining a small seedable pseudo-random number generator used to make benchmark inputs and
+//! schedules reproducible.
+
+/// Seedable pseudo-random number generator (SplitMix64).
+///
+/// It is *not* cryptographically secure. It is intended for the reproducible generation of benchmark inputs
+/// and execution schedules: two instances created with the same seed produce the same sequence of values.
+#[derive(Clone, Debug)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    /// Creates a new instance from `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next pseudo-random `u64`, uniformly distributed over all `u64` values.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns the next pseudo-random `f64`, uniformly distributed in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SeededRng;
+
+    #[test]
+    fn test_seeded_rng() {
+        let mut rng1 = SeededRng::new(42);
+        let mut rng2 = SeededRng::new(42);
+        let mut rng3 = SeededRng::new(43);
+
+        let seq1 = (0..100).map(|_| rng1.next_u64()).collect::<Vec<_>>();
+        let seq2 = (0..100).map(|_| rng2.next_u64()).collect::<Vec<_>>();
+        let seq3 = (0..100).map(|_| rng3.next_u64()).collect::<Vec<_>>();
+        assert_eq!(seq1, seq2, "same seed must produce same sequence");
+        assert_ne!(
+            seq1, seq3,
+            "different seeds must produce different sequences"
+        );
+
+        for _ in 0..1000 {
+            let x = rng1.next_f64();
+            assert!((0. ..1.).contains(&x), "next_f64 must be in [0, 1): {x}");
+        }
+    }
+}
@@ -52,6 +52,28 @@ impl SummaryStats {
             max: hist.max(),
         }
     }
+
+    /// Returns a copy of `self` with all values with a time dimension divided by `divisor`. Integer values are
+    /// rounded to the nearest integer.
+    pub(crate) fn scaled(self, divisor: f64) -> Self {
+        let scale = |v: u64| (v as f64 / divisor).round() as u64;
+        Self {
+            count: self.count,
+            mean: self.mean / divisor,
+            stdev: self.stdev / divisor,
+            min: scale(self.min),
+            p1: scale(self.p1),
+            p5: scale(self.p5),
+            p10: scale(self.p10),
+            p25: scale(self.p25),
+            median: scale(self.median),
+            p75: scale(self.p75),
+            p90: scale(self.p90),
+            p95: scale(self.p95),
+            p99: scale(self.p99),
+            max: scale(self.max),
+        }
+    }
 }
 
 /// Computes a [`SummaryStats`] from a [`Histogram<u64>`].
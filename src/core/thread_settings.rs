@@ -0,0 +1,311 @@
+//! Module defining the CPU affinity and scheduling settings applied to the measuring thread of a benchmark.
+
+use super::Warning;
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+};
+
+/// Scheduling priority of the measuring thread of a benchmark (see [`crate::BenchDiff::priority`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SchedPriority {
+    /// The scheduling of the thread is not changed. This is the default.
+    #[default]
+    Unchanged,
+    /// The thread runs under the normal time-sharing policy with the given nice value, from `-20` (highest
+    /// priority) to `19` (lowest priority). Raising the priority above the current one usually requires the
+    /// `CAP_SYS_NICE` capability or an appropriate `RLIMIT_NICE` resource limit. For the same reason, a value
+    /// above the current one (a lower priority) may not be restored at the end of the benchmark.
+    Nice(i32),
+    /// The thread runs under the real-time `SCHED_FIFO` policy with the given priority, from `1` to `99`, so it
+    /// is only preempted by threads with a higher real-time priority. This requires the `CAP_SYS_NICE`
+    /// capability or an appropriate `RLIMIT_RTPRIO` resource limit.
+    ///
+    /// A real-time thread that never blocks can starve other threads on its core, so this is best combined with
+    /// pinning (see [`crate::BenchDiff::pin_to_core`]) on a machine with other cores available.
+    Fifo(i32),
+}
+
+impl Display for SchedPriority {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unchanged => write!(f, "unchanged"),
+            Self::Nice(nice) => write!(f, "nice {nice}"),
+            Self::Fifo(priority) => write!(f, "SCHED_FIFO priority {priority}"),
+        }
+    }
+}
+
+/// CPU affinity and scheduling settings of the measuring thread of a benchmark (see
+/// [`crate::DiffOut::thread_settings`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ThreadSettings {
+    /// Core to which the thread is pinned, if any.
+    pub core: Option<usize>,
+    /// Scheduling priority of the thread.
+    pub priority: SchedPriority,
+}
+
+/// Original settings of the calling thread, restored when this value is dropped.
+pub(super) struct ThreadSettingsGuard {
+    #[cfg(target_os = "linux")]
+    affinity: Option<libc::cpu_set_t>,
+    #[cfg(target_os = "linux")]
+    scheduling: Option<linux::Scheduling>,
+}
+
+impl ThreadSettingsGuard {
+    /// Applies `requested` to the calling thread. Returns a guard that restores the original settings when
+    /// dropped, the settings actually applied, and a warning for each setting that could not be applied.
+    pub(super) fn apply(requested: ThreadSettings) -> (Self, ThreadSettings, Vec<Warning>) {
+        let mut guard = Self {
+            #[cfg(target_os = "linux")]
+            affinity: None,
+            #[cfg(target_os = "linux")]
+            scheduling: None,
+        };
+        let mut applied = ThreadSettings::default();
+        let mut warnings = Vec::new();
+
+        if let Some(core) = requested.core {
+            match guard.pin(core) {
+                Ok(()) => applied.core = Some(core),
+                Err(e) => warnings.push(Warning::AffinityNotApplied {
+                    core,
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        if requested.priority != SchedPriority::Unchanged {
+            match guard.set_priority(requested.priority) {
+                Ok(()) => applied.priority = requested.priority,
+                Err(e) => warnings.push(Warning::PriorityNotApplied {
+                    priority: requested.priority,
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        (guard, applied, warnings)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn pin(&mut self, core: usize) -> io::Result<()> {
+        let set = linux::single_core(core)?;
+        self.affinity = Some(linux::get_affinity()?);
+        linux::set_affinity(&set)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn set_priority(&mut self, priority: SchedPriority) -> io::Result<()> {
+        let original = linux::Scheduling::get()?;
+        if let Err(e) = linux::Scheduling::from_priority(priority).set() {
+            // The policy may have been changed even though the priority could not be, so the original settings
+            // are restored right away rather than left in effect for the benchmark.
+            let _ = original.set();
+            return Err(e);
+        }
+        self.scheduling = Some(original);
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn pin(&mut self, _core: usize) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn set_priority(&mut self, _priority: SchedPriority) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "not supported on this platform")
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for ThreadSettingsGuard {
+    fn drop(&mut self) {
+        // Restoration can only fail if the original settings became invalid, e.g., a core went offline, in which
+        // case there is nothing better to do than keep the current settings.
+        if let Some(scheduling) = self.scheduling.take() {
+            let _ = scheduling.set();
+        }
+        if let Some(affinity) = self.affinity.take() {
+            let _ = linux::set_affinity(&affinity);
+        }
+    }
+}
+
+/// Thin wrappers of the Linux system calls that get and set the settings of the calling thread.
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::SchedPriority;
+    use std::{io, mem};
+
+    /// Converts the result of a system call into an [`io::Result`].
+    fn check(res: libc::c_int) -> io::Result<libc::c_int> {
+        if res == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(res)
+        }
+    }
+
+    pub(super) fn get_affinity() -> io::Result<libc::cpu_set_t> {
+        // SAFETY: `cpu_set_t` is a plain bit set, for which all zeros is a valid value.
+        let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+        // SAFETY: `set` is a valid `cpu_set_t` of the given size; pid 0 is the calling thread.
+        check(unsafe { libc::sched_getaffinity(0, size_of::<libc::cpu_set_t>(), &mut set) })?;
+        Ok(set)
+    }
+
+    pub(super) fn set_affinity(set: &libc::cpu_set_t) -> io::Result<()> {
+        // SAFETY: `set` is a valid `cpu_set_t` of the given size; pid 0 is the calling thread.
+        check(unsafe { libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), set) })?;
+        Ok(())
+    }
+
+    /// Returns the set containing only `core`.
+    pub(super) fn single_core(core: usize) -> io::Result<libc::cpu_set_t> {
+        if core >= libc::CPU_SETSIZE as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("core must be < {}", libc::CPU_SETSIZE),
+            ));
+        }
+        // SAFETY: all zeros is a valid `cpu_set_t` and `core` is within its bounds.
+        unsafe {
+            let mut set: libc::cpu_set_t = mem::zeroed();
+            libc::CPU_SET(core, &mut set);
+            Ok(set)
+        }
+    }
+
+    /// Scheduling policy and priority of a thread.
+    pub(super) struct Scheduling {
+        policy: libc::c_int,
+        rt_priority: libc::c_int,
+        nice: libc::c_int,
+    }
+
+    impl Scheduling {
+        /// Settings corresponding to `priority`, which must not be [`SchedPriority::Unchanged`].
+        pub(super) fn from_priority(priority: SchedPriority) -> Self {
+            match priority {
+                SchedPriority::Fifo(rt_priority) => Self {
+                    policy: libc::SCHED_FIFO,
+                    rt_priority,
+                    nice: 0,
+                },
+                SchedPriority::Nice(nice) => Self {
+                    policy: libc::SCHED_OTHER,
+                    rt_priority: 0,
+                    nice,
+                },
+                SchedPriority::Unchanged => unreachable!("priority must not be Unchanged"),
+            }
+        }
+
+        /// Settings of the calling thread.
+        pub(super) fn get() -> io::Result<Self> {
+            // SAFETY: pid 0 is the calling thread.
+            let policy = check(unsafe { libc::sched_getscheduler(0) })?;
+            let mut param = libc::sched_param { sched_priority: 0 };
+            // SAFETY: `param` is a valid `sched_param`; pid 0 is the calling thread.
+            check(unsafe { libc::sched_getparam(0, &mut param) })?;
+            // `getpriority` can legitimately return -1, so errors are detected through `errno`.
+            // SAFETY: `__errno_location` returns a valid pointer to the thread's `errno`; with
+            // `PRIO_PROCESS`, the thread ID selects the calling thread only.
+            let nice = unsafe {
+                *libc::__errno_location() = 0;
+                libc::getpriority(libc::PRIO_PROCESS, libc::gettid() as libc::id_t)
+            };
+            if nice == -1 {
+                let e = io::Error::last_os_error();
+                if e.raw_os_error() != Some(0) {
+                    return Err(e);
+                }
+            }
+            Ok(Self {
+                policy,
+                rt_priority: param.sched_priority,
+                nice,
+            })
+        }
+
+        /// Applies `self` to the calling thread.
+        pub(super) fn set(&self) -> io::Result<()> {
+            let param = libc::sched_param {
+                sched_priority: self.rt_priority,
+            };
+            // SAFETY: `param` is a valid `sched_param`; pid 0 is the calling thread.
+            check(unsafe { libc::sched_setscheduler(0, self.policy, &param) })?;
+            if self.policy != libc::SCHED_FIFO && self.policy != libc::SCHED_RR {
+                // SAFETY: with `PRIO_PROCESS`, the thread ID selects the calling thread only.
+                check(unsafe {
+                    libc::setpriority(libc::PRIO_PROCESS, libc::gettid() as libc::id_t, self.nice)
+                })?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use super::*;
+    use crate::{BenchDiff, DiffOut, LatencyUnit};
+
+    fn allowed_cores() -> Vec<usize> {
+        let set = linux::get_affinity().expect("affinity is readable");
+        (0..libc::CPU_SETSIZE as usize)
+            // SAFETY: `core` is within the bounds of `set`.
+            .filter(|&core| unsafe { libc::CPU_ISSET(core, &set) })
+            .collect()
+    }
+
+    /// Warnings other than [`Warning::NearTimerOverhead`], which is expected for empty closures.
+    fn thread_warnings(out: &DiffOut) -> Vec<&Warning> {
+        out.warnings()
+            .iter()
+            .filter(|w| !matches!(w, Warning::NearTimerOverhead { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn test_thread_settings() {
+        let original = allowed_cores();
+        let core = original[0];
+
+        let out = BenchDiff::new(LatencyUnit::Nano, 1_000)
+            .warmup_millis(1)
+            .pin_to_core(core)
+            .run(|| (), || ());
+        assert_eq!(out.thread_settings().core, Some(core));
+        assert!(thread_warnings(&out).is_empty(), "{:?}", out.warnings());
+        assert_eq!(allowed_cores(), original);
+
+        let out = BenchDiff::new(LatencyUnit::Nano, 1_000)
+            .warmup_millis(1)
+            .pin_to_core(libc::CPU_SETSIZE as usize)
+            .priority(SchedPriority::Fifo(1))
+            .run(|| (), || ());
+        assert_eq!(out.thread_settings().core, None);
+        let warnings = thread_warnings(&out);
+        assert!(matches!(warnings[0], Warning::AffinityNotApplied { .. }));
+        // Whether `SCHED_FIFO` can be used depends on the privileges of the process.
+        match out.thread_settings().priority {
+            SchedPriority::Fifo(1) => assert_eq!(warnings.len(), 1),
+            priority => {
+                assert_eq!(priority, SchedPriority::Unchanged);
+                assert!(matches!(warnings[1], Warning::PriorityNotApplied { .. }));
+            }
+        }
+        // SAFETY: pid 0 is the calling thread.
+        assert_eq!(unsafe { libc::sched_getscheduler(0) }, libc::SCHED_OTHER);
+    }
+}
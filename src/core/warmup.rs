@@ -0,0 +1,267 @@
+//! Module defining the warm-up policies of the benchmarking functions and the information recorded about
+//! warm-up.
+
+use super::LatencyUnit;
+use std::{collections::VecDeque, time::Duration};
+
+/// Parameters of a warm-up that stops once the latencies of both functions are stable (see
+/// [`WarmupPolicy::Convergence`]).
+///
+/// Warm-up is checked for convergence after each batch of warm-up executions. At each check, the *rolling*
+/// median of the natural logarithms of the latencies of each function over its last `window` executions is
+/// compared with the rolling median over the `window` executions that preceded them. Warm-up stops at the first
+/// check where these medians differ by at most `tolerance` for both functions, provided that `min_millis` have
+/// elapsed, or when `max_millis` have elapsed, whichever comes first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WarmupConvergence {
+    /// Number of executions of each function over which the rolling median is computed.
+    pub window: usize,
+    /// Maximum absolute difference between the median log-latencies of consecutive windows for the latencies to
+    /// be considered stable. For example, `0.01` corresponds to a change of about 1% in the median.
+    pub tolerance: f64,
+    /// Minimum warm-up duration, in milliseconds.
+    pub min_millis: u64,
+    /// Maximum warm-up duration, in milliseconds.
+    pub max_millis: u64,
+}
+
+impl Default for WarmupConvergence {
+    /// A window of 200 executions, a tolerance of `0.01`, a minimum duration of 100 ms, and a maximum duration
+    /// of 10,000 ms.
+    fn default() -> Self {
+        Self {
+            window: 200,
+            tolerance: 0.01,
+            min_millis: 100,
+            max_millis: 10_000,
+        }
+    }
+}
+
+impl WarmupConvergence {
+    /// Checks that the parameters of `self` are valid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is `0`, `tolerance` is not positive, or `min_millis` is greater than `max_millis`.
+    pub fn validate(&self) {
+        assert!(self.window > 0, "window must be > 0");
+        assert!(self.tolerance > 0., "tolerance must be > 0");
+        assert!(
+            self.min_millis <= self.max_millis,
+            "min_millis must be <= max_millis"
+        );
+    }
+}
+
+/// Strategy used to determine when warm-up ends (see [`crate::BenchDiff::warmup_policy`]).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WarmupPolicy {
+    /// Warm-up lasts a fixed number of milliseconds (see [`crate::BenchDiff::warmup_millis`]). This is the
+    /// default.
+    Fixed,
+    /// Warm-up lasts until the latencies of both functions are stable, within bounds.
+    Convergence(WarmupConvergence),
+}
+
+/// Rolling medians of the log-latencies at a convergence check during warm-up (see [`WarmupConvergence`]).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WarmupTraceEntry {
+    /// Number of warm-up executions of each function at the check.
+    pub exec_count: u64,
+    /// Elapsed warm-up time at the check, in milliseconds.
+    pub elapsed_millis: u64,
+    /// Median of the natural logarithms of `f1`'s latencies in the last window, in nanoseconds.
+    pub median_ln_f1: f64,
+    /// Median of the natural logarithms of `f2`'s latencies in the last window, in nanoseconds.
+    pub median_ln_f2: f64,
+}
+
+/// Information about the warm-up of a benchmark, available from [`crate::DiffOut::warmup`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct WarmupOutcome {
+    /// Policy used.
+    pub policy: WarmupPolicy,
+    /// Number of warm-up executions of each function.
+    pub exec_count: u64,
+    /// Duration of the warm-up.
+    pub duration: Duration,
+    /// Whether warm-up stopped because the latencies were stable; always `false` for
+    /// [`WarmupPolicy::Fixed`].
+    pub converged: bool,
+    /// Rolling medians at each convergence check with a full window of observations; empty for
+    /// [`WarmupPolicy::Fixed`].
+    pub trace: Vec<WarmupTraceEntry>,
+}
+
+impl WarmupOutcome {
+    /// Creates an instance for a benchmark that has not been warmed-up.
+    pub(super) fn new() -> Self {
+        Self {
+            policy: WarmupPolicy::Fixed,
+            exec_count: 0,
+            duration: Duration::ZERO,
+            converged: false,
+            trace: Vec::new(),
+        }
+    }
+}
+
+/// Settings for the warm-up of a benchmark, including the calibration of the timer overhead at its end.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct WarmupPlan {
+    /// Warm-up policy.
+    pub(super) policy: WarmupPolicy,
+    /// Maximum duration of the warm-up, in milliseconds; for [`WarmupPolicy::Fixed`], its duration.
+    pub(super) millis: u64,
+    /// Whether the median timer overhead is subtracted from the latencies collected after warm-up.
+    pub(super) subtract_timer_overhead: bool,
+}
+
+impl WarmupPlan {
+    /// Returns the plan for `policy`, where `fixed_millis` is the duration for [`WarmupPolicy::Fixed`], further
+    /// limited to `max_millis` if it is not `None`.
+    pub(super) fn new(
+        policy: WarmupPolicy,
+        fixed_millis: u64,
+        max_millis: Option<u64>,
+        subtract_timer_overhead: bool,
+    ) -> Self {
+        let millis = match policy {
+            WarmupPolicy::Fixed => fixed_millis,
+            WarmupPolicy::Convergence(convergence) => convergence.max_millis,
+        };
+        Self {
+            policy,
+            millis: millis.min(max_millis.unwrap_or(u64::MAX)),
+            subtract_timer_overhead,
+        }
+    }
+}
+
+/// Tracks the rolling medians of the warm-up observations to determine convergence (see [`WarmupConvergence`]).
+pub(super) struct WarmupMonitor {
+    convergence: WarmupConvergence,
+    /// The last `2 * window` log-latencies of `f1`, oldest first.
+    ln_f1: VecDeque<f64>,
+    /// The last `2 * window` log-latencies of `f2`, oldest first.
+    ln_f2: VecDeque<f64>,
+    /// Buffer reused to compute medians.
+    scratch: Vec<f64>,
+    trace: Vec<WarmupTraceEntry>,
+}
+
+impl WarmupMonitor {
+    pub(super) fn new(convergence: WarmupConvergence) -> Self {
+        let capacity = 2 * convergence.window;
+        Self {
+            convergence,
+            ln_f1: VecDeque::with_capacity(capacity),
+            ln_f2: VecDeque::with_capacity(capacity),
+            scratch: Vec::with_capacity(convergence.window),
+            trace: Vec::new(),
+        }
+    }
+
+    /// Adds the latencies of a duo to the observations. For each pair, the first component is an `f1` latency
+    /// and the second component is an `f2` latency.
+    pub(super) fn observe(&mut self, pairs: &[(Duration, Duration)]) {
+        let capacity = 2 * self.convergence.window;
+        for &(latency1, latency2) in pairs {
+            for (values, latency) in [(&mut self.ln_f1, latency1), (&mut self.ln_f2, latency2)] {
+                if values.len() == capacity {
+                    values.pop_front();
+                }
+                values.push_back((LatencyUnit::Nano.latency_as_u64(latency).max(1) as f64).ln());
+            }
+        }
+    }
+
+    /// Records the rolling medians of the last window, if there are enough observations, and returns whether the
+    /// latencies are stable, i.e., whether there are two full windows of observations and the medians of the
+    /// last window are within the tolerance of the medians of the window before it.
+    pub(super) fn is_stable(&mut self, exec_count: u64, elapsed_millis: u64) -> bool {
+        let window = self.convergence.window;
+        let len = self.ln_f1.len();
+        if len < window {
+            return false;
+        }
+
+        let mut median_of = |values: &VecDeque<f64>, range: std::ops::Range<usize>| {
+            self.scratch.clear();
+            self.scratch.extend(values.range(range));
+            median(&mut self.scratch)
+        };
+        let entry = WarmupTraceEntry {
+            exec_count,
+            elapsed_millis,
+            median_ln_f1: median_of(&self.ln_f1, len - window..len),
+            median_ln_f2: median_of(&self.ln_f2, len - window..len),
+        };
+        self.trace.push(entry);
+
+        if len < 2 * window {
+            return false;
+        }
+        let prev_median_ln_f1 = median_of(&self.ln_f1, 0..window);
+        let prev_median_ln_f2 = median_of(&self.ln_f2, 0..window);
+        (entry.median_ln_f1 - prev_median_ln_f1).abs() <= self.convergence.tolerance
+            && (entry.median_ln_f2 - prev_median_ln_f2).abs() <= self.convergence.tolerance
+    }
+
+    pub(super) fn into_trace(self) -> Vec<WarmupTraceEntry> {
+        self.trace
+    }
+}
+
+/// Median of `values`, which must not be empty. Reorders `values`.
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.
+    } else {
+        values[mid]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_warmup_monitor() {
+        let convergence = WarmupConvergence {
+            window: 4,
+            tolerance: 0.01,
+            min_millis: 0,
+            max_millis: 1_000,
+        };
+        let mut monitor = WarmupMonitor::new(convergence);
+        let pair = |l1: u64, l2: u64| (Duration::from_nanos(l1), Duration::from_nanos(l2));
+
+        monitor.observe(&[pair(200, 100), pair(200, 100)]);
+        assert!(!monitor.is_stable(2, 1), "window is incomplete");
+
+        monitor.observe(&[pair(200, 100), pair(200, 100)]);
+        assert!(!monitor.is_stable(4, 2), "first window has no predecessor");
+
+        monitor.observe(&[pair(150, 100); 4]);
+        assert!(!monitor.is_stable(8, 3), "f1 is not stable");
+
+        monitor.observe(&[pair(151, 100); 2]);
+        assert!(
+            !monitor.is_stable(10, 4),
+            "previous window still has two old observations"
+        );
+
+        monitor.observe(&[pair(151, 100); 2]);
+        assert!(monitor.is_stable(12, 5), "both functions are stable");
+
+        let trace = monitor.into_trace();
+        assert_eq!(trace.len(), 4);
+        assert_eq!(trace[0].median_ln_f1, 200_f64.ln());
+        assert_eq!(trace[2].median_ln_f1, (150_f64.ln() + 151_f64.ln()) / 2.);
+        assert_eq!(trace[3].exec_count, 12);
+    }
+}
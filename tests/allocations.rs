@@ -0,0 +1,51 @@
+//! Tests of allocation tracking, which require [`CountingAllocator`] to be installed as the global allocator of the
+//! test binary.
+
+use bench_diff::{BenchDiff, CountingAllocator, LatencyUnit, counting_allocator_installed};
+use std::{alloc::System, hint::black_box};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator::new(System);
+
+#[test]
+fn test_track_allocations() {
+    assert!(counting_allocator_installed());
+
+    let out = BenchDiff::new(LatencyUnit::Nano, 1_000)
+        .warmup_millis(1)
+        .run(|| (), || ());
+    assert!(out.allocations().is_none());
+
+    let out = BenchDiff::new(LatencyUnit::Nano, 1_000)
+        .warmup_millis(1)
+        .seed(42)
+        .track_allocations(true)
+        .run(
+            || {
+                black_box(Vec::<u8>::with_capacity(100));
+            },
+            || {
+                let a = black_box(Vec::<u8>::with_capacity(60));
+                let b = black_box(Vec::<u8>::with_capacity(40));
+                drop(a);
+                drop(b);
+            },
+        );
+    let allocs = out.allocations().expect("allocations are tracked");
+    assert_eq!(allocs.n(), out.n());
+
+    let f1 = allocs.f1;
+    assert_eq!(f1.allocations, f1.n);
+    assert_eq!(f1.bytes, 100 * f1.n);
+    assert_eq!(f1.max_peak_bytes, 100);
+    assert_eq!(f1.mean_peak_bytes(), 100.);
+
+    let f2 = allocs.f2;
+    assert_eq!(f2.mean_allocations(), 2.);
+    assert_eq!(f2.mean_bytes(), 100.);
+    assert_eq!(f2.mean_peak_bytes(), 100.);
+
+    assert_eq!(allocs.count_f1_lt_f2, allocs.n());
+    assert_eq!(allocs.mean_diff_allocations_f1_f2(), -1.);
+    assert_eq!(allocs.mean_diff_bytes_f1_f2(), 0.);
+}